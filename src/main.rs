@@ -4,6 +4,7 @@ mod spotify;
 fn main() {
     let system = ui::App::new();
 
-    dotenv::from_filename("tokens.env").expect("Failed to load tokens.env");
+    // CLIENT_ID can also come from the environment, so tokens.env is optional.
+    dotenv::from_filename("tokens.env").ok();
     system.render_loop();
 }
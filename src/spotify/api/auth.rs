@@ -0,0 +1,357 @@
+use std::env;
+use std::path::PathBuf;
+use std::net::{TcpListener, TcpStream};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use rand::prelude::*;
+use rand::distributions::Alphanumeric;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+const AUTH_URL: &str = "https://accounts.spotify.com/authorize";
+const TOKEN_URL: &str = "https://accounts.spotify.com/api/token";
+
+const REDIRECT_URI: &str = "http://localhost:8888/callback";
+const CALLBACK_ADDRESS: &str = "127.0.0.1:8888";
+
+/// How long the callback server waits for the browser before giving up and freeing the port.
+const CALLBACK_TIMEOUT: Duration = Duration::from_secs(300);
+/// How often the callback server checks for a connection or a cancelled flow.
+const CALLBACK_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The refresh token is kept in the system keyring, like saved passwords, instead of `token.ron`.
+const KEYRING_SERVICE: &str = "imguify";
+const KEYRING_REFRESH_TOKEN: &str = "web-api-refresh-token";
+
+/// Scopes requested when authorizing imguify.
+pub const SCOPES: &[&str] = &[
+    "playlist-read-private",
+    "playlist-read-collaborative",
+    "playlist-modify-public",
+    "playlist-modify-private",
+    "user-library-read",
+    "user-library-modify",
+    "user-follow-read",
+    "user-follow-modify",
+    "user-read-recently-played",
+    "user-top-read",
+    "user-read-playback-state",
+    "user-modify-playback-state"
+];
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct APIToken {
+    access_token: String,
+    // Older versions saved it to the file, those get moved over to the keyring on load.
+    #[serde(default, skip_serializing)]
+    refresh_token: String,
+
+    expires_at: u64,
+    scopes: Vec<String>
+}
+
+impl APIToken {
+    /// Loads the saved token, refreshing it if it already expired.
+    /// Returns None if there's no usable token and the user has to authorize again.
+    pub fn load() -> Option<APIToken> {
+        let mut token: APIToken = serde_any::from_file(token_path()?).ok()?;
+
+        if !token.has_scopes() {
+            return None;
+        }
+
+        if token.refresh_token.is_empty() {
+            token.refresh_token = refresh_token_keyring().get_password().ok()?;
+        }
+        else if let Err(error) = token.save() {
+            println!("Error moving API refresh token to keyring: {}", error.to_string());
+        }
+
+        if token.is_expired() {
+            if let Err(error) = token.refresh() {
+                println!("Failed to refresh API token: {}", error.to_string());
+                return None;
+            }
+        }
+
+        Some(token)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = token_path().context("Failed to get system cache path")?;
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        refresh_token_keyring().set_password(&self.refresh_token).map_err(|e| anyhow::Error::msg(e.to_string()))?;
+
+        serde_any::to_file(path, self).map_err(|e| anyhow::Error::msg(e.to_string()))
+    }
+
+    pub fn refresh(&mut self) -> Result<()> {
+        let client_id = client_id()?;
+        let response: TokenResponse = reqwest::blocking::Client::new()
+            .post(TOKEN_URL)
+            .form(&[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", self.refresh_token.as_str()),
+                ("client_id", client_id.as_str())
+            ])
+            .send()?
+            .error_for_status()?
+            .json()?
+        ;
+
+        self.update(response);
+        self.save()
+    }
+
+    /// Tokens are considered expired a minute early, so requests don't race the expiry.
+    pub fn is_expired(&self) -> bool {
+        unix_time() + 60 >= self.expires_at
+    }
+
+    fn has_scopes(&self) -> bool {
+        SCOPES.iter().all(|scope| self.scopes.iter().any(|s| s == scope))
+    }
+
+    fn update(&mut self, response: TokenResponse) {
+        self.access_token = response.access_token;
+        self.expires_at = unix_time() + response.expires_in;
+
+        // Spotify may or may not rotate the refresh token.
+        if let Some(refresh_token) = response.refresh_token {
+            self.refresh_token = refresh_token;
+        }
+
+        if let Some(scope) = response.scope {
+            self.scopes = scope.split_whitespace().map(|s| s.to_string()).collect();
+        }
+    }
+
+    pub fn access_token(&self) -> &String {
+        &self.access_token
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+    refresh_token: Option<String>,
+    scope: Option<String>
+}
+
+/// An in-progress Authorization Code with PKCE flow.
+/// The redirect is caught by a small HTTP server listening on localhost.
+pub struct AuthFlow {
+    auth_url: String,
+    result_rx: Receiver<Result<APIToken>>,
+    cancelled: Arc<AtomicBool>
+}
+
+impl AuthFlow {
+    pub fn start() -> Result<AuthFlow> {
+        let client_id = client_id()?;
+
+        let state = random_string(16);
+        let verifier = random_string(64);
+        let challenge = base64::encode_config(Sha256::digest(verifier.as_bytes()), base64::URL_SAFE_NO_PAD);
+
+        let auth_url = url::Url::parse_with_params(
+            AUTH_URL,
+            &[
+                ("client_id", client_id.as_str()),
+                ("response_type", "code"),
+                ("redirect_uri", REDIRECT_URI),
+                ("code_challenge_method", "S256"),
+                ("code_challenge", challenge.as_str()),
+                ("state", state.as_str()),
+                ("scope", SCOPES.join(" ").as_str())
+            ]
+        )?.to_string();
+
+        let listener = bind_callback()?;
+        let (result_tx, result_rx) = mpsc::channel();
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let thread_cancelled = cancelled.clone();
+
+        std::thread::spawn(move || {
+            let result = wait_for_code(listener, &state, &thread_cancelled).and_then(|code| exchange_code(&client_id, &code, &verifier));
+
+            if let Ok(token) = result.as_ref() {
+                if let Err(error) = token.save() {
+                    println!("Error saving API token: {}", error.to_string());
+                }
+            }
+
+            let _ = result_tx.send(result);
+        });
+
+        if let Err(error) = webbrowser::open(&auth_url) {
+            println!("Couldn't open the browser: {}", error.to_string());
+        }
+
+        Ok(AuthFlow { auth_url, result_rx, cancelled })
+    }
+
+    pub fn auth_url(&self) -> &String {
+        &self.auth_url
+    }
+
+    /// Returns the result of the flow once the browser went through the redirect.
+    pub fn poll(&self) -> Option<Result<APIToken>> {
+        self.result_rx.try_recv().ok()
+    }
+}
+
+impl Drop for AuthFlow {
+    /// Stops the callback server of an abandoned flow, so the port is free for the next one.
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+/// A cancelled flow lets go of the port within a poll interval, so binding is retried for a bit.
+fn bind_callback() -> Result<TcpListener> {
+    let deadline = Instant::now() + CALLBACK_POLL_INTERVAL * 5;
+
+    loop {
+        match TcpListener::bind(CALLBACK_ADDRESS) {
+            Ok(listener) => return Ok(listener),
+            Err(_) if Instant::now() < deadline => std::thread::sleep(CALLBACK_POLL_INTERVAL),
+            Err(error) => return Err(error).context("Failed to start the authorization callback server")
+        }
+    }
+}
+
+/// Waits for the next connection, giving up once the flow is cancelled or timed out.
+fn accept(listener: &TcpListener, deadline: Instant, cancelled: &AtomicBool) -> Result<TcpStream> {
+    loop {
+        if cancelled.load(Ordering::Relaxed) {
+            return Err(anyhow::Error::msg("Authorization was cancelled"));
+        }
+
+        if Instant::now() >= deadline {
+            return Err(anyhow::Error::msg("Timed out waiting for authorization"));
+        }
+
+        match listener.accept() {
+            Ok((stream, _)) => {
+                stream.set_nonblocking(false)?;
+                stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+
+                return Ok(stream);
+            }
+            Err(error) if error.kind() == ErrorKind::WouldBlock => std::thread::sleep(CALLBACK_POLL_INTERVAL),
+            Err(error) => return Err(error.into())
+        }
+    }
+}
+
+fn wait_for_code(listener: TcpListener, state: &str, cancelled: &AtomicBool) -> Result<String> {
+    listener.set_nonblocking(true)?;
+
+    let deadline = Instant::now() + CALLBACK_TIMEOUT;
+
+    loop {
+        let mut stream = accept(&listener, deadline, cancelled)?;
+        let mut request_line = String::new();
+
+        BufReader::new(&stream).read_line(&mut request_line)?;
+
+        let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+        let url = url::Url::parse(&format!("http://localhost{}", path))?;
+
+        // Browsers like to ask for a favicon too.
+        if url.path() != "/callback" {
+            stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n")?;
+            continue;
+        }
+
+        let query_value = |key: &str| url.query_pairs().find(|(k, _)| k == key).map(|(_, v)| v.to_string());
+
+        let result = {
+            if query_value("state").as_deref() != Some(state) {
+                Err(anyhow::Error::msg("Authorization state mismatch"))
+            }
+            else if let Some(error) = query_value("error") {
+                Err(anyhow::Error::msg(format!("Authorization denied: {}", error)))
+            }
+            else {
+                query_value("code").context("Authorization callback didn't include a code")
+            }
+        };
+
+        let (status, body) = match result.as_ref() {
+            Ok(_) => ("200 OK", "<html><body>imguify is now authorized, you can close this window.</body></html>".to_string()),
+            Err(error) => ("400 Bad Request", format!("<html><body>Authorization failed: {}</body></html>", error))
+        };
+
+        write!(stream, "HTTP/1.1 {}\r\nContent-Type: text/html\r\nContent-Length: {}\r\n\r\n{}", status, body.len(), body)?;
+
+        return result;
+    }
+}
+
+fn exchange_code(client_id: &str, code: &str, verifier: &str) -> Result<APIToken> {
+    let response: TokenResponse = reqwest::blocking::Client::new()
+        .post(TOKEN_URL)
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", REDIRECT_URI),
+            ("client_id", client_id),
+            ("code_verifier", verifier)
+        ])
+        .send()?
+        .error_for_status()?
+        .json()?
+    ;
+
+    let mut token = APIToken {
+        access_token: String::new(),
+        refresh_token: String::new(),
+
+        expires_at: 0,
+        scopes: SCOPES.iter().map(|s| s.to_string()).collect()
+    };
+
+    token.update(response);
+    Ok(token)
+}
+
+/// Whether a client id is available, which is needed to use the Web API at all.
+pub fn is_configured() -> bool {
+    client_id().is_ok()
+}
+
+fn client_id() -> Result<String> {
+    env::var("CLIENT_ID").context("CLIENT_ID isn't set in tokens.env or the environment")
+}
+
+fn token_path() -> Option<PathBuf> {
+    let mut path = dirs::cache_dir()?;
+    path.push("imguify/data/token.ron");
+
+    Some(path)
+}
+
+fn refresh_token_keyring() -> keyring::Keyring<'static> {
+    keyring::Keyring::new(KEYRING_SERVICE, KEYRING_REFRESH_TOKEN)
+}
+
+fn random_string(len: usize) -> String {
+    thread_rng().sample_iter(&Alphanumeric).take(len).map(char::from).collect()
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
@@ -1,72 +1,568 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use rusqlite::{params, Connection, Transaction};
 
 use rspotify::model::album::FullAlbum;
-use rspotify::model::track::FullTrack;
+use rspotify::model::audio::AudioFeatures as APIAudioFeatures;
+use rspotify::model::track::{FullTrack, SimplifiedTrack};
+use rspotify::model::artist::FullArtist;
+use rspotify::model::category::Category;
+use rspotify::model::playlist::SimplifiedPlaylist;
 
+use super::provider::{AlbumSummary, FeaturedPlaylists, PlaylistItem};
 
-#[derive(Default, Deserialize, Serialize)]
+/// Browse pages change during the day, so they're only kept for a while and never written to disk.
+const BROWSE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// How often pending writes are stored in the database.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Bumped whenever the tables change, older databases are migrated up to it on startup.
+const SCHEMA_VERSION: i32 = 1;
+
+/// Metadata kept around between sessions, in an SQLite database next to the other cached data.
+/// Browse pages are the exception, they only live in memory.
 pub struct APICacheHandler {
-    album_cache: HashMap<String, AlbumInfo>,
-    track_cache: HashMap<String, TrackInfo>
+    // None if the database couldn't be opened, everything is only kept until closing then.
+    db: Option<Connection>,
+    // A second connection that writes are stored through, so lookups on `db` don't wait for them.
+    writer: Option<Arc<Mutex<Connection>>>,
+    // Writes are kept here and stored together by `flush`, instead of hitting the disk on every insert.
+    pending: PendingWrites,
+    // What a background flush is storing right now, still looked up here until it's in the database.
+    flushing: Arc<PendingWrites>,
+
+    new_releases: Option<Expiring<Vec<AlbumSummary>>>,
+    featured_playlists: Option<Expiring<FeaturedPlaylists>>,
+    categories: Option<Expiring<Vec<Category>>>,
+    category_playlists: HashMap<String, Expiring<Vec<SimplifiedPlaylist>>>
 }
 
 impl APICacheHandler {
     pub fn init() -> APICacheHandler {
-        let mut cache_path = dirs::cache_dir().expect("Couldn't get cache dir");
-        cache_path.push("imguify/data/cache.ron");
+        APICacheHandler::open(dirs::cache_dir().map(|path| path.join("imguify/data")))
+    }
 
-        if let Ok(deserialized) = serde_any::from_file(cache_path) {
-            deserialized
+    /// Opens the cache kept in `data_path`, or one that's only kept in memory if there's no path.
+    pub(super) fn open(data_path: Option<PathBuf>) -> APICacheHandler {
+        let (db, writer) = match data_path.as_ref().context("Couldn't get the system cache path").and_then(|path| open_database(path)) {
+            Ok((db, writer)) => (Some(db), Some(Arc::new(Mutex::new(writer)))),
+            Err(error) => {
+                println!("Couldn't open the metadata cache, nothing will be kept after closing: {}", error.to_string());
+                (None, None)
+            }
+        };
+
+        let mut cache_handler = APICacheHandler {
+            db,
+            writer,
+            pending: PendingWrites::default(),
+            flushing: Arc::new(PendingWrites::default()),
+
+            new_releases: None,
+            featured_playlists: None,
+            categories: None,
+            category_playlists: HashMap::new()
+        };
+
+        if let Some(data_path) = data_path.as_ref() {
+            if let Err(error) = cache_handler.migrate_legacy_cache(data_path) {
+                println!("Couldn't migrate the old metadata cache: {}", error.to_string());
+            }
         }
-        else {
-            APICacheHandler::default()
+
+        cache_handler
+    }
+
+    /// Flushes pending writes every few seconds, until the cache is dropped.
+    pub fn start_flushing(cache_handler: &Arc<Mutex<APICacheHandler>>) {
+        let cache_handler = Arc::downgrade(cache_handler);
+
+        std::thread::spawn(move || {
+            loop {
+                std::thread::sleep(FLUSH_INTERVAL);
+
+                match cache_handler.upgrade() {
+                    Some(cache_handler) => APICacheHandler::flush_in_background(&cache_handler),
+                    None => break
+                }
+            }
+        });
+    }
+
+    /// Swaps the pending writes out under the lock and stores them after letting go of it,
+    /// so lookups and new writes don't wait on the disk.
+    fn flush_in_background(cache_handler: &Mutex<APICacheHandler>) {
+        let mut lock = match cache_handler.lock() {
+            Ok(lock) => lock,
+            Err(_) => return
+        };
+
+        let writer = match lock.writer.clone() {
+            Some(writer) if !lock.pending.is_empty() => writer,
+            _ => return
+        };
+
+        // Taken before letting go of the cache, so a flush that starts after this one can't store its newer writes first.
+        let db = match writer.lock() {
+            Ok(db) => db,
+            Err(_) => return
+        };
+
+        let batch = Arc::new(std::mem::take(&mut lock.pending));
+        lock.flushing = batch.clone();
+        drop(lock);
+
+        let result = store(&db, &batch);
+        drop(db);
+
+        if let Ok(mut lock) = cache_handler.lock() {
+            lock.flushing = Arc::new(PendingWrites::default());
+
+            if let Err(error) = result {
+                println!("Couldn't write to the metadata cache: {}", error.to_string());
+                lock.pending.restore(&batch);
+            }
         }
     }
 
-    pub fn try_get_album(&self, id: &str) -> Option<AlbumInfo> {
-        self.album_cache.get(id).cloned()
+    /// Stores every pending write in a single transaction, waiting for a background flush that's still going.
+    /// If that fails the writes stay pending, to be tried again on the next flush.
+    pub fn flush(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+
+        let writer = match self.writer.as_ref() {
+            Some(writer) => writer,
+            None => return
+        };
+
+        let result = match writer.lock() {
+            Ok(db) => store(&db, &self.pending),
+            Err(_) => Err(anyhow::Error::msg("The metadata cache writer panicked"))
+        };
+
+        match result {
+            Ok(()) => self.pending = PendingWrites::default(),
+            Err(error) => println!("Couldn't write to the metadata cache: {}", error.to_string())
+        }
     }
 
-    pub fn add_album_unit(&mut self, album: FullAlbum) -> AlbumInfo {
-        let id = album.id.clone();
-        let unit = AlbumInfo::from_api_data(album);
+    /// Moves everything from the `cache.ron` older versions kept into the database, in one go.
+    /// The old file is renamed afterwards so this only happens once.
+    fn migrate_legacy_cache(&mut self, data_path: &Path) -> Result<()> {
+        let legacy_path = data_path.join("cache.ron");
+
+        // Older versions created a directory in place of the file, and then never managed to write to it.
+        // Removing only works if it's empty, anything else in there isn't ours to delete.
+        if legacy_path.is_dir() {
+            std::fs::remove_dir(&legacy_path).ok();
+            return Ok(());
+        }
+
+        if !legacy_path.is_file() {
+            return Ok(());
+        }
+
+        let legacy: LegacyCache = serde_any::from_file(&legacy_path).map_err(|error| anyhow::Error::msg(error.to_string()))?;
 
-        self.album_cache.insert(id, unit.clone());
-        self.write_cache_data();
+        for album in legacy.album_cache.values() {
+            self.queue_row("albums", &album.id, album);
+        }
+
+        for track in legacy.track_cache.values() {
+            self.queue_row("tracks", &track.id, track);
+        }
+
+        for artist in legacy.artist_cache.values() {
+            self.queue_row("artists", &artist.id, artist);
+        }
 
-        unit
+        for features in legacy.audio_features_cache.values() {
+            self.queue_row("audio_features", &features.id, features);
+        }
+
+        self.pending.library.insert("saved_albums", legacy.saved_albums);
+        self.pending.library.insert("followed_artists", legacy.followed_artists);
+        self.pending.playlists = Some(legacy.user_playlists);
+
+        // Only let go of the old file once its contents are safely in the database.
+        self.flush();
+
+        if !self.pending.is_empty() {
+            return Err(anyhow::Error::msg("Couldn't store the old cache's contents"));
+        }
+
+        std::fs::rename(&legacy_path, data_path.join("cache.ron.migrated"))?;
+        Ok(())
+    }
+
+    /// Queues a row to be written on the next flush, replacing any write to it that's still pending.
+    fn queue_row<T: Serialize>(&mut self, table: &'static str, id: &str, value: &T) {
+        match serde_json::to_string(value) {
+            Ok(data) => {
+                self.pending.rows.insert((table, id.to_string()), data);
+            }
+            Err(error) => println!("Couldn't serialize cache data: {}", error.to_string())
+        }
+    }
+
+    fn get_row<T: DeserializeOwned>(&self, table: &'static str, id: &str) -> Option<T> {
+        let key = (table, id.to_string());
+
+        let data = match self.pending.rows.get(&key).or_else(|| self.flushing.rows.get(&key)) {
+            Some(data) => data.clone(),
+            None => {
+                self.db.as_ref()?
+                    .prepare_cached(&format!("SELECT data FROM {} WHERE id = ?1", table))
+                    .and_then(|mut statement| statement.query_row(params![id], |row| row.get::<_, String>(0)))
+                    .ok()?
+            }
+        };
+
+        serde_json::from_str(&data).ok()
+    }
+
+    pub fn try_get_album(&self, id: &str) -> Option<AlbumInfo> {
+        self.get_row("albums", id)
+    }
+
+    pub fn add_album_unit(&mut self, album: AlbumInfo) -> AlbumInfo {
+        self.queue_row("albums", &album.id, &album);
+        album
     }
 
     pub fn try_get_track(&self, id: &str) -> Option<TrackInfo> {
-        self.track_cache.get(id).cloned()
+        self.get_row("tracks", id)
     }
 
-    pub fn add_track_unit(&mut self, track: FullTrack) -> Option<TrackInfo> {
-        let id = track.id.clone().unwrap_or_else(String::new);
-        let unit = TrackInfo::from_api_data(track);
+    pub fn add_track_unit(&mut self, track: TrackInfo) -> TrackInfo {
+        self.queue_row("tracks", &track.id, &track);
+        track
+    }
 
-        if let Some(unit) = unit.as_ref() {
-            self.track_cache.insert(id, unit.clone());
-            self.write_cache_data();
+    pub fn add_track_units(&mut self, tracks: &[TrackInfo]) {
+        for track in tracks {
+            self.queue_row("tracks", &track.id, track);
         }
+    }
 
-        unit
+    pub fn try_get_audio_features(&self, id: &str) -> Option<AudioFeatures> {
+        self.get_row("audio_features", id)
     }
 
-    fn write_cache_data(&self) {
-        let mut cache_path = dirs::cache_dir().expect("Couldn't get cache dir");
-        cache_path.push("imguify/data/cache.ron");
+    pub fn add_audio_features_units(&mut self, features: &[AudioFeatures]) {
+        for features in features {
+            self.queue_row("audio_features", &features.id, features);
+        }
+    }
 
-        if let Err(error) = std::fs::create_dir_all(&cache_path) {
-            match error.kind() {
-                std::io::ErrorKind::AlreadyExists => {},
-                _ => panic!("{}", error.to_string())
-            }
+    pub fn try_get_artist(&self, id: &str) -> Option<ArtistInfo> {
+        self.get_row("artists", id)
+    }
+
+    /// Ids in a library section, in the order they were stored.
+    fn library(&self, section: &'static str) -> Vec<String> {
+        if let Some(ids) = self.pending.library.get(section).or_else(|| self.flushing.library.get(section)) {
+            return ids.clone();
+        }
+
+        let db = match self.db.as_ref() {
+            Some(db) => db,
+            None => return Vec::new()
+        };
+
+        let ids = db
+            .prepare_cached("SELECT item_id FROM library WHERE section = ?1 ORDER BY position")
+            .and_then(|mut statement| {
+                let ids = statement.query_map(params![section], |row| row.get(0))?.collect();
+                ids
+            })
+        ;
+
+        ids.unwrap_or_default()
+    }
+
+    /// The user's saved albums as of the last time they were fetched.
+    pub fn saved_albums(&self) -> Vec<AlbumInfo> {
+        self.library("saved_albums").iter().filter_map(|id| self.try_get_album(id)).collect()
+    }
+
+    pub fn set_saved_albums(&mut self, albums: &[AlbumInfo]) {
+        for album in albums {
+            self.queue_row("albums", &album.id, album);
+        }
+
+        self.pending.library.insert("saved_albums", albums.iter().map(|a| a.id.clone()).collect());
+    }
+
+    /// The artists the user follows as of the last time they were fetched.
+    pub fn followed_artists(&self) -> Vec<ArtistInfo> {
+        self.library("followed_artists").iter().filter_map(|id| self.try_get_artist(id)).collect()
+    }
+
+    pub fn set_followed_artists(&mut self, artists: &[ArtistInfo]) {
+        for artist in artists {
+            self.queue_row("artists", &artist.id, artist);
+        }
+
+        self.pending.library.insert("followed_artists", artists.iter().map(|a| a.id.clone()).collect());
+    }
+
+    /// The user's playlists with their items as of the last time they were fetched.
+    pub fn user_playlists(&self) -> Vec<CachedPlaylist> {
+        if let Some(playlists) = self.pending.playlists.as_ref().or_else(|| self.flushing.playlists.as_ref()) {
+            return playlists.clone();
+        }
+
+        let db = match self.db.as_ref() {
+            Some(db) => db,
+            None => return Vec::new()
+        };
+
+        let rows: rusqlite::Result<Vec<String>> = db
+            .prepare_cached("SELECT data FROM playlists ORDER BY position")
+            .and_then(|mut statement| {
+                let rows = statement.query_map([], |row| row.get(0))?.collect();
+                rows
+            })
+        ;
+
+        rows.unwrap_or_default().iter().filter_map(|data| serde_json::from_str(data).ok()).collect()
+    }
+
+    /// The items of a playlist, if they were cached at this snapshot.
+    pub fn try_get_playlist_items(&self, id: &str, snapshot_id: &str) -> Option<Vec<PlaylistItem>> {
+        if let Some(playlists) = self.pending.playlists.as_ref().or_else(|| self.flushing.playlists.as_ref()) {
+            return playlists.iter().find(|p| p.id == id && p.snapshot_id == snapshot_id).map(|p| p.items.clone());
+        }
+
+        let data: String = self.db.as_ref()?
+            .prepare_cached("SELECT data FROM playlists WHERE id = ?1 AND snapshot_id = ?2")
+            .and_then(|mut statement| statement.query_row(params![id, snapshot_id], |row| row.get(0)))
+            .ok()?
+        ;
+
+        serde_json::from_str::<CachedPlaylist>(&data).ok().map(|p| p.items)
+    }
+
+    pub fn set_user_playlists(&mut self, playlists: Vec<CachedPlaylist>) {
+        self.pending.playlists = Some(playlists);
+    }
+
+    pub fn try_get_new_releases(&self) -> Option<Vec<AlbumSummary>> {
+        self.new_releases.as_ref().and_then(Expiring::fresh)
+    }
+
+    pub fn set_new_releases(&mut self, albums: &[AlbumSummary]) {
+        self.new_releases = Some(Expiring::new(albums.to_vec()));
+    }
+
+    pub fn try_get_featured_playlists(&self) -> Option<FeaturedPlaylists> {
+        self.featured_playlists.as_ref().and_then(Expiring::fresh)
+    }
+
+    pub fn set_featured_playlists(&mut self, featured: &FeaturedPlaylists) {
+        self.featured_playlists = Some(Expiring::new(featured.clone()));
+    }
+
+    pub fn try_get_categories(&self) -> Option<Vec<Category>> {
+        self.categories.as_ref().and_then(Expiring::fresh)
+    }
+
+    pub fn set_categories(&mut self, categories: &[Category]) {
+        self.categories = Some(Expiring::new(categories.to_vec()));
+    }
+
+    pub fn try_get_category_playlists(&self, category_id: &str) -> Option<Vec<SimplifiedPlaylist>> {
+        self.category_playlists.get(category_id).and_then(Expiring::fresh)
+    }
+
+    pub fn set_category_playlists(&mut self, category_id: &str, playlists: &[SimplifiedPlaylist]) {
+        self.category_playlists.insert(category_id.to_string(), Expiring::new(playlists.to_vec()));
+    }
+}
+
+impl Drop for APICacheHandler {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+/// Writes waiting for the next flush, already serialized.
+#[derive(Default)]
+struct PendingWrites {
+    rows: HashMap<(&'static str, String), String>,
+    library: HashMap<&'static str, Vec<String>>,
+    playlists: Option<Vec<CachedPlaylist>>
+}
+
+impl PendingWrites {
+    fn is_empty(&self) -> bool {
+        self.rows.is_empty() && self.library.is_empty() && self.playlists.is_none()
+    }
+
+    /// Puts back the writes of a flush that failed, unless newer ones replaced them in the meantime.
+    fn restore(&mut self, batch: &PendingWrites) {
+        for (key, data) in batch.rows.iter() {
+            self.rows.entry(key.clone()).or_insert_with(|| data.clone());
+        }
+
+        for (section, ids) in batch.library.iter() {
+            self.library.entry(*section).or_insert_with(|| ids.clone());
+        }
+
+        if self.playlists.is_none() {
+            self.playlists = batch.playlists.clone();
+        }
+    }
+}
+
+/// The `cache.ron` older versions kept everything in.
+#[derive(Deserialize)]
+struct LegacyCache {
+    album_cache: HashMap<String, AlbumInfo>,
+    track_cache: HashMap<String, TrackInfo>,
+
+    #[serde(default)]
+    artist_cache: HashMap<String, ArtistInfo>,
+    #[serde(default)]
+    saved_albums: Vec<String>,
+    #[serde(default)]
+    followed_artists: Vec<String>,
+    #[serde(default)]
+    user_playlists: Vec<CachedPlaylist>,
+    #[serde(default)]
+    audio_features_cache: HashMap<String, AudioFeatures>
+}
+
+/// Opens the database twice, once for lookups and once for the writer.
+fn open_database(data_path: &Path) -> Result<(Connection, Connection)> {
+    std::fs::create_dir_all(data_path)?;
+
+    let db = Connection::open(data_path.join("cache.sqlite"))?;
+
+    // Lets lookups read while a flush is being written, instead of failing on a locked database.
+    db.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))?;
+
+    let version: i32 = db.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+    if version < SCHEMA_VERSION {
+        db.execute_batch(
+            "BEGIN;
+            CREATE TABLE IF NOT EXISTS tracks (id TEXT PRIMARY KEY, data TEXT NOT NULL);
+            CREATE TABLE IF NOT EXISTS albums (id TEXT PRIMARY KEY, data TEXT NOT NULL);
+            CREATE TABLE IF NOT EXISTS artists (id TEXT PRIMARY KEY, data TEXT NOT NULL);
+            CREATE TABLE IF NOT EXISTS audio_features (id TEXT PRIMARY KEY, data TEXT NOT NULL);
+            CREATE TABLE IF NOT EXISTS playlists (id TEXT PRIMARY KEY, position INTEGER NOT NULL, snapshot_id TEXT NOT NULL, data TEXT NOT NULL);
+            CREATE TABLE IF NOT EXISTS library (section TEXT NOT NULL, position INTEGER NOT NULL, item_id TEXT NOT NULL, PRIMARY KEY (section, position));
+            PRAGMA user_version = 1;
+            COMMIT;"
+        )?;
+    }
+
+    let writer = Connection::open(data_path.join("cache.sqlite"))?;
+    writer.busy_timeout(Duration::from_secs(5))?;
+
+    Ok((db, writer))
+}
+
+/// Stores a batch of writes in a single transaction, so it's either stored completely or not at all.
+fn store(db: &Connection, pending: &PendingWrites) -> Result<()> {
+    let tx = db.unchecked_transaction()?;
+
+    for ((table, id), data) in pending.rows.iter() {
+        insert_row(&tx, table, id, data)?;
+    }
+
+    for (section, ids) in pending.library.iter() {
+        set_library(&tx, section, ids)?;
+    }
+
+    if let Some(playlists) = pending.playlists.as_ref() {
+        set_playlists(&tx, playlists)?;
+    }
+
+    tx.commit()?;
+    Ok(())
+}
+
+fn insert_row(tx: &Transaction, table: &str, id: &str, data: &str) -> rusqlite::Result<()> {
+    tx.prepare_cached(&format!("INSERT OR REPLACE INTO {} (id, data) VALUES (?1, ?2)", table))?
+        .execute(params![id, data])?
+    ;
+
+    Ok(())
+}
+
+fn set_library(tx: &Transaction, section: &str, ids: &[String]) -> rusqlite::Result<()> {
+    tx.execute("DELETE FROM library WHERE section = ?1", params![section])?;
+
+    for (position, id) in ids.iter().enumerate() {
+        tx.execute("INSERT INTO library (section, position, item_id) VALUES (?1, ?2, ?3)", params![section, position as i64, id])?;
+    }
+
+    Ok(())
+}
+
+fn set_playlists(tx: &Transaction, playlists: &[CachedPlaylist]) -> rusqlite::Result<()> {
+    tx.execute("DELETE FROM playlists", [])?;
+
+    for (position, playlist) in playlists.iter().enumerate() {
+        let data = serde_json::to_string(playlist).map_err(|error| rusqlite::Error::ToSqlConversionFailure(Box::new(error)))?;
+
+        tx.execute(
+            "INSERT INTO playlists (id, position, snapshot_id, data) VALUES (?1, ?2, ?3, ?4)",
+            params![playlist.id, position as i64, playlist.snapshot_id, data]
+        )?;
+    }
+
+    Ok(())
+}
+
+/// One of the user's playlists, kept with the snapshot its items belong to.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct CachedPlaylist {
+    pub id: String,
+    pub name: String,
+    pub owner: String,
+    #[serde(default)]
+    pub description: String,
+    pub public: bool,
+    pub collaborative: bool,
+    pub editable: bool,
+    pub snapshot_id: String,
+    pub items: Vec<PlaylistItem>
+}
+
+/// A cached value that's only good for `BROWSE_TTL` after it was fetched.
+struct Expiring<T> {
+    value: T,
+    fetched_at: Instant
+}
+
+impl<T: Clone> Expiring<T> {
+    fn new(value: T) -> Expiring<T> {
+        Expiring {
+            value,
+            fetched_at: Instant::now()
+        }
+    }
+
+    fn fresh(&self) -> Option<T> {
+        if self.fetched_at.elapsed() < BROWSE_TTL {
+            Some(self.value.clone())
+        }
+        else {
+            None
         }
-        
-        serde_any::to_file_pretty(cache_path, self).expect("Failed to write cache data");
     }
 }
 
@@ -76,23 +572,195 @@ pub struct AlbumInfo {
     name: String,
     
     tracks: Vec<String>,
-    artists: Vec<String>
+    artists: Vec<String>,
+
+    // Only albums with their details loaded have these, see `has_details`.
+    #[serde(default)]
+    release_date: String,
+    #[serde(default)]
+    label: String,
+    #[serde(default)]
+    tracklist: Vec<AlbumTrack>
 }
 
 impl AlbumInfo {
+    pub fn new(id: String, name: String, tracks: Vec<String>, artists: Vec<String>) -> AlbumInfo {
+        AlbumInfo {
+            id,
+            name,
+
+            tracks,
+            artists,
+
+            release_date: String::new(),
+            label: String::new(),
+            tracklist: Vec::new()
+        }
+    }
+
     pub fn from_api_data(album: FullAlbum) -> AlbumInfo {
+        // rspotify doesn't expose the label itself, but the phonographic copyright line names it.
+        let label = album.copyrights.iter()
+            .find(|c| c.get("type").map(|t| t == "P").unwrap_or(false))
+            .or_else(|| album.copyrights.first())
+            .and_then(|c| c.get("text").cloned())
+            .unwrap_or_default()
+        ;
+
+        let tracklist: Vec<AlbumTrack> = album.tracks.items.into_iter().filter_map(AlbumTrack::from_api_data).collect();
+
         AlbumInfo {
             id: album.id,
             name: album.name,
             
-            tracks: album.tracks.items.into_iter().map(|t| t.id.unwrap_or_else(String::new)).collect(),
-            artists: album.artists.into_iter().map(|a| a.name).collect()
+            tracks: tracklist.iter().map(|t| t.id.clone()).collect(),
+            artists: album.artists.into_iter().map(|a| a.name).collect(),
+
+            release_date: album.release_date,
+            label,
+            tracklist
         }
     }
 
+    pub fn with_tracklist(mut self, tracklist: Vec<AlbumTrack>) -> AlbumInfo {
+        self.tracklist = tracklist;
+        self
+    }
+
+    /// Whether this has the release date, label and tracklist, and not just the track ids.
+    pub fn has_details(&self) -> bool {
+        !self.tracklist.is_empty()
+    }
+
+    pub fn id(&self) -> &String {
+        &self.id
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
     pub fn tracks(&self) -> &Vec<String> {
         &self.tracks
     }
+
+    pub fn artists(&self) -> &Vec<String> {
+        &self.artists
+    }
+
+    pub fn release_date(&self) -> &String {
+        &self.release_date
+    }
+
+    pub fn label(&self) -> &String {
+        &self.label
+    }
+
+    pub fn tracklist(&self) -> &Vec<AlbumTrack> {
+        &self.tracklist
+    }
+}
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct AlbumTrack {
+    id: String,
+    name: String,
+    disc_number: i32,
+    track_number: u32,
+    duration: u32,
+    artists: Vec<String>
+}
+
+impl AlbumTrack {
+    pub fn new(id: String, name: String, disc_number: i32, track_number: u32, duration: u32, artists: Vec<String>) -> AlbumTrack {
+        AlbumTrack {
+            id,
+            name,
+            disc_number,
+            track_number,
+            duration,
+            artists
+        }
+    }
+
+    pub fn from_api_data(track: SimplifiedTrack) -> Option<AlbumTrack> {
+        Some(
+            AlbumTrack {
+                id: track.id?,
+                name: track.name,
+                disc_number: track.disc_number,
+                track_number: track.track_number,
+                duration: track.duration_ms,
+                artists: track.artists.into_iter().map(|a| a.name).collect()
+            }
+        )
+    }
+
+    pub fn id(&self) -> &String {
+        &self.id
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn disc_number(&self) -> &i32 {
+        &self.disc_number
+    }
+
+    pub fn track_number(&self) -> &u32 {
+        &self.track_number
+    }
+
+    pub fn duration(&self) -> &u32 {
+        &self.duration
+    }
+
+    pub fn artists(&self) -> &Vec<String> {
+        &self.artists
+    }
+}
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct ArtistInfo {
+    id: String,
+    name: String,
+    genres: Vec<String>,
+    followers: u32
+}
+
+impl ArtistInfo {
+    pub fn from_api_data(artist: FullArtist) -> ArtistInfo {
+        let followers = artist.followers
+            .get("total")
+            .and_then(|total| total.as_ref())
+            .and_then(|total| total.as_u64())
+            .unwrap_or(0)
+        ;
+
+        ArtistInfo {
+            id: artist.id,
+            name: artist.name,
+            genres: artist.genres,
+            followers: followers as u32
+        }
+    }
+
+    pub fn id(&self) -> &String {
+        &self.id
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn genres(&self) -> &Vec<String> {
+        &self.genres
+    }
+
+    pub fn followers(&self) -> &u32 {
+        &self.followers
+    }
 }
 
 #[derive(Clone, Default, Deserialize, Serialize)]
@@ -102,10 +770,26 @@ pub struct TrackInfo {
     duration: u32,
     popularity: u32,
     album: String,
-    artists: Vec<String>
+    artists: Vec<String>,
+
+    #[serde(default)]
+    album_name: String
 }
 
 impl TrackInfo {
+    pub fn new(id: String, name: String, duration: u32, popularity: u32, album: String, album_name: String, artists: Vec<String>) -> TrackInfo {
+        TrackInfo {
+            id,
+            name,
+            duration,
+            popularity,
+            album,
+            artists,
+
+            album_name
+        }
+    }
+
     pub fn from_api_data(track: FullTrack) -> Option<TrackInfo> {
         if let (Some(id), Some(album)) = (track.id, track.album.id) {
             Some(
@@ -115,7 +799,9 @@ impl TrackInfo {
                     duration: track.duration_ms,
                     popularity: track.popularity,
                     album,
-                    artists: track.artists.into_iter().map(|a| a.name).collect()
+                    artists: track.artists.into_iter().map(|a| a.name).collect(),
+
+                    album_name: track.album.name
                 }
             )
         }
@@ -143,4 +829,227 @@ impl TrackInfo {
     pub fn popularity(&self) -> &u32 {
         &self.popularity
     }
+
+    /// The id of the album the track is on.
+    pub fn album(&self) -> &String {
+        &self.album
+    }
+
+    /// Empty for tracks cached before album names were kept.
+    pub fn album_name(&self) -> &String {
+        &self.album_name
+    }
+}
+
+/// What a track sounds like, as analysed by Spotify.
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct AudioFeatures {
+    id: String,
+    tempo: f32,
+    /// Pitch class of the key, C is 0. -1 if no key was detected.
+    key: i32,
+    /// 1 for major, 0 for minor.
+    mode: i32,
+    energy: f32,
+    danceability: f32,
+    valence: f32,
+    loudness: f32
+}
+
+impl AudioFeatures {
+    pub fn from_api_data(features: APIAudioFeatures) -> AudioFeatures {
+        AudioFeatures {
+            id: features.id,
+            tempo: features.tempo,
+            key: features.key,
+            mode: features.mode as i32,
+            energy: features.energy,
+            danceability: features.danceability,
+            valence: features.valence,
+            loudness: features.loudness
+        }
+    }
+
+    pub fn id(&self) -> &String {
+        &self.id
+    }
+
+    /// Beats per minute.
+    pub fn tempo(&self) -> &f32 {
+        &self.tempo
+    }
+
+    pub fn energy(&self) -> &f32 {
+        &self.energy
+    }
+
+    pub fn danceability(&self) -> &f32 {
+        &self.danceability
+    }
+
+    pub fn valence(&self) -> &f32 {
+        &self.valence
+    }
+
+    /// Average loudness in dB, usually between -60 and 0.
+    pub fn loudness(&self) -> &f32 {
+        &self.loudness
+    }
+
+    /// Like "F#m", empty if no key was detected.
+    pub fn key_name(&self) -> String {
+        const PITCHES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+
+        match PITCHES.get(self.key as usize) {
+            Some(pitch) if self.key >= 0 => format!("{}{}", pitch, if self.mode == 1 { "" } else { "m" }),
+            _ => String::new()
+        }
+    }
+
+    /// The key on the Camelot wheel as its number and letter, like (11, 'A') for F#m.
+    /// Keys next to each other on the wheel mix well.
+    pub fn camelot(&self) -> Option<(u32, char)> {
+        if self.key < 0 || self.key > 11 {
+            return None;
+        }
+
+        // Going around the wheel moves up a fifth, and minor keys sit next to their relative major.
+        let key = self.key as u32;
+
+        if self.mode == 1 {
+            Some(((7 * key + 7) % 12 + 1, 'B'))
+        }
+        else {
+            Some(((7 * key + 28) % 12 + 1, 'A'))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A cache from before the library, playlists and audio features were kept.
+    const OLD_CACHE: &str = r#"(
+        album_cache: {
+            "a1": (id: "a1", name: "Album", tracks: ["t1"], artists: ["Artist"]),
+        },
+        track_cache: {
+            "t1": (id: "t1", name: "Track", duration: 1000, popularity: 5, album: "a1", artists: ["Artist"]),
+        },
+    )"#;
+
+    const FULL_CACHE: &str = r#"(
+        album_cache: {
+            "a1": (id: "a1", name: "Album", tracks: ["t1"], artists: ["Artist"], release_date: "2020-01-01", label: "Label", tracklist: []),
+        },
+        track_cache: {
+            "t1": (id: "t1", name: "Track", duration: 1000, popularity: 5, album: "a1", artists: ["Artist"], album_name: "Album"),
+        },
+        artist_cache: {
+            "ar1": (id: "ar1", name: "Artist", genres: ["pop"], followers: 10),
+        },
+        saved_albums: ["a1"],
+        followed_artists: ["ar1"],
+        user_playlists: [
+            (
+                id: "p1",
+                name: "Playlist",
+                owner: "me",
+                public: false,
+                collaborative: false,
+                editable: true,
+                snapshot_id: "s1",
+                items: [(uri: "spotify:track:t1", kind: Track("t1"), name: "", added_at: None, added_by: None)],
+            ),
+        ],
+        audio_features_cache: {
+            "t1": (id: "t1", tempo: 120.0, key: 9, mode: 0, energy: 0.5, danceability: 0.6, valence: 0.7, loudness: -5.0),
+        },
+    )"#;
+
+    fn temp_data_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("imguify-cache-test-{}-{}", name, std::process::id()));
+
+        std::fs::remove_dir_all(&path).ok();
+        std::fs::create_dir_all(&path).unwrap();
+
+        path
+    }
+
+    #[test]
+    fn migrates_cache_without_newer_fields() {
+        let path = temp_data_path("old");
+        std::fs::write(path.join("cache.ron"), OLD_CACHE).unwrap();
+
+        drop(APICacheHandler::open(Some(path.clone())));
+
+        assert!(!path.join("cache.ron").exists());
+        assert!(path.join("cache.ron.migrated").is_file());
+
+        // Opening again reads what the migration stored, with nothing pending.
+        let cache = APICacheHandler::open(Some(path.clone()));
+
+        let track = cache.try_get_track("t1").unwrap();
+        assert_eq!(track.name(), "Track");
+        assert_eq!(track.album_name(), "");
+
+        let album = cache.try_get_album("a1").unwrap();
+        assert_eq!(album.tracks(), &vec![String::from("t1")]);
+        assert!(!album.has_details());
+
+        assert!(cache.saved_albums().is_empty());
+        assert!(cache.followed_artists().is_empty());
+        assert!(cache.user_playlists().is_empty());
+        assert!(cache.try_get_audio_features("t1").is_none());
+
+        std::fs::remove_dir_all(&path).ok();
+    }
+
+    #[test]
+    fn migrates_full_cache() {
+        let path = temp_data_path("full");
+        std::fs::write(path.join("cache.ron"), FULL_CACHE).unwrap();
+
+        drop(APICacheHandler::open(Some(path.clone())));
+        let cache = APICacheHandler::open(Some(path.clone()));
+
+        assert!(path.join("cache.ron.migrated").is_file());
+
+        assert_eq!(cache.try_get_track("t1").unwrap().album_name(), "Album");
+        assert_eq!(cache.try_get_album("a1").unwrap().label(), "Label");
+        assert_eq!(cache.try_get_artist("ar1").unwrap().genres(), &vec![String::from("pop")]);
+
+        let saved_albums: Vec<String> = cache.saved_albums().iter().map(|a| a.id().clone()).collect();
+        assert_eq!(saved_albums, vec![String::from("a1")]);
+
+        let followed_artists: Vec<String> = cache.followed_artists().iter().map(|a| a.id().clone()).collect();
+        assert_eq!(followed_artists, vec![String::from("ar1")]);
+
+        let playlists = cache.user_playlists();
+        assert_eq!(playlists.len(), 1);
+        assert!(playlists[0].editable);
+        assert_eq!(cache.try_get_playlist_items("p1", "s1").unwrap().len(), 1);
+        assert!(cache.try_get_playlist_items("p1", "outdated").is_none());
+
+        let features = cache.try_get_audio_features("t1").unwrap();
+        assert_eq!(features.camelot(), Some((8, 'A')));
+        assert_eq!(features.key_name(), "Am");
+
+        std::fs::remove_dir_all(&path).ok();
+    }
+
+    #[test]
+    fn removes_empty_cache_directory() {
+        let path = temp_data_path("directory");
+        std::fs::create_dir_all(path.join("cache.ron")).unwrap();
+
+        let cache = APICacheHandler::open(Some(path.clone()));
+
+        assert!(!path.join("cache.ron").exists());
+        assert!(!path.join("cache.ron.migrated").exists());
+        assert!(cache.try_get_track("t1").is_none());
+
+        std::fs::remove_dir_all(&path).ok();
+    }
 }
@@ -0,0 +1,174 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use anyhow::Result;
+
+const WORKER_COUNT: usize = 4;
+
+type Job = Box<dyn FnOnce() + Send>;
+type InFlightMap = Arc<Mutex<HashMap<String, Box<dyn Any + Send>>>>;
+
+pub enum RequestState<T> {
+    Loading,
+    Done(T),
+    Failed(String)
+}
+
+/// A handle to a request running on the executor.
+/// Dropping every handle to a request that didn't start yet cancels it.
+pub struct RequestHandle<T> {
+    state: Arc<RwLock<RequestState<T>>>
+}
+
+impl<T> Clone for RequestHandle<T> {
+    fn clone(&self) -> RequestHandle<T> {
+        RequestHandle {
+            state: self.state.clone()
+        }
+    }
+}
+
+impl<T: Clone> RequestHandle<T> {
+    pub fn is_loading(&self) -> bool {
+        if let Ok(lock) = self.state.read() {
+            matches!(*lock, RequestState::Loading)
+        }
+        else {
+            false
+        }
+    }
+
+    pub fn result(&self) -> Option<T> {
+        if let Ok(lock) = self.state.read() {
+            if let RequestState::Done(result) = &*lock {
+                return Some(result.clone());
+            }
+        }
+
+        None
+    }
+
+    pub fn error(&self) -> Option<String> {
+        if let Ok(lock) = self.state.read() {
+            if let RequestState::Failed(error) = &*lock {
+                return Some(error.clone());
+            }
+        }
+
+        None
+    }
+}
+
+/// Runs API requests on a small pool of worker threads, so the UI thread never waits on the network.
+/// Requests are keyed, and submitting a request that's already in flight returns the existing handle.
+pub struct RequestExecutor {
+    job_tx: Mutex<Sender<Job>>,
+    in_flight: InFlightMap
+}
+
+impl RequestExecutor {
+    pub fn init() -> RequestExecutor {
+        let (job_tx, job_rx) = mpsc::channel();
+        let job_rx: Arc<Mutex<Receiver<Job>>> = Arc::new(Mutex::new(job_rx));
+
+        for _ in 0..WORKER_COUNT {
+            let job_rx = job_rx.clone();
+
+            std::thread::spawn(move || {
+                loop {
+                    let job = {
+                        if let Ok(lock) = job_rx.lock() {
+                            lock.recv()
+                        }
+                        else {
+                            break;
+                        }
+                    };
+
+                    match job {
+                        Ok(job) => job(),
+                        Err(_) => break
+                    }
+                }
+            });
+        }
+
+        RequestExecutor {
+            job_tx: Mutex::new(job_tx),
+            in_flight: Arc::new(Mutex::new(HashMap::new()))
+        }
+    }
+
+    pub fn submit<T, F>(&self, key: String, request: F) -> RequestHandle<T>
+    where
+        T: Send + Sync + 'static,
+        F: FnOnce() -> Result<T> + Send + 'static
+    {
+        let state = {
+            if let Ok(mut lock) = self.in_flight.lock() {
+                let existing = lock
+                    .get(&key)
+                    .and_then(|state| state.downcast_ref::<Weak<RwLock<RequestState<T>>>>())
+                    .and_then(|state| state.upgrade())
+                ;
+
+                if let Some(state) = existing {
+                    return RequestHandle { state };
+                }
+
+                let state = Arc::new(RwLock::new(RequestState::Loading));
+                lock.insert(key.clone(), Box::new(Arc::downgrade(&state)));
+
+                state
+            }
+            else {
+                Arc::new(RwLock::new(RequestState::Loading))
+            }
+        };
+
+        let weak_state = Arc::downgrade(&state);
+        let in_flight = self.in_flight.clone();
+
+        let job: Job = Box::new(move || {
+            // Nobody is waiting for the result anymore, don't bother sending the request.
+            let result = if weak_state.strong_count() > 0 {
+                Some(request())
+            }
+            else {
+                None
+            };
+
+            if let Ok(mut lock) = in_flight.lock() {
+                let is_current = lock
+                    .get(&key)
+                    .and_then(|state| state.downcast_ref::<Weak<RwLock<RequestState<T>>>>())
+                    .map(|state| state.ptr_eq(&weak_state))
+                    .unwrap_or(false)
+                ;
+
+                if is_current {
+                    lock.remove(&key);
+                }
+            }
+
+            if let (Some(result), Some(state)) = (result, weak_state.upgrade()) {
+                if let Ok(mut lock) = state.write() {
+                    *lock = match result {
+                        Ok(result) => RequestState::Done(result),
+                        Err(error) => RequestState::Failed(error.to_string())
+                    };
+                }
+            }
+        });
+
+        if let Ok(lock) = self.job_tx.lock() {
+            if let Err(error) = lock.send(job) {
+                println!("Error submitting API request: {}", error.to_string());
+            }
+        }
+
+        RequestHandle { state }
+    }
+}
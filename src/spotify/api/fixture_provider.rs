@@ -0,0 +1,194 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use rspotify::model::page::Page;
+use rspotify::model::user::PrivateUser;
+use rspotify::model::track::{FullTrack, FullTracks, SavedTrack};
+use rspotify::model::artist::{FullArtist, FullArtists};
+use rspotify::model::album::{FullAlbum, SavedAlbum, SimplifiedAlbum};
+use rspotify::model::search::SearchResult;
+use rspotify::model::playlist::{PlaylistTrack, SimplifiedPlaylist};
+use rspotify::senum::SearchType;
+
+use super::cache::{AlbumInfo, ArtistInfo, TrackInfo};
+use super::provider::{AlbumSummary, MetadataProvider, PlaylistItem};
+use crate::spotify::search::SearchQuery;
+
+/// Metadata loaded from Web API responses saved as JSON files, for offline use and demos.
+///
+/// The fixtures directory is laid out like this:
+/// - `me.json`: the current user.
+/// - `user_playlists.json`: a page of simplified playlists, with their descriptions.
+/// - `playlist_tracks/<id>.json`: a page of playlist tracks.
+/// - `tracks/<id>.json`, `albums/<id>.json`, `artists/<id>.json`: full objects.
+/// - `artist_albums/<id>.json`: a page of simplified albums.
+/// - `artist_top_tracks/<id>.json`: an artist's top tracks.
+/// - `related_artists/<id>.json`: an artist's related artists.
+/// - `saved_tracks.json`: a page of saved tracks.
+/// - `saved_albums.json`: a page of saved albums.
+/// - `followed_artists.json`: a page of full artists.
+///
+/// Searches match the query against the names of every track and artist in the directory,
+/// and the user's playlists.
+pub struct FixtureProvider {
+    path: PathBuf
+}
+
+impl FixtureProvider {
+    pub fn init(path: PathBuf) -> Result<FixtureProvider> {
+        if !path.is_dir() {
+            return Err(anyhow::Error::msg(format!("{} isn't a fixtures directory", path.display())));
+        }
+
+        Ok(FixtureProvider { path })
+    }
+
+    fn read<T: DeserializeOwned>(&self, file: &str) -> Result<T> {
+        let path = self.path.join(file);
+        let data = fs::read_to_string(&path).with_context(|| format!("Missing fixture {}", path.display()))?;
+
+        serde_json::from_str(&data).with_context(|| format!("Invalid fixture {}", path.display()))
+    }
+
+    fn read_all<T: DeserializeOwned>(&self, dir: &str) -> Vec<T> {
+        let mut results = Vec::new();
+
+        if let Ok(entries) = fs::read_dir(self.path.join(dir)) {
+            for entry in entries.flatten() {
+                if let Some(name) = entry.file_name().to_str() {
+                    if let Ok(item) = self.read(&format!("{}/{}", dir, name)) {
+                        results.push(item);
+                    }
+                }
+            }
+        }
+
+        results
+    }
+}
+
+impl MetadataProvider for FixtureProvider {
+    fn user_playlists(&self) -> Result<Page<SimplifiedPlaylist>> {
+        self.read("user_playlists.json")
+    }
+
+    fn playlist_tracks(&self, playlist_id: &str) -> Result<Vec<PlaylistItem>> {
+        let page: Page<PlaylistTrack> = self.read(&format!("playlist_tracks/{}.json", playlist_id))?;
+        Ok(page.items.into_iter().map(PlaylistItem::from_api_data).collect())
+    }
+
+    // Read straight from `user_playlists.json`, which has the descriptions that the simplified playlists leave out.
+    fn playlist_description(&self, playlist_id: &str) -> Result<String> {
+        let page: Value = self.read("user_playlists.json")?;
+
+        page["items"].as_array()
+            .and_then(|items| items.iter().find(|item| item["id"] == playlist_id))
+            .map(|item| item["description"].as_str().unwrap_or_default().to_string())
+            .context("Playlist isn't in the user's playlists")
+    }
+
+    fn track(&self, track_id: &str) -> Result<TrackInfo> {
+        let track: FullTrack = self.read(&format!("tracks/{}.json", track_id))?;
+        TrackInfo::from_api_data(track).context("Track is missing its id or album")
+    }
+
+    fn album(&self, album_id: &str) -> Result<AlbumInfo> {
+        let album: FullAlbum = self.read(&format!("albums/{}.json", album_id))?;
+        Ok(AlbumInfo::from_api_data(album))
+    }
+
+    fn artist(&self, artist_id: &str) -> Result<FullArtist> {
+        self.read(&format!("artists/{}.json", artist_id))
+    }
+
+    fn artist_albums(&self, artist_id: &str) -> Result<Vec<AlbumSummary>> {
+        let page: Page<SimplifiedAlbum> = self.read(&format!("artist_albums/{}.json", artist_id))?;
+        Ok(page.items.into_iter().filter_map(AlbumSummary::from_api_data).collect())
+    }
+
+    fn artist_top_tracks(&self, artist_id: &str) -> Result<Vec<TrackInfo>> {
+        let tracks: FullTracks = self.read(&format!("artist_top_tracks/{}.json", artist_id))?;
+        Ok(tracks.tracks.into_iter().filter_map(TrackInfo::from_api_data).collect())
+    }
+
+    fn related_artists(&self, artist_id: &str) -> Result<Vec<ArtistInfo>> {
+        let artists: FullArtists = self.read(&format!("related_artists/{}.json", artist_id))?;
+        Ok(artists.artists.into_iter().map(ArtistInfo::from_api_data).collect())
+    }
+
+    // Markets are ignored, and of the filters only `artist:` narrows down tracks.
+    fn search(&self, query: &str, search_type: SearchType, _market: Option<&str>, limit: u32, offset: u32) -> Result<SearchResult> {
+        let parsed = SearchQuery::parse(query);
+        let query = parsed.text.to_lowercase();
+        let artist = parsed.artist.to_lowercase();
+
+        match search_type {
+            SearchType::Track => {
+                let mut items: Vec<FullTrack> = self.read_all("tracks");
+
+                items.retain(|t| t.name.to_lowercase().contains(&query) && t.artists.iter().any(|a| a.name.to_lowercase().contains(&artist)));
+                items.sort_by(|a, b| a.name.cmp(&b.name));
+
+                Ok(SearchResult::Tracks(fixture_page(items, limit, offset)))
+            }
+            SearchType::Artist => {
+                let mut items: Vec<FullArtist> = self.read_all("artists");
+
+                items.retain(|a| a.name.to_lowercase().contains(&query));
+                items.sort_by(|a, b| a.name.cmp(&b.name));
+
+                Ok(SearchResult::Artists(fixture_page(items, limit, offset)))
+            }
+            SearchType::Playlist => {
+                let mut items = self.user_playlists()?.items;
+
+                items.retain(|p| p.name.to_lowercase().contains(&query));
+                items.sort_by(|a, b| a.name.cmp(&b.name));
+
+                Ok(SearchResult::Playlists(fixture_page(items, limit, offset)))
+            }
+            _ => Err(anyhow::Error::msg("Fixtures can only be searched for tracks, artists and playlists"))
+        }
+    }
+
+    fn current_user_id(&self) -> Result<String> {
+        let user: PrivateUser = self.read("me.json")?;
+        Ok(user.id)
+    }
+
+    fn saved_tracks(&self) -> Result<Vec<TrackInfo>> {
+        let page: Page<SavedTrack> = self.read("saved_tracks.json")?;
+        Ok(page.items.into_iter().filter_map(|item| TrackInfo::from_api_data(item.track)).collect())
+    }
+
+    fn saved_albums(&self) -> Result<Vec<AlbumInfo>> {
+        let page: Page<SavedAlbum> = self.read("saved_albums.json")?;
+        Ok(page.items.into_iter().map(|item| AlbumInfo::from_api_data(item.album)).collect())
+    }
+
+    fn followed_artists(&self) -> Result<Vec<ArtistInfo>> {
+        let page: Page<FullArtist> = self.read("followed_artists.json")?;
+        Ok(page.items.into_iter().map(ArtistInfo::from_api_data).collect())
+    }
+}
+
+/// A page out of `items`, with `next` set if there's more after it like in real responses.
+fn fixture_page<T>(items: Vec<T>, limit: u32, offset: u32) -> Page<T> {
+    let total = items.len() as u32;
+    let items: Vec<T> = items.into_iter().skip(offset as usize).take(limit as usize).collect();
+    let next = if offset + (items.len() as u32) < total { Some(String::new()) } else { None };
+
+    Page {
+        href: String::new(),
+        limit,
+        next,
+        offset,
+        previous: None,
+        total,
+        items
+    }
+}
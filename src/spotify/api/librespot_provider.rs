@@ -0,0 +1,375 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use anyhow::{Context, Result};
+use futures::future::join_all;
+use serde_json::json;
+use tokio::runtime::Handle;
+
+use librespot::core::session::Session;
+use librespot::core::spotify_id::{SpotifyAudioType, SpotifyId};
+use librespot::metadata::{Album, Artist, Metadata, Playlist, Track};
+use librespot::protocol::playlist4changes::SelectedListContent;
+
+use rspotify::model::page::Page;
+use rspotify::model::artist::FullArtist;
+use rspotify::model::search::SearchResult;
+use rspotify::model::category::Category;
+use rspotify::model::playlist::SimplifiedPlaylist;
+use rspotify::senum::SearchType;
+
+use super::cache::{AlbumInfo, AlbumTrack, ArtistInfo, AudioFeatures, TrackInfo};
+use super::provider::{
+    unsupported, AlbumSummary, AttributeRange, FeaturedPlaylists, MetadataProvider, PlayedTrack,
+    PlaylistItem, PlaylistItemKind, RecommendationSeed, TopTerm
+};
+
+/// Metadata fetched over the librespot session, so it only needs the user's login.
+///
+/// Anything librespot can't provide (search, the user's library)
+/// goes to the fallback provider, which is usually the Web API if it was authorized.
+/// The fallback is also used for ids the session can't resolve.
+pub struct LibrespotProvider {
+    rt: Handle,
+    session: Session,
+    fallback: Option<Arc<dyn MetadataProvider>>,
+
+    artist_names: RwLock<HashMap<SpotifyId, String>>,
+    album_names: RwLock<HashMap<SpotifyId, String>>
+}
+
+impl LibrespotProvider {
+    pub fn init(rt: Handle, session: Session, fallback: Option<Arc<dyn MetadataProvider>>) -> LibrespotProvider {
+        LibrespotProvider {
+            rt,
+            session,
+            fallback,
+
+            artist_names: RwLock::new(HashMap::new()),
+            album_names: RwLock::new(HashMap::new())
+        }
+    }
+
+    fn fallback(&self, feature: &str) -> Result<&dyn MetadataProvider> {
+        self.fallback.as_deref().ok_or_else(|| unsupported(feature))
+    }
+
+    fn get<T: Metadata>(&self, id: &str) -> Result<T> {
+        let id = SpotifyId::from_base62(id).map_err(|_| anyhow::Error::msg(format!("Invalid Spotify id {}", id)))?;
+        self.rt.block_on(T::get(&self.session, id)).map_err(|_| anyhow::Error::msg("Couldn't get metadata from Spotify"))
+    }
+
+    // Tracks and albums only reference their artists by id, and the names are needed everywhere.
+    fn artist_names(&self, artists: &[SpotifyId]) -> Vec<String> {
+        let mut results = Vec::new();
+
+        for id in artists {
+            if let Some(name) = self.artist_names.read().ok().and_then(|lock| lock.get(id).cloned()) {
+                results.push(name);
+                continue;
+            }
+
+            if let Ok(artist) = self.rt.block_on(Artist::get(&self.session, *id)) {
+                if let Ok(mut lock) = self.artist_names.write() {
+                    lock.insert(*id, artist.name.clone());
+                }
+
+                results.push(artist.name);
+            }
+        }
+
+        results
+    }
+
+    fn album_name(&self, album: SpotifyId) -> String {
+        if let Some(name) = self.album_names.read().ok().and_then(|lock| lock.get(&album).cloned()) {
+            return name;
+        }
+
+        if let Ok(data) = self.rt.block_on(Album::get(&self.session, album)) {
+            if let Ok(mut lock) = self.album_names.write() {
+                lock.insert(album, data.name.clone());
+            }
+
+            data.name
+        }
+        else {
+            String::new()
+        }
+    }
+
+    /// The ids of the playlists in the user's library, in the order the Spotify client shows them.
+    fn rootlist(&self) -> Result<Vec<SpotifyId>> {
+        let uri = format!("hm://playlist/v2/user/{}/rootlist", self.session.username());
+        let response = self.rt.block_on(self.session.mercury().get(uri)).map_err(|_| anyhow::Error::msg("Couldn't get the playlist list from Spotify"))?;
+
+        let data = response.payload.first().context("Spotify sent an empty playlist list")?;
+        let list: SelectedListContent = protobuf::parse_from_bytes(data)?;
+
+        // Folders show up as their own start and end markers, only the playlists matter here.
+        Ok(
+            list.get_contents().get_items().iter()
+                .filter_map(|item| item.get_uri().strip_prefix("spotify:playlist:"))
+                .filter_map(|id| SpotifyId::from_base62(id).ok())
+                .collect()
+        )
+    }
+
+    fn librespot_user_playlists(&self) -> Result<Page<SimplifiedPlaylist>> {
+        let ids = self.rootlist()?;
+        let playlists = self.rt.block_on(join_all(ids.iter().map(|id| Playlist::get(&self.session, *id))));
+
+        // Librespot doesn't know whether a playlist is public or collaborative. This list is only used
+        // without the Web API, and then nothing can be edited, so it doesn't matter much.
+        let items: Vec<serde_json::Value> = ids.iter().zip(playlists).filter_map(|(id, playlist)| {
+            let playlist = playlist.ok()?;
+
+            Some(json!({
+                "collaborative": false,
+                "external_urls": {},
+                "href": "",
+                "id": id.to_base62(),
+                "images": [],
+                "name": playlist.name,
+                "owner": {
+                    "external_urls": {},
+                    "href": "",
+                    "id": playlist.user,
+                    "type": "user",
+                    "uri": format!("spotify:user:{}", playlist.user)
+                },
+                "public": null,
+                "snapshot_id": base64::encode(&playlist.revision),
+                "tracks": { "total": playlist.tracks.len() },
+                "type": "playlist",
+                "uri": id.to_uri()
+            }))
+        }).collect();
+
+        let page = json!({
+            "href": "",
+            "limit": items.len(),
+            "next": null,
+            "offset": 0,
+            "previous": null,
+            "total": items.len(),
+            "items": items
+        });
+
+        Ok(serde_json::from_value(page)?)
+    }
+
+    fn librespot_track(&self, track_id: &str) -> Result<TrackInfo> {
+        let track: Track = self.get(track_id)?;
+        Ok(self.track_info(track))
+    }
+
+    fn track_info(&self, track: Track) -> TrackInfo {
+        TrackInfo::new(
+            track.id.to_base62(),
+            track.name,
+            track.duration.max(0) as u32,
+            0,
+            track.album.to_base62(),
+            self.album_name(track.album),
+            self.artist_names(&track.artists)
+        )
+    }
+
+    fn librespot_album(&self, album_id: &str) -> Result<AlbumInfo> {
+        let album: Album = self.get(album_id)?;
+
+        Ok(AlbumInfo::new(
+            album.id.to_base62(),
+            album.name,
+            album.tracks.iter().map(|t| t.to_base62()).collect(),
+            self.artist_names(&album.artists)
+        ))
+    }
+
+    // Librespot doesn't know release dates, labels or disc numbers, so this is as close as it gets.
+    fn librespot_album_details(&self, album_id: &str) -> Result<AlbumInfo> {
+        let album = self.librespot_album(album_id)?;
+        let tracks = self.tracks(album.tracks())?;
+
+        let tracklist = tracks.into_iter().enumerate().map(|(idx, track)| {
+            AlbumTrack::new(track.id().clone(), track.name().clone(), 1, idx as u32 + 1, *track.duration(), track.artists().clone())
+        }).collect();
+
+        Ok(album.with_tracklist(tracklist))
+    }
+}
+
+impl MetadataProvider for LibrespotProvider {
+    // The Web API's list says which playlists are public or collaborative, librespot's can't.
+    fn user_playlists(&self) -> Result<Page<SimplifiedPlaylist>> {
+        match self.fallback.as_ref() {
+            Some(fallback) => fallback.user_playlists(),
+            None => self.librespot_user_playlists()
+        }
+    }
+
+    // Librespot doesn't know when or by whom items were added, and leaves local files out,
+    // so its positions don't match Spotify's. It's only good enough when nothing can be edited anyway.
+    fn playlist_tracks(&self, playlist_id: &str) -> Result<Vec<PlaylistItem>> {
+        if let Some(fallback) = self.fallback.as_ref() {
+            return fallback.playlist_tracks(playlist_id);
+        }
+
+        let playlist: Playlist = self.get(playlist_id)?;
+
+        Ok(playlist.tracks.iter().map(|id| {
+            let kind = match id.audio_type {
+                SpotifyAudioType::Track => PlaylistItemKind::Track(id.to_base62()),
+                SpotifyAudioType::Podcast => PlaylistItemKind::Episode(id.to_base62()),
+                SpotifyAudioType::NonPlayable => PlaylistItemKind::Unavailable
+            };
+
+            PlaylistItem {
+                uri: id.to_uri(),
+                kind,
+                name: String::new(),
+
+                added_at: None,
+                added_by: None
+            }
+        }).collect())
+    }
+
+    fn track(&self, track_id: &str) -> Result<TrackInfo> {
+        match self.librespot_track(track_id) {
+            Ok(track) => Ok(track),
+            Err(error) => self.fallback.as_ref().map(|f| f.track(track_id)).unwrap_or(Err(error))
+        }
+    }
+
+    fn tracks(&self, track_ids: &[String]) -> Result<Vec<TrackInfo>> {
+        let ids = track_ids.iter().filter_map(|id| SpotifyId::from_base62(id).ok()).collect::<Vec<SpotifyId>>();
+        let tracks = self.rt.block_on(join_all(ids.iter().map(|id| Track::get(&self.session, *id))));
+
+        let mut results = Vec::new();
+        let mut missing = Vec::new();
+
+        for (id, track) in ids.iter().zip(tracks) {
+            match track {
+                Ok(track) => results.push(self.track_info(track)),
+                Err(_) => missing.push(id.to_base62())
+            }
+        }
+
+        if !missing.is_empty() {
+            if let Some(fallback) = self.fallback.as_ref() {
+                // What librespot did find is still worth keeping.
+                match fallback.tracks(&missing) {
+                    Ok(tracks) => results.extend(tracks),
+                    Err(error) if results.is_empty() => return Err(error),
+                    Err(error) => println!("Couldn't fetch {} tracks: {}", missing.len(), error.to_string())
+                }
+            }
+        }
+
+        Ok(results)
+    }
+
+    fn album(&self, album_id: &str) -> Result<AlbumInfo> {
+        match self.librespot_album(album_id) {
+            Ok(album) => Ok(album),
+            Err(error) => self.fallback.as_ref().map(|f| f.album(album_id)).unwrap_or(Err(error))
+        }
+    }
+
+    fn album_details(&self, album_id: &str) -> Result<AlbumInfo> {
+        match self.fallback.as_ref().map(|f| f.album_details(album_id)) {
+            Some(Ok(album)) => Ok(album),
+            _ => self.librespot_album_details(album_id)
+        }
+    }
+
+    fn artist(&self, artist_id: &str) -> Result<FullArtist> {
+        self.fallback("Artist details")?.artist(artist_id)
+    }
+
+    fn artist_albums(&self, artist_id: &str) -> Result<Vec<AlbumSummary>> {
+        self.fallback("Artist discographies")?.artist_albums(artist_id)
+    }
+
+    fn artist_top_tracks(&self, artist_id: &str) -> Result<Vec<TrackInfo>> {
+        self.fallback("Artist top tracks")?.artist_top_tracks(artist_id)
+    }
+
+    fn related_artists(&self, artist_id: &str) -> Result<Vec<ArtistInfo>> {
+        self.fallback("Related artists")?.related_artists(artist_id)
+    }
+
+    fn new_releases(&self) -> Result<Vec<AlbumSummary>> {
+        self.fallback("New releases")?.new_releases()
+    }
+
+    fn featured_playlists(&self) -> Result<FeaturedPlaylists> {
+        self.fallback("Featured playlists")?.featured_playlists()
+    }
+
+    fn categories(&self) -> Result<Vec<Category>> {
+        self.fallback("Browse categories")?.categories()
+    }
+
+    fn category_playlists(&self, category_id: &str) -> Result<Vec<SimplifiedPlaylist>> {
+        self.fallback("Category playlists")?.category_playlists(category_id)
+    }
+
+    fn recently_played(&self) -> Result<Vec<PlayedTrack>> {
+        self.fallback("Recently played tracks")?.recently_played()
+    }
+
+    fn top_tracks(&self, term: TopTerm) -> Result<Vec<TrackInfo>> {
+        self.fallback("Top tracks")?.top_tracks(term)
+    }
+
+    fn top_artists(&self, term: TopTerm) -> Result<Vec<ArtistInfo>> {
+        self.fallback("Top artists")?.top_artists(term)
+    }
+
+    fn audio_features(&self, track_ids: &[String]) -> Result<Vec<AudioFeatures>> {
+        self.fallback("Audio features")?.audio_features(track_ids)
+    }
+
+    fn recommendations(&self, seeds: &[RecommendationSeed], attributes: &[AttributeRange], limit: u32) -> Result<Vec<String>> {
+        self.fallback("Recommendations")?.recommendations(seeds, attributes, limit)
+    }
+
+    fn search(&self, query: &str, search_type: SearchType, market: Option<&str>, limit: u32, offset: u32) -> Result<SearchResult> {
+        self.fallback("Searching")?.search(query, search_type, market, limit, offset)
+    }
+
+    fn current_user_id(&self) -> Result<String> {
+        match self.fallback.as_ref() {
+            Some(fallback) => fallback.current_user_id(),
+            // The username librespot logged in with is also the user's id.
+            None => Ok(self.session.username())
+        }
+    }
+
+    fn playlist_snapshot_id(&self, playlist_id: &str) -> Result<String> {
+        self.fallback("Playlist snapshots")?.playlist_snapshot_id(playlist_id)
+    }
+
+    fn playlist_description(&self, playlist_id: &str) -> Result<String> {
+        self.fallback("Playlist descriptions")?.playlist_description(playlist_id)
+    }
+
+    fn saved_tracks(&self) -> Result<Vec<TrackInfo>> {
+        self.fallback("Liked Songs")?.saved_tracks()
+    }
+
+    fn saved_albums(&self) -> Result<Vec<AlbumInfo>> {
+        self.fallback("Saved albums")?.saved_albums()
+    }
+
+    fn followed_artists(&self) -> Result<Vec<ArtistInfo>> {
+        self.fallback("Followed artists")?.followed_artists()
+    }
+
+    fn is_throttled(&self) -> bool {
+        self.fallback.as_ref().map(|f| f.is_throttled()).unwrap_or(false)
+    }
+}
@@ -1,130 +1,609 @@
+pub mod auth;
 pub mod cache;
+pub mod executor;
+pub mod provider;
+pub mod web_provider;
+pub mod fixture_provider;
+pub mod librespot_provider;
 
-use std::env;
 use std::sync::{Arc, Mutex};
 
 use anyhow::{Context, Result};
 
 use rspotify::model::page::Page;
-use rspotify::model::track::FullTrack;
-use rspotify::model::artist::FullArtist;
 use rspotify::model::search::SearchResult;
-use rspotify::model::album::SimplifiedAlbum;
-use rspotify::model::playlist::SimplifiedPlaylist;
-
+use rspotify::model::category::Category;
+use rspotify::model::playlist::{FullPlaylist, SimplifiedPlaylist};
 use rspotify::senum::SearchType;
-use rspotify::blocking::client::Spotify;
-use rspotify::blocking::oauth2::{SpotifyClientCredentials, SpotifyOAuth};
 
 use cache::*;
+use provider::{
+    unsupported, AlbumSummary, AttributeRange, ConnectDevice, FeaturedPlaylists, LibraryEditor, MetadataProvider, PlayedTrack, PlaylistChanges,
+    PlaylistEditor, PlaylistItem, RecommendationSeed, RemoteCommand, RemoteControl, RemotePlayback, TopTerm
+};
+use executor::{RequestExecutor, RequestHandle};
+use web_provider::WebAPIProvider;
 
 pub struct SpotifyAPIHandler {
-    api_client: Spotify,
-    cache_handler: Arc<Mutex<APICacheHandler>>
+    provider: Box<dyn MetadataProvider>,
+    // Changing the user's playlists and library or controlling other devices is only done through the Web API.
+    web_api: Option<Arc<WebAPIProvider>>,
+    cache_handler: Arc<Mutex<APICacheHandler>>,
+    executor: RequestExecutor
 }
 
 impl SpotifyAPIHandler {
-    pub fn init(cache_handler: Arc<Mutex<APICacheHandler>>) -> Result<SpotifyAPIHandler> {
-        let client_id = env::var("CLIENT_ID")?;
-        let client_secret = env::var("CLIENT_SECRET")?;
+    pub fn init(cache_handler: Arc<Mutex<APICacheHandler>>, provider: Box<dyn MetadataProvider>, web_api: Option<Arc<WebAPIProvider>>) -> SpotifyAPIHandler {
+        SpotifyAPIHandler {
+            provider,
+            web_api,
+            cache_handler,
+            executor: RequestExecutor::init()
+        }
+    }
+
+    fn playlist_editor(&self, feature: &str) -> Result<&dyn PlaylistEditor> {
+        self.web_api.as_deref().map(|api| api as &dyn PlaylistEditor).ok_or_else(|| unsupported(feature))
+    }
+
+    fn library_editor(&self, feature: &str) -> Result<&dyn LibraryEditor> {
+        self.web_api.as_deref().map(|api| api as &dyn LibraryEditor).ok_or_else(|| unsupported(feature))
+    }
+
+    fn remote_control(&self, feature: &str) -> Result<&dyn RemoteControl> {
+        self.web_api.as_deref().map(|api| api as &dyn RemoteControl).ok_or_else(|| unsupported(feature))
+    }
 
-        let mut oauth = SpotifyOAuth::default()
-            .scope("playlist-read-private")
-            .redirect_uri("http://localhost:8888/callback")
-            .client_id(&client_id)
-            .client_secret(&client_secret)
-            .build()
-        ;
+    /// Runs a request in the background. Requests with the same key share a single handle while in flight.
+    pub fn request<T, F>(self: &Arc<Self>, key: String, request: F) -> RequestHandle<T>
+    where
+        T: Send + Sync + 'static,
+        F: FnOnce(&SpotifyAPIHandler) -> Result<T> + Send + 'static
+    {
+        let handler = self.clone();
+        self.executor.submit(key, move || request(&handler))
+    }
 
-        let token = rspotify::blocking::util::get_token(&mut oauth).context("Failed to get API token")?;
-        let api_credentials = SpotifyClientCredentials::default().token_info(token);
-        let api_client = Spotify::default().client_credentials_manager(api_credentials).build();
+    pub fn flush_cache(&self) {
+        if let Ok(mut lock) = self.cache_handler.lock() {
+            lock.flush();
+        }
+    }
 
-        let handler = SpotifyAPIHandler {
-            api_client,
-            cache_handler
-        };
+    /// Whether Spotify asked us to slow down and requests are currently on hold.
+    pub fn is_throttled(&self) -> bool {
+        self.provider.is_throttled()
+    }
 
-        Ok(handler)
+    pub fn get_user_playlists(&self) -> Result<Page<SimplifiedPlaylist>> {
+        self.provider.user_playlists()
     }
 
-    pub fn get_user_playlists(&self) -> Option<Page<SimplifiedPlaylist>> {
-        self.api_client.current_user_playlists(10, 0).ok()
+    pub fn get_playlist_tracks(&self, playlist_id: &str) -> Result<Vec<PlaylistItem>> {
+        self.provider.playlist_tracks(playlist_id)
     }
 
-    pub fn remove_track_from_playlist(&self, playlist_id: &str, track_id: &str) -> bool {
-        let user_id = self.api_client.me().unwrap().id;
-        self.api_client.user_playlist_remove_all_occurrences_of_tracks(&user_id, playlist_id, &[track_id.to_string()], None).is_ok()
+    pub fn get_current_user_id(&self) -> Result<String> {
+        self.provider.current_user_id()
     }
 
-    pub fn get_track(&self, track_id: String) -> Result<TrackInfo> {
+    pub fn remove_tracks_from_playlist(&self, playlist_id: &str, tracks: &[(String, usize)], snapshot_id: &str) -> Result<String> {
+        self.playlist_editor("Removing tracks from playlists")?.remove_tracks_from_playlist(playlist_id, tracks, snapshot_id)
+    }
+
+    pub fn add_tracks_to_playlist(&self, playlist_id: &str, track_ids: &[String], position: Option<usize>) -> Result<()> {
+        self.playlist_editor("Adding tracks to playlists")?.add_tracks_to_playlist(playlist_id, track_ids, position)
+    }
+
+    /// Moves a range of tracks as they were at `snapshot_id`, Spotify rejects the move if that can't be done anymore.
+    /// Returns the playlist's new snapshot id.
+    pub fn reorder_playlist_tracks(&self, playlist_id: &str, range_start: usize, range_length: usize, insert_before: usize, snapshot_id: &str) -> Result<String> {
+        self.playlist_editor("Reordering playlists")?.reorder_playlist_tracks(playlist_id, range_start, range_length, insert_before, snapshot_id)
+    }
+
+    pub fn get_playlist_snapshot_id(&self, playlist_id: &str) -> Result<String> {
+        self.provider.playlist_snapshot_id(playlist_id)
+    }
+
+    pub fn get_playlist_description(&self, playlist_id: &str) -> Result<String> {
+        self.provider.playlist_description(playlist_id)
+    }
+
+    pub fn create_playlist(&self, name: &str, description: &str, public: bool) -> Result<FullPlaylist> {
+        self.playlist_editor("Creating playlists")?.create_playlist(name, description, public)
+    }
+
+    pub fn change_playlist_details(&self, playlist_id: &str, changes: &PlaylistChanges) -> Result<()> {
+        self.playlist_editor("Editing playlists")?.change_playlist_details(playlist_id, changes)
+    }
+
+    pub fn delete_playlist(&self, playlist_id: &str) -> Result<()> {
+        self.playlist_editor("Deleting playlists")?.delete_playlist(playlist_id)
+    }
+
+    /// Gets the user's Liked Songs, adding them to the cache along the way.
+    pub fn get_saved_tracks(&self) -> Result<Vec<TrackInfo>> {
+        let tracks = self.provider.saved_tracks()?;
+
         if let Ok(mut lock) = self.cache_handler.lock() {
-            if let Some(unit) = lock.try_get_track(&track_id) {
-                Ok(unit)
+            lock.add_track_units(&tracks);
+        }
+
+        Ok(tracks)
+    }
+
+    pub fn set_tracks_saved(&self, track_ids: &[String], saved: bool) -> Result<()> {
+        if saved {
+            self.library_editor("Saving tracks")?.save_tracks(track_ids)
+        }
+        else {
+            self.library_editor("Removing saved tracks")?.remove_saved_tracks(track_ids)
+        }
+    }
+
+    /// The library as it was cached on the last run, so it can be shown before the requests finish.
+    pub fn get_cached_library(&self) -> (Vec<AlbumInfo>, Vec<ArtistInfo>) {
+        if let Ok(lock) = self.cache_handler.lock() {
+            (lock.saved_albums(), lock.followed_artists())
+        }
+        else {
+            (Vec::new(), Vec::new())
+        }
+    }
+
+    pub fn get_cached_playlists(&self) -> Vec<CachedPlaylist> {
+        if let Ok(lock) = self.cache_handler.lock() {
+            lock.user_playlists()
+        }
+        else {
+            Vec::new()
+        }
+    }
+
+    pub fn get_cached_playlist_items(&self, playlist_id: &str, snapshot_id: &str) -> Option<Vec<PlaylistItem>> {
+        if let Ok(lock) = self.cache_handler.lock() {
+            lock.try_get_playlist_items(playlist_id, snapshot_id)
+        }
+        else {
+            None
+        }
+    }
+
+    pub fn cache_playlists(&self, playlists: Vec<CachedPlaylist>) {
+        if let Ok(mut lock) = self.cache_handler.lock() {
+            lock.set_user_playlists(playlists);
+        }
+    }
+
+    pub fn get_saved_albums(&self) -> Result<Vec<AlbumInfo>> {
+        let albums = self.provider.saved_albums()?;
+        self.cache_saved_albums(&albums);
+
+        Ok(albums)
+    }
+
+    pub fn cache_saved_albums(&self, albums: &[AlbumInfo]) {
+        if let Ok(mut lock) = self.cache_handler.lock() {
+            lock.set_saved_albums(albums);
+        }
+    }
+
+    pub fn set_albums_saved(&self, album_ids: &[String], saved: bool) -> Result<()> {
+        if saved {
+            self.library_editor("Saving albums")?.save_albums(album_ids)
+        }
+        else {
+            self.library_editor("Removing saved albums")?.remove_saved_albums(album_ids)
+        }
+    }
+
+    pub fn get_followed_artists(&self) -> Result<Vec<ArtistInfo>> {
+        let artists = self.provider.followed_artists()?;
+        self.cache_followed_artists(&artists);
+
+        Ok(artists)
+    }
+
+    pub fn cache_followed_artists(&self, artists: &[ArtistInfo]) {
+        if let Ok(mut lock) = self.cache_handler.lock() {
+            lock.set_followed_artists(artists);
+        }
+    }
+
+    pub fn set_artists_followed(&self, artist_ids: &[String], followed: bool) -> Result<()> {
+        if followed {
+            self.library_editor("Following artists")?.follow_artists(artist_ids)
+        }
+        else {
+            self.library_editor("Unfollowing artists")?.unfollow_artists(artist_ids)
+        }
+    }
+
+    pub fn get_artist(&self, artist_id: String) -> Result<ArtistInfo> {
+        if let Ok(lock) = self.cache_handler.lock() {
+            if let Some(unit) = lock.try_get_artist(&artist_id) {
+                return Ok(unit);
             }
-            else {
-                let track_data = self.api_client.track(&track_id).ok().context("Couldn't find track on API")?;
-                lock.add_track_unit(track_data).context("Failed to add track to cache")
+        }
+
+        self.provider.artist(&artist_id).map(ArtistInfo::from_api_data)
+    }
+
+    // The cache is only locked around lookups and inserts, so requests running
+    // on other threads don't have to wait on this one's network round trip.
+    pub fn get_track(&self, track_id: String) -> Result<TrackInfo> {
+        if let Ok(lock) = self.cache_handler.lock() {
+            if let Some(unit) = lock.try_get_track(&track_id) {
+                return Ok(unit);
             }
         }
+
+        let track_data = self.provider.track(&track_id).context("Couldn't find track on API")?;
+
+        if let Ok(mut lock) = self.cache_handler.lock() {
+            Ok(lock.add_track_unit(track_data))
+        }
         else {
             Err(anyhow::Error::msg("Couldn't lock API cache handler"))
         }
     }
 
-    pub fn get_album(&self, album_id: String) -> Option<AlbumInfo> {
+    /// Looks tracks up in the cache, with None for the ones that aren't in it.
+    pub fn get_cached_tracks(&self, track_ids: &[String]) -> Vec<Option<TrackInfo>> {
+        if let Ok(lock) = self.cache_handler.lock() {
+            track_ids.iter().map(|id| lock.try_get_track(id)).collect()
+        }
+        else {
+            vec![None; track_ids.len()]
+        }
+    }
+
+    /// Fetches several tracks at once, skipping the cache, and adds them to it.
+    pub fn fetch_tracks(&self, track_ids: &[String]) -> Result<Vec<TrackInfo>> {
+        let tracks = self.provider.tracks(track_ids)?;
+
         if let Ok(mut lock) = self.cache_handler.lock() {
+            lock.add_track_units(&tracks);
+        }
+
+        Ok(tracks)
+    }
+
+    /// Audio features of the tracks, from the cache where possible. Tracks without any are left out.
+    pub fn get_audio_features(&self, track_ids: &[String]) -> Result<Vec<AudioFeatures>> {
+        let mut results = Vec::new();
+        let mut missing = Vec::new();
+
+        if let Ok(lock) = self.cache_handler.lock() {
+            for id in track_ids {
+                match lock.try_get_audio_features(id) {
+                    Some(features) => results.push(features),
+                    None => missing.push(id.clone())
+                }
+            }
+        }
+
+        if !missing.is_empty() {
+            let fetched = self.provider.audio_features(&missing)?;
+
+            if let Ok(mut lock) = self.cache_handler.lock() {
+                lock.add_audio_features_units(&fetched);
+            }
+
+            results.extend(fetched);
+        }
+
+        Ok(results)
+    }
+
+    pub fn get_album(&self, album_id: String) -> Option<AlbumInfo> {
+        if let Ok(lock) = self.cache_handler.lock() {
             let cache_result = lock.try_get_album(&album_id);
 
             if cache_result.is_some() {
-                cache_result
-            }
-            else {
-                self.api_client.album(&album_id).ok().map(| album_data | lock.add_album_unit(album_data))
+                return cache_result;
             }
         }
+
+        let album_data = self.provider.album(&album_id).ok()?;
+
+        if let Ok(mut lock) = self.cache_handler.lock() {
+            Some(lock.add_album_unit(album_data))
+        }
         else {
             None
         }
     }
 
-    pub fn get_all_albums_for_artist(&self, artist_id: String) -> Option<Page<SimplifiedAlbum>> {
-        self.api_client.artist_albums(&artist_id, None, None, Some(10), None).ok()
+    /// Gets an album with its details, going to Spotify if the cached one only has the track ids.
+    pub fn get_album_details(&self, album_id: &str) -> Result<AlbumInfo> {
+        if let Ok(lock) = self.cache_handler.lock() {
+            if let Some(album) = lock.try_get_album(album_id).filter(|a| a.has_details()) {
+                return Ok(album);
+            }
+        }
+
+        let album = self.provider.album_details(album_id)?;
+
+        if let Ok(mut lock) = self.cache_handler.lock() {
+            lock.add_album_unit(album.clone());
+        }
+
+        Ok(album)
     }
 
-    pub fn search_tracks(&self, query: String) -> Option<Page<FullTrack>> {
-        let result = self.api_client.search(
-            &query,
-            SearchType::Track,
-            10,
-            0,
-            None,
-            None
-        );
+    pub fn get_artist_discography(&self, artist_id: &str) -> Result<Vec<AlbumSummary>> {
+        self.provider.artist_albums(artist_id)
+    }
+
+    pub fn get_artist_top_tracks(&self, artist_id: &str) -> Result<Vec<TrackInfo>> {
+        let tracks = self.provider.artist_top_tracks(artist_id)?;
 
-        if let Ok(SearchResult::Tracks(data)) = result {
-            Some(data)
+        if let Ok(mut lock) = self.cache_handler.lock() {
+            lock.add_track_units(&tracks);
         }
-        else {
-            None
+
+        Ok(tracks)
+    }
+
+    pub fn get_related_artists(&self, artist_id: &str) -> Result<Vec<ArtistInfo>> {
+        self.provider.related_artists(artist_id)
+    }
+
+    pub fn get_new_releases(&self) -> Result<Vec<AlbumSummary>> {
+        if let Some(albums) = self.cache_handler.lock().ok().and_then(|lock| lock.try_get_new_releases()) {
+            return Ok(albums);
         }
+
+        let albums = self.provider.new_releases()?;
+
+        if let Ok(mut lock) = self.cache_handler.lock() {
+            lock.set_new_releases(&albums);
+        }
+
+        Ok(albums)
     }
 
-    pub fn search_artists(&self, query: String) -> Option<Page<FullArtist>> {
-        let result = self.api_client.search(
-            &query,
-            SearchType::Artist,
-            10,
-            0,
-            None,
-            None
-        );
+    pub fn get_featured_playlists(&self) -> Result<FeaturedPlaylists> {
+        if let Some(featured) = self.cache_handler.lock().ok().and_then(|lock| lock.try_get_featured_playlists()) {
+            return Ok(featured);
+        }
+
+        let featured = self.provider.featured_playlists()?;
 
-        if let Ok(SearchResult::Artists(data)) = result {
-            Some(data)
+        if let Ok(mut lock) = self.cache_handler.lock() {
+            lock.set_featured_playlists(&featured);
         }
-        else {
-            None
+
+        Ok(featured)
+    }
+
+    pub fn get_categories(&self) -> Result<Vec<Category>> {
+        if let Some(categories) = self.cache_handler.lock().ok().and_then(|lock| lock.try_get_categories()) {
+            return Ok(categories);
+        }
+
+        let categories = self.provider.categories()?;
+
+        if let Ok(mut lock) = self.cache_handler.lock() {
+            lock.set_categories(&categories);
+        }
+
+        Ok(categories)
+    }
+
+    pub fn get_category_playlists(&self, category_id: &str) -> Result<Vec<SimplifiedPlaylist>> {
+        if let Some(playlists) = self.cache_handler.lock().ok().and_then(|lock| lock.try_get_category_playlists(category_id)) {
+            return Ok(playlists);
+        }
+
+        let playlists = self.provider.category_playlists(category_id)?;
+
+        if let Ok(mut lock) = self.cache_handler.lock() {
+            lock.set_category_playlists(category_id, &playlists);
+        }
+
+        Ok(playlists)
+    }
+
+    pub fn get_recently_played(&self) -> Result<Vec<PlayedTrack>> {
+        let played = self.provider.recently_played()?;
+
+        if let Ok(mut lock) = self.cache_handler.lock() {
+            let tracks: Vec<TrackInfo> = played.iter().map(|p| p.track.clone()).collect();
+            lock.add_track_units(&tracks);
         }
+
+        Ok(played)
+    }
+
+    pub fn get_top_tracks(&self, term: TopTerm) -> Result<Vec<TrackInfo>> {
+        let tracks = self.provider.top_tracks(term)?;
+
+        if let Ok(mut lock) = self.cache_handler.lock() {
+            lock.add_track_units(&tracks);
+        }
+
+        Ok(tracks)
+    }
+
+    pub fn get_top_artists(&self, term: TopTerm) -> Result<Vec<ArtistInfo>> {
+        self.provider.top_artists(term)
+    }
+
+    pub fn get_devices(&self) -> Result<Vec<ConnectDevice>> {
+        self.remote_control("Spotify Connect")?.devices()
+    }
+
+    pub fn get_remote_playback(&self) -> Result<Option<RemotePlayback>> {
+        self.remote_control("Spotify Connect")?.remote_playback()
+    }
+
+    pub fn transfer_playback(&self, device_id: &str, play: bool) -> Result<()> {
+        self.remote_control("Spotify Connect")?.transfer_playback(device_id, play)
     }
-}
\ No newline at end of file
+
+    pub fn remote_command(&self, device_id: &str, command: RemoteCommand) -> Result<()> {
+        self.remote_control("Spotify Connect")?.remote_command(device_id, command)
+    }
+
+    /// Recommended tracks, with their metadata looked up so they can be shown right away.
+    pub fn get_recommendations(&self, seeds: &[RecommendationSeed], attributes: &[AttributeRange], limit: u32) -> Result<Vec<TrackInfo>> {
+        let track_ids = self.provider.recommendations(seeds, attributes, limit)?;
+        self.fetch_tracks(&track_ids)
+    }
+
+    pub fn search(&self, query: &str, search_type: SearchType, market: Option<&str>, limit: u32, offset: u32) -> Result<SearchResult> {
+        self.provider.search(query, search_type, market, limit, offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixture_provider::FixtureProvider;
+    use provider::PlaylistItemKind;
+
+    use std::path::{Path, PathBuf};
+
+    const BLUE_SONGS: &str = "37i9dQZF1DX0XUsuxWHRQd";
+    const EIGHTIES: &str = "3cEYpjA9oz9GiPac4AsH4n";
+    const BLUE_MONDAY: &str = "4KmjHVwXYvrKiJcRIjM6Mp";
+
+    fn copy_dir(from: &Path, to: &Path) {
+        std::fs::create_dir_all(to).unwrap();
+
+        for entry in std::fs::read_dir(from).unwrap().flatten() {
+            let target = to.join(entry.file_name());
+
+            if entry.path().is_dir() {
+                copy_dir(&entry.path(), &target);
+            }
+            else {
+                std::fs::copy(entry.path(), target).unwrap();
+            }
+        }
+    }
+
+    /// A handler reading from a copy of the example fixtures, so tests can change them, with a fresh cache.
+    fn fixture_handler(name: &str) -> (SpotifyAPIHandler, PathBuf) {
+        let path = std::env::temp_dir().join(format!("imguify-api-test-{}-{}", name, std::process::id()));
+        std::fs::remove_dir_all(&path).ok();
+
+        copy_dir(&Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures"), &path.join("fixtures"));
+
+        let cache_handler = Arc::new(Mutex::new(APICacheHandler::open(Some(path.join("cache")))));
+        let provider = FixtureProvider::init(path.join("fixtures")).unwrap();
+
+        (SpotifyAPIHandler::init(cache_handler, Box::new(provider), None), path)
+    }
+
+    fn track_names(result: SearchResult) -> Vec<String> {
+        match result {
+            SearchResult::Tracks(page) => page.items.into_iter().map(|t| t.name).collect(),
+            _ => panic!("Expected tracks")
+        }
+    }
+
+    #[test]
+    fn search_filters_by_text_and_artist() {
+        let (api, path) = fixture_handler("search");
+
+        let tracks = api.search("blue", SearchType::Track, None, 10, 0).unwrap();
+        assert_eq!(track_names(tracks), vec!["Blue Monday", "Blue Velvet"]);
+
+        let tracks = api.search("blue artist:\"new order\"", SearchType::Track, None, 10, 0).unwrap();
+        assert_eq!(track_names(tracks), vec!["Blue Monday"]);
+
+        match api.search("eighties", SearchType::Playlist, None, 10, 0).unwrap() {
+            SearchResult::Playlists(page) => assert_eq!(page.items.len(), 1),
+            _ => panic!("Expected playlists")
+        }
+
+        assert!(api.search("blue", SearchType::Album, None, 10, 0).is_err());
+
+        std::fs::remove_dir_all(&path).ok();
+    }
+
+    #[test]
+    fn search_pages_through_results() {
+        let (api, path) = fixture_handler("search_pages");
+
+        match api.search("", SearchType::Track, None, 2, 0).unwrap() {
+            SearchResult::Tracks(page) => {
+                assert_eq!(page.items.len(), 2);
+                assert_eq!(page.total, 3);
+                assert!(page.next.is_some());
+            }
+            _ => panic!("Expected tracks")
+        }
+
+        match api.search("", SearchType::Track, None, 2, 2).unwrap() {
+            SearchResult::Tracks(page) => {
+                assert_eq!(page.items.len(), 1);
+                assert!(page.next.is_none());
+            }
+            _ => panic!("Expected tracks")
+        }
+
+        std::fs::remove_dir_all(&path).ok();
+    }
+
+    #[test]
+    fn playlist_tracks_keep_their_order() {
+        let (api, path) = fixture_handler("playlist_tracks");
+
+        let playlists = api.get_user_playlists().unwrap();
+        assert_eq!(playlists.items.len(), 2);
+
+        let items = api.get_playlist_tracks(BLUE_SONGS).unwrap();
+        let uris: Vec<&str> = items.iter().map(|item| item.uri.as_str()).collect();
+
+        assert_eq!(uris, vec!["spotify:track:4KmjHVwXYvrKiJcRIjM6Mp", "spotify:track:6aAkbn7uXKgNNYpnB9ZK5K"]);
+        assert_eq!(items[0].added_at.as_deref(), Some("2021-03-01"));
+        assert_eq!(items[0].added_by.as_deref(), Some("Fixture User"));
+
+        assert!(api.get_playlist_tracks("missing").is_err());
+
+        assert_eq!(api.get_playlist_description(BLUE_SONGS).unwrap(), "Songs with blue in the title.");
+        assert!(api.get_playlist_description("missing").is_err());
+
+        std::fs::remove_dir_all(&path).ok();
+    }
+
+    #[test]
+    fn local_files_keep_their_position() {
+        let (api, path) = fixture_handler("local_files");
+
+        let items = api.get_playlist_tracks(EIGHTIES).unwrap();
+
+        // Positions are what removing and moving tracks go by, so every item needs its row.
+        assert_eq!(items.len(), 3);
+        assert!(items[0].kind == PlaylistItemKind::Track(BLUE_MONDAY.to_string()));
+        assert!(items[1].kind == PlaylistItemKind::Local);
+        assert_eq!(items[1].name, "Demo Tape");
+        assert_eq!(items[1].added_at.as_deref(), Some("2021-03-02"));
+        assert!(items[2].kind == PlaylistItemKind::Track(String::from("1z9LhZrvdpqXWGZ7oVo1Au")));
+
+        std::fs::remove_dir_all(&path).ok();
+    }
+
+    #[test]
+    fn tracks_come_from_the_cache_once_loaded() {
+        let (api, path) = fixture_handler("cache_hits");
+
+        assert!(api.get_cached_tracks(&[BLUE_MONDAY.to_string()])[0].is_none());
+
+        let track = api.get_track(BLUE_MONDAY.to_string()).unwrap();
+        assert_eq!(track.album_name(), "Power, Corruption & Lies");
+
+        // Without the fixture, only the cache can still have it.
+        std::fs::remove_file(path.join("fixtures/tracks").join(format!("{}.json", BLUE_MONDAY))).unwrap();
+
+        let cached = api.get_track(BLUE_MONDAY.to_string()).unwrap();
+        assert_eq!(cached.name(), "Blue Monday");
+        assert!(api.get_cached_tracks(&[BLUE_MONDAY.to_string()])[0].is_some());
+
+        std::fs::remove_dir_all(&path).ok();
+    }
+}
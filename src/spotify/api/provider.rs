@@ -0,0 +1,467 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use rspotify::model::page::Page;
+use rspotify::model::artist::FullArtist;
+use rspotify::model::search::SearchResult;
+use rspotify::model::album::SimplifiedAlbum;
+use rspotify::model::category::Category;
+use rspotify::model::playlist::{FullPlaylist, PlaylistTrack, SimplifiedPlaylist};
+use rspotify::senum::SearchType;
+
+use super::cache::{AlbumInfo, ArtistInfo, AudioFeatures, TrackInfo};
+
+/// A source of Spotify metadata.
+/// SpotifyAPIHandler keeps the cache in front of it, so implementations don't need to cache anything.
+pub trait MetadataProvider: Send + Sync {
+    fn user_playlists(&self) -> Result<Page<SimplifiedPlaylist>>;
+    fn playlist_tracks(&self, playlist_id: &str) -> Result<Vec<PlaylistItem>>;
+
+    fn track(&self, track_id: &str) -> Result<TrackInfo>;
+
+    /// Several tracks at once. Tracks that couldn't be found are left out.
+    fn tracks(&self, track_ids: &[String]) -> Result<Vec<TrackInfo>> {
+        Ok(track_ids.iter().filter_map(|id| self.track(id).ok()).collect())
+    }
+
+    /// Audio features of several tracks at once. Tracks Spotify hasn't analysed are left out.
+    fn audio_features(&self, _track_ids: &[String]) -> Result<Vec<AudioFeatures>> {
+        Err(unsupported("Audio features"))
+    }
+
+    fn album(&self, album_id: &str) -> Result<AlbumInfo>;
+
+    /// Like `album`, but with the release date, label and full tracklist.
+    fn album_details(&self, album_id: &str) -> Result<AlbumInfo> {
+        self.album(album_id)
+    }
+
+    fn artist(&self, artist_id: &str) -> Result<FullArtist>;
+
+    /// The artist's whole discography, including singles, compilations and albums they appear on.
+    fn artist_albums(&self, artist_id: &str) -> Result<Vec<AlbumSummary>>;
+
+    fn artist_top_tracks(&self, _artist_id: &str) -> Result<Vec<TrackInfo>> {
+        Err(unsupported("Artist top tracks"))
+    }
+
+    fn related_artists(&self, _artist_id: &str) -> Result<Vec<ArtistInfo>> {
+        Err(unsupported("Related artists"))
+    }
+
+    /// Ids of tracks picked from up to five seeds, within the attribute ranges.
+    fn recommendations(&self, _seeds: &[RecommendationSeed], _attributes: &[AttributeRange], _limit: u32) -> Result<Vec<String>> {
+        Err(unsupported("Recommendations"))
+    }
+
+    /// New albums in the user's market.
+    fn new_releases(&self) -> Result<Vec<AlbumSummary>> {
+        Err(unsupported("New releases"))
+    }
+
+    fn featured_playlists(&self) -> Result<FeaturedPlaylists> {
+        Err(unsupported("Featured playlists"))
+    }
+
+    /// The categories Spotify sorts its own playlists into, like moods and genres.
+    fn categories(&self) -> Result<Vec<Category>> {
+        Err(unsupported("Browse categories"))
+    }
+
+    fn category_playlists(&self, _category_id: &str) -> Result<Vec<SimplifiedPlaylist>> {
+        Err(unsupported("Category playlists"))
+    }
+
+    /// The user's last played tracks, most recent first.
+    fn recently_played(&self) -> Result<Vec<PlayedTrack>> {
+        Err(unsupported("Recently played tracks"))
+    }
+
+    fn top_tracks(&self, _term: TopTerm) -> Result<Vec<TrackInfo>> {
+        Err(unsupported("Top tracks"))
+    }
+
+    fn top_artists(&self, _term: TopTerm) -> Result<Vec<ArtistInfo>> {
+        Err(unsupported("Top artists"))
+    }
+
+    /// One page of results of a single type. `market` is a country code overriding the user's own.
+    fn search(&self, query: &str, search_type: SearchType, market: Option<&str>, limit: u32, offset: u32) -> Result<SearchResult>;
+
+    /// The id of the logged in user, which owns the playlists they can edit.
+    fn current_user_id(&self) -> Result<String> {
+        Err(unsupported("Getting the current user"))
+    }
+
+    /// The playlist's current snapshot id, which changes with every edit.
+    fn playlist_snapshot_id(&self, _playlist_id: &str) -> Result<String> {
+        Err(unsupported("Playlist snapshots"))
+    }
+
+    /// Only full playlists come with their description, not the ones in a list.
+    fn playlist_description(&self, _playlist_id: &str) -> Result<String> {
+        Err(unsupported("Playlist descriptions"))
+    }
+
+    /// The user's Liked Songs, most recently added first.
+    fn saved_tracks(&self) -> Result<Vec<TrackInfo>> {
+        Err(unsupported("Liked Songs"))
+    }
+
+    /// The user's saved albums, most recently added first.
+    fn saved_albums(&self) -> Result<Vec<AlbumInfo>> {
+        Err(unsupported("Saved albums"))
+    }
+
+    fn followed_artists(&self) -> Result<Vec<ArtistInfo>> {
+        Err(unsupported("Followed artists"))
+    }
+
+    /// Whether requests are currently held back by a rate limit.
+    fn is_throttled(&self) -> bool {
+        false
+    }
+}
+
+/// Changes to the user's playlists, which only the Web API can make.
+pub trait PlaylistEditor: Send + Sync {
+    /// Removes the items with these uris at the given positions, which have to match the playlist at `snapshot_id`.
+    /// Returns the new snapshot id.
+    fn remove_tracks_from_playlist(&self, playlist_id: &str, tracks: &[(String, usize)], snapshot_id: &str) -> Result<String>;
+
+    /// Adds tracks at `position`, or at the end if there's none.
+    fn add_tracks_to_playlist(&self, playlist_id: &str, track_ids: &[String], position: Option<usize>) -> Result<()>;
+
+    /// Moves a range of tracks in a playlist, returning the new snapshot id.
+    fn reorder_playlist_tracks(&self, playlist_id: &str, range_start: usize, range_length: usize, insert_before: usize, snapshot_id: &str) -> Result<String>;
+
+    fn create_playlist(&self, name: &str, description: &str, public: bool) -> Result<FullPlaylist>;
+
+    fn change_playlist_details(&self, playlist_id: &str, changes: &PlaylistChanges) -> Result<()>;
+
+    /// Spotify doesn't really delete playlists, this unfollows it so it's gone from the user's library.
+    fn delete_playlist(&self, playlist_id: &str) -> Result<()>;
+}
+
+/// Changes to the user's library: Liked Songs, saved albums and followed artists.
+pub trait LibraryEditor: Send + Sync {
+    fn save_tracks(&self, track_ids: &[String]) -> Result<()>;
+
+    fn remove_saved_tracks(&self, track_ids: &[String]) -> Result<()>;
+
+    fn save_albums(&self, album_ids: &[String]) -> Result<()>;
+
+    fn remove_saved_albums(&self, album_ids: &[String]) -> Result<()>;
+
+    fn follow_artists(&self, artist_ids: &[String]) -> Result<()>;
+
+    fn unfollow_artists(&self, artist_ids: &[String]) -> Result<()>;
+}
+
+/// Spotify Connect, seeing and controlling playback on the user's other devices.
+pub trait RemoteControl: Send + Sync {
+    /// The user's Spotify Connect devices, like phones and speakers.
+    fn devices(&self) -> Result<Vec<ConnectDevice>>;
+
+    /// What's playing on the active Connect device, if anything is.
+    fn remote_playback(&self) -> Result<Option<RemotePlayback>>;
+
+    /// Moves playback to another device, starting it there if `play` is set.
+    fn transfer_playback(&self, device_id: &str, play: bool) -> Result<()>;
+
+    fn remote_command(&self, device_id: &str, command: RemoteCommand) -> Result<()>;
+}
+
+/// What's at a position in a playlist. Only tracks have metadata that can be fetched.
+#[derive(Clone, PartialEq, Deserialize, Serialize)]
+pub enum PlaylistItemKind {
+    Track(String),
+    Episode(String),
+    Local,
+    Unavailable
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct PlaylistItem {
+    pub uri: String,
+    pub kind: PlaylistItemKind,
+    /// The name Spotify listed the item with, if any. Local files don't have anything else.
+    pub name: String,
+
+    pub added_at: Option<String>,
+    pub added_by: Option<String>
+}
+
+impl PlaylistItem {
+    pub fn track(track_id: String) -> PlaylistItem {
+        PlaylistItem {
+            uri: format!("spotify:track:{}", track_id),
+            kind: PlaylistItemKind::Track(track_id),
+            name: String::new(),
+
+            added_at: None,
+            added_by: None
+        }
+    }
+
+    pub fn from_api_data(item: PlaylistTrack) -> PlaylistItem {
+        let added_at = Some(item.added_at.format("%Y-%m-%d").to_string());
+        let added_by = item.added_by.map(|user| user.display_name.unwrap_or(user.id));
+
+        let (uri, kind, name) = match item.track {
+            Some(track) => {
+                let kind = if item.is_local {
+                    PlaylistItemKind::Local
+                }
+                // Episodes come back dressed up as tracks.
+                else if track.uri.starts_with("spotify:episode:") {
+                    track.id.map(PlaylistItemKind::Episode).unwrap_or(PlaylistItemKind::Unavailable)
+                }
+                else {
+                    track.id.map(PlaylistItemKind::Track).unwrap_or(PlaylistItemKind::Unavailable)
+                };
+
+                (track.uri, kind, track.name)
+            }
+            None => (String::new(), PlaylistItemKind::Unavailable, String::new())
+        };
+
+        PlaylistItem {
+            uri,
+            kind,
+            name,
+
+            added_at,
+            added_by
+        }
+    }
+
+    pub fn track_id(&self) -> Option<&String> {
+        if let PlaylistItemKind::Track(id) = &self.kind {
+            Some(id)
+        }
+        else {
+            None
+        }
+    }
+}
+
+/// Which part of an artist's discography an album is listed under.
+#[derive(Clone, Copy, PartialEq)]
+pub enum AlbumGroup {
+    Album,
+    Single,
+    Compilation,
+    AppearsOn
+}
+
+impl AlbumGroup {
+    pub const ALL: [AlbumGroup; 4] = [AlbumGroup::Album, AlbumGroup::Single, AlbumGroup::Compilation, AlbumGroup::AppearsOn];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AlbumGroup::Album => "Albums",
+            AlbumGroup::Single => "Singles and EPs",
+            AlbumGroup::Compilation => "Compilations",
+            AlbumGroup::AppearsOn => "Appears On"
+        }
+    }
+
+    fn from_api_data(group: &str) -> AlbumGroup {
+        match group {
+            "single" => AlbumGroup::Single,
+            "compilation" => AlbumGroup::Compilation,
+            "appears_on" => AlbumGroup::AppearsOn,
+            _ => AlbumGroup::Album
+        }
+    }
+}
+
+/// An album as listed in a discography, without its tracks.
+#[derive(Clone)]
+pub struct AlbumSummary {
+    pub id: String,
+    pub name: String,
+    pub group: AlbumGroup,
+    pub release_date: String,
+    pub artists: Vec<String>
+}
+
+impl AlbumSummary {
+    pub fn from_api_data(album: SimplifiedAlbum) -> Option<AlbumSummary> {
+        // The group is only there when listing an artist's albums, the type is always there.
+        let group = album.album_group.as_deref().or_else(|| album.album_type.as_deref()).unwrap_or("album");
+
+        Some(
+            AlbumSummary {
+                id: album.id?,
+                name: album.name,
+                group: AlbumGroup::from_api_data(group),
+                release_date: album.release_date.unwrap_or_default(),
+                artists: album.artists.into_iter().map(|a| a.name).collect()
+            }
+        )
+    }
+}
+
+/// Spotify's featured playlists, with the message it shows above them, like "Monday morning music".
+#[derive(Clone)]
+pub struct FeaturedPlaylists {
+    pub message: String,
+    pub playlists: Vec<SimplifiedPlaylist>
+}
+
+#[derive(Clone)]
+pub struct ConnectDevice {
+    pub id: String,
+    pub name: String,
+    /// Like "Computer" or "Speaker".
+    pub kind: String,
+    pub is_active: bool,
+    pub volume: u32
+}
+
+/// The state of playback on another device, as of when it was polled.
+#[derive(Clone)]
+pub struct RemotePlayback {
+    pub device: ConnectDevice,
+    pub is_playing: bool,
+    pub progress: u32,
+    /// None while playing something that isn't a track, like an episode.
+    pub track: Option<TrackInfo>
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum RemoteCommand {
+    Play,
+    Pause,
+    Next,
+    Previous,
+    /// A position in milliseconds.
+    Seek(u32),
+    /// A volume from 0 to 100.
+    Volume(u8)
+}
+
+/// How far back the user's top tracks and artists go.
+#[derive(Clone, Copy, PartialEq)]
+pub enum TopTerm {
+    Short,
+    Medium,
+    Long
+}
+
+impl TopTerm {
+    pub const ALL: [TopTerm; 3] = [TopTerm::Short, TopTerm::Medium, TopTerm::Long];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TopTerm::Short => "Last 4 weeks",
+            TopTerm::Medium => "Last 6 months",
+            TopTerm::Long => "All time"
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TopTerm::Short => "short_term",
+            TopTerm::Medium => "medium_term",
+            TopTerm::Long => "long_term"
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct PlayedTrack {
+    pub track: TrackInfo,
+    pub played_at: String
+}
+
+/// Spotify takes at most five seeds, of any kind, per recommendations request.
+pub const MAX_SEEDS: usize = 5;
+
+/// Something recommendations are based on. Tracks and artists keep their name to show it.
+#[derive(Clone, PartialEq)]
+pub enum RecommendationSeed {
+    Track { id: String, name: String },
+    Artist { id: String, name: String },
+    Genre(String)
+}
+
+impl RecommendationSeed {
+    /// The id of the track or artist, or the genre itself.
+    pub fn id(&self) -> &str {
+        match self {
+            RecommendationSeed::Track { id, .. } | RecommendationSeed::Artist { id, .. } => id,
+            RecommendationSeed::Genre(genre) => genre
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            RecommendationSeed::Track { name, .. } => format!("Track: {}", name),
+            RecommendationSeed::Artist { name, .. } => format!("Artist: {}", name),
+            RecommendationSeed::Genre(genre) => format!("Genre: {}", genre)
+        }
+    }
+}
+
+/// The audio attributes recommendations can be narrowed down by.
+#[derive(Clone, Copy, PartialEq)]
+pub enum TrackAttribute {
+    Energy,
+    Danceability,
+    Tempo,
+    Valence
+}
+
+impl TrackAttribute {
+    pub const ALL: [TrackAttribute; 4] = [TrackAttribute::Energy, TrackAttribute::Danceability, TrackAttribute::Tempo, TrackAttribute::Valence];
+
+    /// The name the API uses, after its `min_` and `max_` prefixes.
+    pub fn name(&self) -> &'static str {
+        match self {
+            TrackAttribute::Energy => "energy",
+            TrackAttribute::Danceability => "danceability",
+            TrackAttribute::Tempo => "tempo",
+            TrackAttribute::Valence => "valence"
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TrackAttribute::Energy => "Energy",
+            TrackAttribute::Danceability => "Danceability",
+            TrackAttribute::Tempo => "Tempo (BPM)",
+            TrackAttribute::Valence => "Valence"
+        }
+    }
+
+    /// The whole range the attribute can take. Tempo is in beats per minute, everything else goes from 0 to 1.
+    pub fn bounds(&self) -> (f32, f32) {
+        match self {
+            TrackAttribute::Tempo => (40.0, 220.0),
+            _ => (0.0, 1.0)
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct AttributeRange {
+    pub attribute: TrackAttribute,
+    pub min: f32,
+    pub max: f32
+}
+
+/// Changes to a playlist's details. Fields left as None stay the same.
+#[derive(Clone, Default)]
+pub struct PlaylistChanges {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub public: Option<bool>,
+    pub collaborative: Option<bool>
+}
+
+pub fn unsupported(feature: &str) -> anyhow::Error {
+    anyhow::Error::msg(format!("{} isn't supported by this metadata provider", feature))
+}
@@ -0,0 +1,767 @@
+use std::str::FromStr;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use rand::prelude::*;
+use serde_json::{Map, Value};
+
+use rspotify::model::page::Page;
+use rspotify::model::artist::FullArtist;
+use rspotify::model::search::SearchResult;
+use rspotify::model::PlayingItem;
+use rspotify::model::device::Device;
+use rspotify::model::category::Category;
+use rspotify::model::playlist::{FullPlaylist, SimplifiedPlaylist};
+
+use rspotify::senum::{Country, SearchType, TimeRange};
+use rspotify::client::ApiError;
+use rspotify::blocking::client::Spotify;
+
+use super::auth::APIToken;
+use super::cache::{AlbumInfo, ArtistInfo, AudioFeatures, TrackInfo};
+use super::provider::{
+    AlbumSummary, AttributeRange, ConnectDevice, FeaturedPlaylists, LibraryEditor, MetadataProvider, PlayedTrack, PlaylistChanges,
+    PlaylistEditor, PlaylistItem, RecommendationSeed, RemoteCommand, RemoteControl, RemotePlayback, TopTerm, MAX_SEEDS
+};
+
+const MAX_RETRIES: u32 = 5;
+const BACKOFF_BASE_MS: u64 = 500;
+const BACKOFF_MAX_MS: u64 = 30_000;
+
+/// Metadata straight from the Spotify Web API.
+pub struct WebAPIProvider {
+    api_token: RwLock<APIToken>,
+    api_client: RwLock<Spotify>,
+    // Where requests go instead of Spotify's Web API, like a local stand-in for testing.
+    api_prefix: Option<String>,
+    user_id: RwLock<Option<String>>,
+    user_country: RwLock<Option<Country>>,
+
+    throttled_until: RwLock<Option<Instant>>
+}
+
+impl WebAPIProvider {
+    pub fn init(api_token: APIToken, api_prefix: Option<String>) -> WebAPIProvider {
+        let api_client = build_client(&api_token, api_prefix.as_deref());
+
+        WebAPIProvider {
+            api_token: RwLock::new(api_token),
+            api_client: RwLock::new(api_client),
+            api_prefix,
+            user_id: RwLock::new(None),
+            user_country: RwLock::new(None),
+
+            throttled_until: RwLock::new(None)
+        }
+    }
+
+    fn refresh_token(&self) {
+        if let Ok(mut token) = self.api_token.write() {
+            if token.is_expired() {
+                match token.refresh() {
+                    Ok(_) => {
+                        if let Ok(mut client) = self.api_client.write() {
+                            *client = build_client(&token, self.api_prefix.as_deref());
+                        }
+                    }
+                    Err(error) => println!("Failed to refresh API token: {}", error.to_string())
+                }
+            }
+        }
+    }
+
+    /// The country of the logged in user, which browse pages are tailored to.
+    fn user_country(&self) -> Option<Country> {
+        self.current_user_id().ok()?;
+        self.user_country.read().ok().and_then(|lock| *lock)
+    }
+
+    /// Runs a request with the API client, refreshing the access token first if it expired.
+    ///
+    /// Rate limited requests are retried after the Retry-After delay Spotify sends back,
+    /// and server errors are retried with a jittered exponential backoff.
+    fn with_client<T>(&self, request: impl Fn(&Spotify) -> Result<T, failure::Error>) -> Result<T> {
+        let mut attempt = 0;
+
+        loop {
+            // Another request got rate limited, so there's no point in sending this one yet.
+            if let Some(until) = self.throttled_until.read().ok().and_then(|lock| *lock) {
+                let now = Instant::now();
+
+                if until > now {
+                    std::thread::sleep(until - now);
+                }
+            }
+
+            self.refresh_token();
+
+            let result = {
+                if let Ok(client) = self.api_client.read() {
+                    request(&client)
+                }
+                else {
+                    return Err(anyhow::Error::msg("Couldn't lock API client"));
+                }
+            };
+
+            let error = match result {
+                Ok(result) => return Ok(result),
+                Err(error) => error
+            };
+
+            let delay = match error.downcast_ref::<ApiError>() {
+                Some(ApiError::RateLimited(retry_after)) => {
+                    let delay = Duration::from_secs(retry_after.unwrap_or(1) as u64);
+
+                    if let Ok(mut lock) = self.throttled_until.write() {
+                        *lock = Some(Instant::now() + delay);
+                    }
+
+                    delay
+                }
+                Some(ApiError::Other(status)) if *status >= 500 => {
+                    let backoff = (BACKOFF_BASE_MS << attempt).min(BACKOFF_MAX_MS);
+                    Duration::from_millis(backoff + thread_rng().gen_range(0..=backoff / 2))
+                }
+                _ => return Err(anyhow::Error::msg(error.to_string()))
+            };
+
+            if attempt >= MAX_RETRIES {
+                return Err(anyhow::Error::msg(format!("Request failed after {} retries: {}", MAX_RETRIES, error.to_string())));
+            }
+
+            attempt += 1;
+            std::thread::sleep(delay);
+        }
+    }
+}
+
+impl MetadataProvider for WebAPIProvider {
+    fn user_playlists(&self) -> Result<Page<SimplifiedPlaylist>> {
+        let mut playlists = self.with_client(|client| client.current_user_playlists(50, 0))?;
+
+        while playlists.next.is_some() {
+            let offset = playlists.items.len() as u32;
+            let page = self.with_client(|client| client.current_user_playlists(50, offset))?;
+
+            if page.items.is_empty() {
+                break;
+            }
+
+            playlists.next = page.next;
+            playlists.items.extend(page.items);
+        }
+
+        Ok(playlists)
+    }
+
+    fn playlist_tracks(&self, playlist_id: &str) -> Result<Vec<PlaylistItem>> {
+        let user_id = self.current_user_id()?;
+        let mut results = Vec::new();
+
+        loop {
+            let offset = results.len() as u32;
+            let page = self.with_client(|client| client.user_playlist_tracks(&user_id, playlist_id, None, 100, offset, None))?;
+
+            let page_len = page.items.len();
+            results.extend(page.items.into_iter().map(PlaylistItem::from_api_data));
+
+            if page.next.is_none() || page_len == 0 {
+                break;
+            }
+        }
+
+        Ok(results)
+    }
+
+    fn track(&self, track_id: &str) -> Result<TrackInfo> {
+        let track = self.with_client(|client| client.track(track_id))?;
+        TrackInfo::from_api_data(track).context("Track is missing its id or album")
+    }
+
+    fn tracks(&self, track_ids: &[String]) -> Result<Vec<TrackInfo>> {
+        let mut results = Vec::new();
+        let mut last_error = None;
+
+        // Spotify takes at most 50 ids per request. A failed chunk doesn't take the others down with it.
+        for chunk in track_ids.chunks(50) {
+            let ids = chunk.iter().map(|id| id.as_str()).collect::<Vec<&str>>();
+
+            match self.with_client(|client| client.tracks(ids.clone(), None)) {
+                Ok(tracks) => results.extend(tracks.tracks.into_iter().filter_map(TrackInfo::from_api_data)),
+                Err(error) => {
+                    println!("Couldn't fetch {} tracks: {}", chunk.len(), error.to_string());
+                    last_error = Some(error);
+                }
+            }
+        }
+
+        match last_error {
+            Some(error) if results.is_empty() => Err(error),
+            _ => Ok(results)
+        }
+    }
+
+    fn album(&self, album_id: &str) -> Result<AlbumInfo> {
+        let mut album = self.with_client(|client| client.album(album_id))?;
+
+        // Only the first page of tracks comes with the album.
+        while album.tracks.next.is_some() {
+            let offset = album.tracks.items.len() as u32;
+            let page = self.with_client(|client| client.album_track(album_id, 50, offset))?;
+
+            if page.items.is_empty() {
+                break;
+            }
+
+            album.tracks.next = page.next;
+            album.tracks.items.extend(page.items);
+        }
+
+        Ok(AlbumInfo::from_api_data(album))
+    }
+
+    fn artist(&self, artist_id: &str) -> Result<FullArtist> {
+        self.with_client(|client| client.artist(artist_id))
+    }
+
+    fn artist_albums(&self, artist_id: &str) -> Result<Vec<AlbumSummary>> {
+        let mut results = Vec::new();
+        let mut offset = 0;
+
+        loop {
+            let page = self.with_client(|client| client.artist_albums(artist_id, None, None, Some(50), Some(offset)))?;
+            let last_page = page.next.is_none() || page.items.is_empty();
+
+            offset += page.items.len() as u32;
+            results.extend(page.items.into_iter().filter_map(AlbumSummary::from_api_data));
+
+            if last_page {
+                break;
+            }
+        }
+
+        Ok(results)
+    }
+
+    fn artist_top_tracks(&self, artist_id: &str) -> Result<Vec<TrackInfo>> {
+        let tracks = self.with_client(|client| client.artist_top_tracks(artist_id, None))?;
+        Ok(tracks.tracks.into_iter().filter_map(TrackInfo::from_api_data).collect())
+    }
+
+    fn related_artists(&self, artist_id: &str) -> Result<Vec<ArtistInfo>> {
+        let artists = self.with_client(|client| client.artist_related_artists(artist_id))?;
+        Ok(artists.artists.into_iter().map(ArtistInfo::from_api_data).collect())
+    }
+
+    fn new_releases(&self) -> Result<Vec<AlbumSummary>> {
+        let country = self.user_country();
+        let page = self.with_client(|client| client.new_releases(country, 50, 0))?.albums;
+
+        Ok(page.items.into_iter().filter_map(AlbumSummary::from_api_data).collect())
+    }
+
+    fn featured_playlists(&self) -> Result<FeaturedPlaylists> {
+        let country = self.user_country();
+        let featured = self.with_client(|client| client.featured_playlists(None, country, None, 50, 0))?;
+
+        Ok(
+            FeaturedPlaylists {
+                message: featured.message,
+                playlists: featured.playlists.items
+            }
+        )
+    }
+
+    fn categories(&self) -> Result<Vec<Category>> {
+        let country = self.user_country();
+        let page = self.with_client(|client| client.categories(None, country, 50, 0))?.categories;
+
+        Ok(page.items)
+    }
+
+    fn category_playlists(&self, category_id: &str) -> Result<Vec<SimplifiedPlaylist>> {
+        let country = self.user_country();
+        let page = self.with_client(|client| client.category_playlists(category_id, country, 50, 0))?.playlists;
+
+        Ok(page.items)
+    }
+
+    fn recently_played(&self) -> Result<Vec<PlayedTrack>> {
+        let page = self.with_client(|client| client.current_user_recently_played(50))?;
+
+        Ok(page.items.into_iter().filter_map(|item| {
+            let played_at = item.played_at.format("%Y-%m-%d %H:%M").to_string();
+            TrackInfo::from_api_data(item.track).map(|track| PlayedTrack { track, played_at })
+        }).collect())
+    }
+
+    fn top_tracks(&self, term: TopTerm) -> Result<Vec<TrackInfo>> {
+        let page = self.with_client(|client| client.current_user_top_tracks(50, 0, Some(time_range(term))))?;
+        Ok(page.items.into_iter().filter_map(TrackInfo::from_api_data).collect())
+    }
+
+    fn top_artists(&self, term: TopTerm) -> Result<Vec<ArtistInfo>> {
+        let page = self.with_client(|client| client.current_user_top_artists(50, 0, Some(time_range(term))))?;
+        Ok(page.items.into_iter().map(ArtistInfo::from_api_data).collect())
+    }
+
+    fn audio_features(&self, track_ids: &[String]) -> Result<Vec<AudioFeatures>> {
+        let mut results = Vec::new();
+
+        // Spotify takes at most 100 ids per request.
+        for chunk in track_ids.chunks(100) {
+            if let Some(payload) = self.with_client(|client| client.audios_features(chunk))? {
+                results.extend(payload.audio_features.into_iter().map(AudioFeatures::from_api_data));
+            }
+        }
+
+        Ok(results)
+    }
+
+    fn recommendations(&self, seeds: &[RecommendationSeed], attributes: &[AttributeRange], limit: u32) -> Result<Vec<String>> {
+        let mut seed_tracks = Vec::new();
+        let mut seed_artists = Vec::new();
+        let mut seed_genres = Vec::new();
+
+        for seed in seeds.iter().take(MAX_SEEDS) {
+            match seed {
+                RecommendationSeed::Track { id, .. } => seed_tracks.push(id.clone()),
+                RecommendationSeed::Artist { id, .. } => seed_artists.push(id.clone()),
+                RecommendationSeed::Genre(genre) => seed_genres.push(genre.clone())
+            }
+        }
+
+        let mut payload = Map::new();
+
+        for range in attributes.iter() {
+            payload.insert(format!("min_{}", range.attribute.name()), Value::from(range.min as f64));
+            payload.insert(format!("max_{}", range.attribute.name()), Value::from(range.max as f64));
+        }
+
+        let some_if_any = |seeds: &Vec<String>| if seeds.is_empty() { None } else { Some(seeds.clone()) };
+
+        let recommendations = self.with_client(|client| client.recommendations(
+            some_if_any(&seed_artists),
+            some_if_any(&seed_genres),
+            some_if_any(&seed_tracks),
+            limit,
+            None,
+            &payload
+        ))?;
+
+        Ok(recommendations.tracks.into_iter().filter_map(|t| t.id).collect())
+    }
+
+    fn search(&self, query: &str, search_type: SearchType, market: Option<&str>, limit: u32, offset: u32) -> Result<SearchResult> {
+        let market = match market {
+            Some(market) => Some(Country::from_str(&market.to_uppercase()).map_err(|_| anyhow::Error::msg(format!("Unknown market {}", market)))?),
+            None => None
+        };
+
+        self.with_client(|client| client.search(query, search_type, limit, offset, market, None))
+    }
+
+    fn current_user_id(&self) -> Result<String> {
+        if let Some(user_id) = self.user_id.read().ok().and_then(|lock| lock.clone()) {
+            return Ok(user_id);
+        }
+
+        let user = self.with_client(|client| client.me())?;
+
+        if let Ok(mut lock) = self.user_country.write() {
+            *lock = user.country;
+        }
+
+        if let Ok(mut lock) = self.user_id.write() {
+            *lock = Some(user.id.clone());
+        }
+
+        Ok(user.id)
+    }
+
+    fn playlist_snapshot_id(&self, playlist_id: &str) -> Result<String> {
+        self.with_client(|client| client.playlist(playlist_id, None, None)).map(|playlist| playlist.snapshot_id)
+    }
+
+    fn playlist_description(&self, playlist_id: &str) -> Result<String> {
+        self.with_client(|client| client.playlist(playlist_id, None, None)).map(|playlist| playlist.description.unwrap_or_default())
+    }
+
+    fn saved_tracks(&self) -> Result<Vec<TrackInfo>> {
+        let mut results = Vec::new();
+        let mut offset = 0;
+
+        loop {
+            let page = self.with_client(|client| client.current_user_saved_tracks(50, offset))?;
+
+            let page_len = page.items.len();
+            offset += page_len as u32;
+            results.extend(page.items.into_iter().filter_map(|item| TrackInfo::from_api_data(item.track)));
+
+            if page.next.is_none() || page_len == 0 {
+                break;
+            }
+        }
+
+        Ok(results)
+    }
+
+    fn saved_albums(&self) -> Result<Vec<AlbumInfo>> {
+        let mut results = Vec::new();
+        let mut offset = 0;
+
+        loop {
+            let page = self.with_client(|client| client.current_user_saved_albums(50, offset))?;
+
+            let page_len = page.items.len();
+            offset += page_len as u32;
+            results.extend(page.items.into_iter().map(|item| AlbumInfo::from_api_data(item.album)));
+
+            if page.next.is_none() || page_len == 0 {
+                break;
+            }
+        }
+
+        Ok(results)
+    }
+
+    fn followed_artists(&self) -> Result<Vec<ArtistInfo>> {
+        let mut results = Vec::new();
+        let mut after = None;
+
+        // Followed artists are paged with a cursor instead of an offset.
+        loop {
+            let page = self.with_client(|client| client.current_user_followed_artists(50, after.clone()))?.artists;
+
+            let page_len = page.items.len();
+            results.extend(page.items.into_iter().map(ArtistInfo::from_api_data));
+
+            after = page.cursors.after;
+
+            if page.next.is_none() || after.is_none() || page_len == 0 {
+                break;
+            }
+        }
+
+        Ok(results)
+    }
+
+    fn is_throttled(&self) -> bool {
+        if let Ok(lock) = self.throttled_until.read() {
+            lock.map(|until| until > Instant::now()).unwrap_or(false)
+        }
+        else {
+            false
+        }
+    }
+}
+
+impl PlaylistEditor for WebAPIProvider {
+    fn remove_tracks_from_playlist(&self, playlist_id: &str, tracks: &[(String, usize)], snapshot_id: &str) -> Result<String> {
+        let user_id = self.current_user_id()?;
+        let mut snapshot_id = snapshot_id.to_string();
+
+        // Spotify takes at most 100 tracks per request. Removing from the back first
+        // keeps the positions of the remaining chunks valid in each new snapshot.
+        let mut tracks = tracks.to_vec();
+        tracks.sort_by(|a, b| b.1.cmp(&a.1));
+
+        for chunk in tracks.chunks(100) {
+            let items: Vec<Map<String, Value>> = chunk.iter().map(|(uri, position)| {
+                let mut item = Map::new();
+                item.insert(String::from("uri"), Value::from(uri.as_str()));
+                item.insert(String::from("positions"), Value::from(vec![*position]));
+
+                item
+            }).collect();
+
+            snapshot_id = self.with_client(|client| {
+                client.user_playlist_remove_specific_occurrenes_of_tracks(&user_id, playlist_id, items.clone(), Some(snapshot_id.clone()))
+            })?.snapshot_id;
+        }
+
+        Ok(snapshot_id)
+    }
+
+    fn add_tracks_to_playlist(&self, playlist_id: &str, track_ids: &[String], position: Option<usize>) -> Result<()> {
+        let user_id = self.current_user_id()?;
+
+        // Spotify takes at most 100 tracks per request.
+        for (idx, chunk) in track_ids.chunks(100).enumerate() {
+            let position = position.map(|p| (p + idx * 100) as i32);
+            self.with_client(|client| client.user_playlist_add_tracks(&user_id, playlist_id, chunk, position))?;
+        }
+
+        Ok(())
+    }
+
+    fn reorder_playlist_tracks(&self, playlist_id: &str, range_start: usize, range_length: usize, insert_before: usize, snapshot_id: &str) -> Result<String> {
+        let user_id = self.current_user_id()?;
+
+        self.with_client(|client| client.user_playlist_recorder_tracks(
+            &user_id,
+            playlist_id,
+            range_start as i32,
+            range_length as i32,
+            insert_before as i32,
+            Some(snapshot_id.to_string())
+        )).map(|result| result.snapshot_id)
+    }
+
+    fn create_playlist(&self, name: &str, description: &str, public: bool) -> Result<FullPlaylist> {
+        let user_id = self.current_user_id()?;
+        self.with_client(|client| client.user_playlist_create(&user_id, name, public, description.to_string()))
+    }
+
+    fn change_playlist_details(&self, playlist_id: &str, changes: &PlaylistChanges) -> Result<()> {
+        let user_id = self.current_user_id()?;
+
+        self.with_client(|client| client.user_playlist_change_detail(
+            &user_id,
+            playlist_id,
+            changes.name.as_deref(),
+            changes.public,
+            changes.description.clone(),
+            changes.collaborative
+        )).map(|_| ())
+    }
+
+    fn delete_playlist(&self, playlist_id: &str) -> Result<()> {
+        let user_id = self.current_user_id()?;
+        self.with_client(|client| client.user_playlist_unfollow(&user_id, playlist_id)).map(|_| ())
+    }
+}
+
+impl LibraryEditor for WebAPIProvider {
+    fn save_tracks(&self, track_ids: &[String]) -> Result<()> {
+        self.with_client(|client| client.current_user_saved_tracks_add(track_ids))
+    }
+
+    fn remove_saved_tracks(&self, track_ids: &[String]) -> Result<()> {
+        self.with_client(|client| client.current_user_saved_tracks_delete(track_ids))
+    }
+
+    fn save_albums(&self, album_ids: &[String]) -> Result<()> {
+        self.with_client(|client| client.current_user_saved_albums_add(album_ids))
+    }
+
+    fn remove_saved_albums(&self, album_ids: &[String]) -> Result<()> {
+        self.with_client(|client| client.current_user_saved_albums_delete(album_ids))
+    }
+
+    fn follow_artists(&self, artist_ids: &[String]) -> Result<()> {
+        self.with_client(|client| client.user_follow_artists(artist_ids))
+    }
+
+    fn unfollow_artists(&self, artist_ids: &[String]) -> Result<()> {
+        self.with_client(|client| client.user_unfollow_artists(artist_ids))
+    }
+}
+
+impl RemoteControl for WebAPIProvider {
+    fn devices(&self) -> Result<Vec<ConnectDevice>> {
+        let devices = self.with_client(|client| client.device())?.devices;
+        Ok(devices.into_iter().map(connect_device).collect())
+    }
+
+    fn remote_playback(&self) -> Result<Option<RemotePlayback>> {
+        let playback = match self.with_client(|client| client.current_playback(None, None))? {
+            Some(playback) => playback,
+            None => return Ok(None)
+        };
+
+        let track = match playback.item {
+            Some(PlayingItem::Track(track)) => TrackInfo::from_api_data(track),
+            _ => None
+        };
+
+        Ok(
+            Some(
+                RemotePlayback {
+                    device: connect_device(playback.device),
+                    is_playing: playback.is_playing,
+                    progress: playback.progress_ms.unwrap_or(0),
+                    track
+                }
+            )
+        )
+    }
+
+    fn transfer_playback(&self, device_id: &str, play: bool) -> Result<()> {
+        self.with_client(|client| client.transfer_playback(device_id, play))
+    }
+
+    fn remote_command(&self, device_id: &str, command: RemoteCommand) -> Result<()> {
+        let device = || Some(device_id.to_string());
+
+        self.with_client(|client| match command {
+            RemoteCommand::Play => client.start_playback(device(), None, None, None, None),
+            RemoteCommand::Pause => client.pause_playback(device()),
+            RemoteCommand::Next => client.next_track(device()),
+            RemoteCommand::Previous => client.previous_track(device()),
+            RemoteCommand::Seek(position) => client.seek_track(position, device()),
+            RemoteCommand::Volume(volume) => client.volume(volume, device())
+        })
+    }
+}
+
+fn time_range(term: TopTerm) -> TimeRange {
+    match term {
+        TopTerm::Short => TimeRange::ShortTerm,
+        TopTerm::Medium => TimeRange::MediumTerm,
+        TopTerm::Long => TimeRange::LongTerm
+    }
+}
+
+fn build_client(api_token: &APIToken, api_prefix: Option<&str>) -> Spotify {
+    let client = Spotify::default().access_token(api_token.access_token());
+
+    match api_prefix {
+        Some(prefix) => client.prefix(prefix).build(),
+        None => client.build()
+    }
+}
+
+fn connect_device(device: Device) -> ConnectDevice {
+    ConnectDevice {
+        id: device.id,
+        name: device.name,
+        kind: format!("{:?}", device._type),
+        is_active: device.is_active,
+        volume: device.volume_percent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    const ME: &str = r#"{"display_name": "Stub User", "external_urls": {}, "href": "", "id": "stub-user", "type": "user", "uri": "spotify:user:stub-user", "country": "DE"}"#;
+
+    fn response(status: &str, headers: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {}\r\n{}Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status, headers, body.len(), body
+        )
+    }
+
+    /// Answers one connection per response, in order, and records each request line with its body.
+    fn stub_server(responses: Vec<String>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let prefix = format!("http://{}/v1/", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let received = requests.clone();
+
+        std::thread::spawn(move || {
+            for response in responses {
+                let mut stream = match listener.accept() {
+                    Ok((stream, _)) => stream,
+                    Err(_) => return
+                };
+
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request = String::new();
+                reader.read_line(&mut request).unwrap();
+
+                let mut content_length = 0;
+
+                loop {
+                    let mut line = String::new();
+
+                    if reader.read_line(&mut line).unwrap_or(0) <= 2 {
+                        break;
+                    }
+
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap_or(0);
+                        }
+                    }
+                }
+
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+
+                received.lock().unwrap().push(format!("{} {}", request.trim(), String::from_utf8_lossy(&body)).trim().to_string());
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        (prefix, requests)
+    }
+
+    fn stub_provider(prefix: String) -> WebAPIProvider {
+        let token: APIToken = serde_json::from_value(serde_json::json!({
+            "access_token": "stub",
+            "refresh_token": "stub",
+            "expires_at": u64::MAX,
+            "scopes": []
+        })).unwrap();
+
+        WebAPIProvider::init(token, Some(prefix))
+    }
+
+    #[test]
+    fn looks_up_the_user_once() {
+        let (prefix, requests) = stub_server(vec![response("200 OK", "", ME)]);
+        let provider = stub_provider(prefix);
+
+        assert_eq!(provider.current_user_id().unwrap(), "stub-user");
+        assert_eq!(provider.current_user_id().unwrap(), "stub-user");
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].starts_with("GET /v1/me"));
+    }
+
+    #[test]
+    fn retries_after_being_rate_limited() {
+        let (prefix, requests) = stub_server(vec![
+            response("429 Too Many Requests", "Retry-After: 0\r\n", "{}"),
+            response("200 OK", "", ME)
+        ]);
+
+        let provider = stub_provider(prefix);
+
+        assert_eq!(provider.current_user_id().unwrap(), "stub-user");
+        assert_eq!(requests.lock().unwrap().len(), 2);
+        assert!(!provider.is_throttled());
+    }
+
+    #[test]
+    fn gives_up_on_client_errors() {
+        let (prefix, requests) = stub_server(vec![
+            response("404 Not Found", "", r#"{"error": {"status": 404, "message": "Not found"}}"#),
+            response("200 OK", "", ME)
+        ]);
+
+        let provider = stub_provider(prefix);
+
+        assert!(provider.current_user_id().is_err());
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn transfers_and_pauses_on_the_given_device() {
+        let (prefix, requests) = stub_server(vec![
+            response("204 No Content", "", ""),
+            response("204 No Content", "", "")
+        ]);
+
+        let provider = stub_provider(prefix);
+
+        provider.transfer_playback("speaker", true).unwrap();
+        provider.remote_command("speaker", RemoteCommand::Pause).unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert!(requests[0].starts_with("PUT /v1/me/player"));
+        assert!(requests[0].contains("speaker"));
+        assert!(requests[1].starts_with("PUT /v1/me/player/pause?device_id=speaker"));
+    }
+}
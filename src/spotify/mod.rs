@@ -3,13 +3,20 @@ mod dbus;
 
 pub mod api;
 pub mod player;
+pub mod search;
 
 use api::SpotifyAPIHandler;
-use player::{PlayerCommand, PlayerHandler};
-use api::cache::{APICacheHandler, TrackInfo};
+use api::executor::RequestHandle;
+use api::provider::{AlbumSummary, AttributeRange, ConnectDevice, FeaturedPlaylists, MetadataProvider, PlayedTrack, PlaylistChanges, PlaylistItem, PlaylistItemKind, RecommendationSeed, RemoteCommand, RemotePlayback, TopTerm};
+use api::librespot_provider::LibrespotProvider;
+use api::web_provider::WebAPIProvider;
+use player::{PlayContext, PlayerCommand, PlayerHandler};
+use api::cache::{APICacheHandler, AlbumInfo, ArtistInfo, AudioFeatures, CachedPlaylist, TrackInfo};
 
-use std::sync::{Arc, Mutex, RwLock};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex, RwLock, Weak};
 use std::sync::mpsc::{Sender, Receiver};
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
 use tokio::runtime::Runtime;
@@ -20,22 +27,48 @@ use librespot::core::config::SessionConfig;
 use librespot::core::spotify_id::SpotifyId;
 use librespot::core::authentication::Credentials;
 
-use librespot::metadata::{Metadata, Playlist};
-
-use rspotify::model::track::FullTrack;
-use rspotify::model::artist::FullArtist;
+use rspotify::senum::SearchType;
+use rspotify::model::search::SearchResult;
+use rspotify::model::category::Category;
+use rspotify::model::playlist::SimplifiedPlaylist;
 
 pub struct SpotifyHandler {
+    // Librespot's futures run on this runtime, it has to live as long as the session.
     rt: Runtime,
-    spotify_session: Session,
 
     api_handler: Arc<SpotifyAPIHandler>,
-    playlist_data: Vec<Arc<PlaylistData>>,
-    player_handler: Arc<Mutex<PlayerHandler>>
+    playlist_data: Arc<RwLock<Vec<Arc<PlaylistData>>>>,
+    playlists_request: Option<RequestHandle<()>>,
+    player_handler: Arc<Mutex<PlayerHandler>>,
+
+    liked_songs: Arc<RwLock<Option<Arc<PlaylistData>>>>,
+    liked_songs_request: Option<RequestHandle<()>>,
+    saved_tracks: Arc<RwLock<HashSet<String>>>,
+    track_saves: Arc<Mutex<TrackSaves>>,
+    save_requests: usize,
+
+    saved_albums: Arc<RwLock<Vec<AlbumInfo>>>,
+    followed_artists: Arc<RwLock<Vec<ArtistInfo>>>,
+    library_request: Option<RequestHandle<()>>,
+
+    last_removal: Arc<RwLock<Option<RemovedTracks>>>,
+
+    // Moves and removals waiting to be sent, per playlist. The first one is the one being sent right now.
+    pending_edits: Arc<Mutex<HashMap<SpotifyId, VecDeque<PlaylistEdit>>>>,
+    edit_requests: usize,
+
+    // The Connect device playback was moved to, while imguify is acting as a remote for it.
+    remote_device: Arc<RwLock<Option<String>>>,
+    remote_request: Option<RequestHandle<()>>,
+
+    // Requests nothing waits on, kept here so they don't get cancelled.
+    pending_requests: Vec<RequestHandle<()>>
 }
 
 impl SpotifyHandler {
-    pub fn init(username: String, password: String, cmd_tx: Sender<PlayerCommand>, cmd_rx: Receiver<PlayerCommand>) -> Result<SpotifyHandler> {
+    /// `metadata_provider` is only needed for search and library features, everything else goes through librespot.
+    /// Changes to playlists and the library, and controlling other devices, need `web_api`.
+    pub fn init(username: String, password: String, metadata_provider: Option<Arc<dyn MetadataProvider>>, web_api: Option<Arc<WebAPIProvider>>, cmd_tx: Sender<PlayerCommand>, cmd_rx: Receiver<PlayerCommand>) -> Result<SpotifyHandler> {
         let rt = Runtime::new().unwrap();
         let cache_path = {
             let mut path = dirs::cache_dir().context("Failed to get system cache path")?;
@@ -45,8 +78,6 @@ impl SpotifyHandler {
         };
 
         let player_cache = Cache::new(None, Some(cache_path), None)?;
-        let api_cache_handler = Arc::new(Mutex::new(APICacheHandler::init()));
-        let api_handler = Arc::new(SpotifyAPIHandler::init(api_cache_handler)?);
 
         let session_cfg = SessionConfig {
             device_id: String::from("imguify-cookie"),
@@ -55,7 +86,17 @@ impl SpotifyHandler {
 
         let credentials = Credentials::with_password(username, password);
         let spotify_session = rt.block_on(Session::connect(session_cfg, credentials, Some(player_cache)))?;
+
+        let provider = LibrespotProvider::init(rt.handle().clone(), spotify_session.clone(), metadata_provider);
+        let api_cache_handler = Arc::new(Mutex::new(APICacheHandler::init()));
+        APICacheHandler::start_flushing(&api_cache_handler);
+        let api_handler = Arc::new(SpotifyAPIHandler::init(api_cache_handler, Box::new(provider), web_api));
+
         let player_handler = PlayerHandler::init(spotify_session.clone(), cmd_rx);
+        let (saved_albums, followed_artists) = api_handler.get_cached_library();
+        let playlist_data = Arc::new(RwLock::new(cached_user_playlists(&api_handler)));
+
+        spawn_playlist_refresh(Arc::downgrade(&api_handler), Arc::downgrade(&playlist_data));
 
         if cfg!(target_os = "linux") {
             dbus::init_connection(cmd_tx, player_handler.clone());
@@ -63,30 +104,58 @@ impl SpotifyHandler {
 
         let spotify_handler = SpotifyHandler {
             rt,
-            spotify_session,
             
             api_handler,
-            playlist_data: Vec::new(),
-            player_handler
+            playlist_data,
+            playlists_request: None,
+            player_handler,
+
+            liked_songs: Arc::new(RwLock::new(None)),
+            liked_songs_request: None,
+            saved_tracks: Arc::new(RwLock::new(HashSet::new())),
+            track_saves: Arc::new(Mutex::new(TrackSaves::default())),
+            save_requests: 0,
+
+            saved_albums: Arc::new(RwLock::new(saved_albums)),
+            followed_artists: Arc::new(RwLock::new(followed_artists)),
+            library_request: None,
+
+            last_removal: Arc::new(RwLock::new(None)),
+
+            pending_edits: Arc::new(Mutex::new(HashMap::new())),
+            edit_requests: 0,
+
+            remote_device: Arc::new(RwLock::new(None)),
+            remote_request: None,
+
+            pending_requests: Vec::new()
         };
         
         Ok(spotify_handler)
     }
 
-    pub fn get_playlist(&mut self, plist: usize) -> Option<Arc<PlaylistData>> {
-        self.playlist_data.get(plist).cloned()
-    }
-
-    pub fn get_playlists_names(&self) -> Vec<String> {
+    /// The user's playlists by name, with what to look them up by since the list can change in the meantime.
+    pub fn get_playlists_names(&self) -> Vec<(PlaylistKind, String)> {
         let mut results = Vec::new();
-        
-        for playlist in self.playlist_data.iter() {
-            results.push(format!("{} - {} tracks", playlist.title, playlist.entries.len()));
+
+        if let Ok(lock) = self.playlist_data.read() {
+            for playlist in lock.iter() {
+                results.push((*playlist.kind(), format!("{} - {} tracks", playlist.title, playlist.items.len())));
+            }
         }
 
         results
     }
 
+    pub fn is_loading_playlists(&self) -> bool {
+        if let Some(request) = self.playlists_request.as_ref() {
+            request.is_loading()
+        }
+        else {
+            false
+        }
+    }
+
     pub fn get_next_song(&self) -> Option<SpotifyId> {
         if let Ok(lock) = self.player_handler.try_lock() {
             lock.get_next_song()
@@ -123,175 +192,1831 @@ impl SpotifyHandler {
         }
     }
 
+    /// Writes out anything the cache hasn't stored yet, for when imguify is closing.
+    pub fn flush_cache(&self) {
+        self.api_handler.flush_cache();
+    }
+
     pub fn get_api_handler(&self) -> Arc<SpotifyAPIHandler> {
         self.api_handler.clone()
     }
 
+    pub fn playlists_error(&self) -> Option<String> {
+        self.playlists_request.as_ref().and_then(|r| r.error())
+    }
+
     pub fn fetch_user_playlists(&mut self) {
-        if let Some(playlists) = self.api_handler.get_user_playlists() {
-            self.playlist_data.clear();
+        let playlist_data = self.playlist_data.clone();
 
-            for item in playlists.items {
-                let id = SpotifyId::from_base62(&item.id).expect("Failed to parse id");
+        self.playlists_request = Some(self.api_handler.request(String::from("user_playlists"), move |api| {
+            load_user_playlists(api, &playlist_data)
+        }));
+    }
 
-                if let Ok(list) = self.rt.block_on(Playlist::get(&self.spotify_session, id)) {
-                    let entries = list.tracks;
+    pub fn play_single_track(&mut self, track: SpotifyId) {
+        if let Ok(mut lock) = self.player_handler.lock() {
+            lock.play_single_track(track);
+        }
+    }
 
-                    self.playlist_data.push(Arc::new(
-                        PlaylistData {
-                            id,
-                            title: list.name,
+    pub fn play_song_on_playlist(&mut self, playlist: &PlaylistData, track: &str) {
+        if let Ok(mut lock) = self.player_handler.lock() {
+            if let Ok(track) = SpotifyId::from_base62(track) {
+                lock.play_track_from_playlist(playlist.entries(), track);
+            }
+        }
+    }
 
-                            entries,
-                            entries_data: Arc::new(RwLock::new(Vec::new())),
+    /// Plays an album in order, or shuffled. `start` is the id of the track to start from.
+    pub fn play_album(&mut self, album: &AlbumInfo, shuffle: bool, start: Option<&str>) {
+        let album_id = match SpotifyId::from_base62(album.id()) {
+            Ok(id) => id,
+            Err(_) => return
+        };
 
-                            data_fetched: RwLock::new(false),
-                            data_fetching: RwLock::new(false)
-                        }
-                    ));
-                }
-            }
+        let track_ids: Vec<&String> = if album.has_details() {
+            album.tracklist().iter().map(|t| t.id()).collect()
+        }
+        else {
+            album.tracks().iter().collect()
+        };
+
+        let tracks = track_ids.iter().filter_map(|id| SpotifyId::from_base62(id).ok()).collect();
+        let start = start.and_then(|id| SpotifyId::from_base62(id).ok());
+
+        if let Ok(mut lock) = self.player_handler.lock() {
+            lock.play_context(PlayContext::Album(album_id), tracks, shuffle, start);
         }
     }
 
-    pub fn play_single_track(&mut self, track: SpotifyId) {
+    /// Plays an artist's top tracks in order, from `start` if it's given.
+    pub fn play_artist_top_tracks(&mut self, artist_id: &str, top_tracks: &[TrackInfo], start: Option<&str>) {
+        let artist_id = match SpotifyId::from_base62(artist_id) {
+            Ok(id) => id,
+            Err(_) => return
+        };
+
+        let tracks = top_tracks.iter().filter_map(|t| SpotifyId::from_base62(t.id()).ok()).collect();
+        let start = start.and_then(|id| SpotifyId::from_base62(id).ok());
+
         if let Ok(mut lock) = self.player_handler.lock() {
-            lock.play_single_track(track);
+            lock.play_context(PlayContext::Artist(artist_id), tracks, false, start);
         }
     }
 
-    pub fn play_song_on_playlist(&mut self, playlist: String, track: &str) {
-        if let Some(plist) = self.playlist_data.iter().find(|p| p.id().to_base62() == playlist) {
-            if let Ok(mut lock) = self.player_handler.lock() {
-                if let Ok(track) = SpotifyId::from_base62(track) {
-                    lock.play_track_from_playlist(plist.entries.clone(), track);
-                }
-            }
+    /// Plays a list of tracks like recommendations in the order they came, from `start` if it's given.
+    pub fn play_tracks(&mut self, context: PlayContext, tracks: &[TrackInfo], start: Option<&str>) {
+        let tracks = tracks.iter().filter_map(|t| SpotifyId::from_base62(t.id()).ok()).collect();
+        let start = start.and_then(|id| SpotifyId::from_base62(id).ok());
+
+        if let Ok(mut lock) = self.player_handler.lock() {
+            lock.play_context(context, tracks, false, start);
         }
     }
 
-    pub fn remove_track_from_playlist(&mut self, playlist_id: &str, track_id: &str) {
-        if self.api_handler.remove_track_from_playlist(playlist_id, track_id) {
-            self.fetch_user_playlists();
+    /// Plays a mix of the top tracks of several artists, like the user's own top artists.
+    pub fn play_artists(&mut self, artist_ids: Vec<String>) {
+        let player_handler = self.player_handler.clone();
+
+        let request = self.api_handler.request(format!("play_artists:{}", artist_ids.join(",")), move |api| {
+            let tracks = artists_top_track_ids(api, &artist_ids).iter().filter_map(|id| SpotifyId::from_base62(id).ok()).collect();
+
+            if let Ok(mut lock) = player_handler.lock() {
+                lock.play_context(PlayContext::Tracks, tracks, true, None);
+            }
+
+            Ok(())
+        });
+
+        self.track_request(request);
+    }
+
+    /// Like `save_as_playlist`, with the top tracks of each artist.
+    pub fn save_artists_as_playlist(&mut self, name: String, artist_ids: Vec<String>) {
+        let playlist_data = self.playlist_data.clone();
+
+        let request = self.api_handler.request(format!("save_as_playlist:{}", name), move |api| {
+            let track_ids = artists_top_track_ids(api, &artist_ids);
+            save_as_playlist(api, &playlist_data, &name, track_ids)
+        });
+
+        self.track_request(request);
+    }
+
+    pub fn get_play_context(&self) -> Option<PlayContext> {
+        if let Ok(lock) = self.player_handler.try_lock() {
+            Some(lock.get_context())
+        }
+        else {
+            None
         }
     }
 
-    pub fn search_tracks(&self, query: String) -> Vec<FullTrack> {
-        if let Some(results) = self.api_handler.search_tracks(query) {
-            return results.items;
+    /// The name of what's playing, like the album's title or the artist's name.
+    pub fn get_context_name(&self, context: PlayContext) -> RequestHandle<String> {
+        match context {
+            PlayContext::Album(album) => {
+                let album_id = album.to_base62();
+                self.api_handler.request(format!("context_name:{}", album_id), move |api| api.get_album_details(&album_id).map(|a| a.name().clone()))
+            }
+            PlayContext::Artist(artist) => {
+                let artist_id = artist.to_base62();
+                self.api_handler.request(format!("context_name:{}", artist_id), move |api| api.get_artist(artist_id).map(|a| a.name().clone()))
+            }
+            PlayContext::Recommendations => self.api_handler.request(String::from("context_name:recommendations"), |_| Ok(String::from("Recommendations"))),
+            PlayContext::Tracks => self.api_handler.request(String::from("context_name:tracks"), |_| Ok(String::new()))
         }
+    }
+
+    pub fn get_album(&self, album_id: String) -> RequestHandle<AlbumInfo> {
+        self.api_handler.request(format!("album_details:{}", album_id), move |api| api.get_album_details(&album_id))
+    }
 
-        Vec::new()
+    pub fn get_liked_songs(&self) -> Option<Arc<PlaylistData>> {
+        if let Ok(lock) = self.liked_songs.read() {
+            lock.clone()
+        }
+        else {
+            None
+        }
     }
 
-    pub fn search_artists(&self, query: String) -> Vec<FullArtist> {
-        if let Some(results) = self.api_handler.search_artists(query) {
-            return results.items;
+    pub fn is_loading_liked_songs(&self) -> bool {
+        if let Some(request) = self.liked_songs_request.as_ref() {
+            request.is_loading()
+        }
+        else {
+            false
         }
+    }
 
-        Vec::new()
+    pub fn liked_songs_error(&self) -> Option<String> {
+        self.liked_songs_request.as_ref().and_then(|r| r.error())
     }
 
-    pub fn get_artist_data(&mut self, artist: String) -> Vec<TrackInfo> {
-        let mut results = Vec::new();
+    pub fn fetch_liked_songs(&mut self) {
+        let liked_songs = self.liked_songs.clone();
+        let saved_tracks = self.saved_tracks.clone();
+        let track_saves = self.track_saves.clone();
+
+        self.liked_songs_request = Some(self.api_handler.request(String::from("liked_songs"), move |api| {
+            let started = Instant::now();
+            let tracks = api.get_saved_tracks()?;
+            let mut track_ids: Vec<String> = tracks.iter().map(|t| t.id().clone()).collect();
+
+            if let Ok(mut saves) = track_saves.lock() {
+                if let Ok(mut lock) = saved_tracks.write() {
+                    // Tracks saved or removed here since the request went out, or still on their way, are newer than what came back.
+                    let newer: HashSet<&String> = saves.pending.keys()
+                        .chain(saves.changed_at.iter().filter(|(_, at)| **at >= started).map(|(id, _)| id))
+                        .collect();
+
+                    for id in newer {
+                        track_ids.retain(|t| t != id);
+
+                        if lock.contains(id) {
+                            track_ids.insert(0, id.clone());
+                        }
+                    }
+
+                    *lock = track_ids.iter().cloned().collect();
+                }
+
+                saves.changed_at.retain(|_, at| *at >= started);
+            }
+
+            if let Ok(mut lock) = liked_songs.write() {
+                *lock = Some(Arc::new(PlaylistData::from_tracks(PlaylistKind::LikedSongs, String::from("Liked Songs"), track_ids)));
+            }
+
+            Ok(())
+        }));
+    }
+
+    pub fn is_track_saved(&self, track_id: &str) -> bool {
+        if let Ok(lock) = self.saved_tracks.read() {
+            lock.contains(track_id)
+        }
+        else {
+            false
+        }
+    }
+
+    /// Saves or removes a track from Liked Songs.
+    /// The change shows up right away, and gets rolled back if the request fails.
+    /// Changes to the same track are sent one at a time, so they reach Spotify in the order they were made.
+    pub fn set_track_saved(&mut self, track_id: &str, saved: bool) {
+        let liked_songs = self.liked_songs.clone();
+        let saved_tracks = self.saved_tracks.clone();
+        let track_saves = self.track_saves.clone();
+        let track_id = track_id.to_string();
+
+        let was_saved = self.is_track_saved(&track_id);
+
+        let is_first = match track_saves.lock() {
+            Ok(mut saves) => {
+                if let Ok(mut lock) = saved_tracks.write() {
+                    if saved {
+                        lock.insert(track_id.clone());
+                    }
+                    else {
+                        lock.remove(&track_id);
+                    }
+                }
+
+                saves.changed_at.insert(track_id.clone(), Instant::now());
+                saves.pending.insert(track_id.clone(), saved).is_none()
+            }
+            Err(_) => return
+        };
+
+        // The request that's already sending this track's changes gets to this one once it's done.
+        if !is_first {
+            return;
+        }
+
+        self.save_requests += 1;
+        let key = format!("save_track:{}:{}", track_id, self.save_requests);
+
+        let request = self.api_handler.request(key, move |api| {
+            // What Spotify has for the track, as far as we know.
+            let mut confirmed = was_saved;
+
+            loop {
+                let wanted = match track_saves.lock() {
+                    Ok(saves) => saves.pending.get(&track_id).copied(),
+                    Err(_) => None
+                };
+
+                let wanted = match wanted {
+                    Some(wanted) => wanted,
+                    None => return Ok(())
+                };
+
+                if wanted != confirmed {
+                    if let Err(error) = api.set_tracks_saved(&[track_id.clone()], wanted) {
+                        // Anything toggled in the meantime goes too, the track shows what Spotify has again.
+                        if let Ok(mut saves) = track_saves.lock() {
+                            saves.pending.remove(&track_id);
+                            saves.changed_at.insert(track_id.clone(), Instant::now());
+
+                            if let Ok(mut lock) = saved_tracks.write() {
+                                if confirmed {
+                                    lock.insert(track_id.clone());
+                                }
+                                else {
+                                    lock.remove(&track_id);
+                                }
+                            }
+                        }
+
+                        println!("Error updating Liked Songs: {}", error.to_string());
+                        return Err(error);
+                    }
+
+                    confirmed = wanted;
 
-        if let Some(albums) = self.api_handler.get_all_albums_for_artist(artist) {
-            for album in albums.items {
-                if let Some(id) = album.id {
-                    if let Some(data) = self.api_handler.get_album(id) {
-                        for track in data.tracks() {
-                            if let Ok(track) = self.api_handler.get_track(track.clone()) {
-                                results.push(track);
+                    if let Ok(mut lock) = liked_songs.write() {
+                        if let Some(current) = lock.as_ref() {
+                            let mut items = current.items().clone();
+                            items.retain(|i| i.track_id() != Some(&track_id));
+
+                            if confirmed {
+                                items.insert(0, PlaylistItem::track(track_id.clone()));
                             }
+
+                            *lock = Some(Arc::new(PlaylistData::new(PlaylistKind::LikedSongs, current.title.clone(), items)));
+                        }
+                    }
+                }
+
+                // Done under the lock, so a change made right now either gets picked up here or starts a new request.
+                match track_saves.lock() {
+                    Ok(mut saves) => {
+                        if saves.pending.get(&track_id) == Some(&confirmed) {
+                            saves.pending.remove(&track_id);
+                            return Ok(());
                         }
                     }
+                    Err(_) => return Ok(())
                 }
             }
+        });
+
+        self.track_request(request);
+    }
+
+    pub fn get_saved_albums(&self) -> Vec<AlbumInfo> {
+        if let Ok(lock) = self.saved_albums.read() {
+            lock.clone()
+        }
+        else {
+            Vec::new()
         }
+    }
 
-        results.sort_by(|a, b| b.popularity().cmp(a.popularity()));
-        results
+    pub fn get_followed_artists(&self) -> Vec<ArtistInfo> {
+        if let Ok(lock) = self.followed_artists.read() {
+            lock.clone()
+        }
+        else {
+            Vec::new()
+        }
     }
-}
 
-pub struct PlaylistData {
-    id: SpotifyId,
-    title: String,
+    pub fn is_loading_library(&self) -> bool {
+        if let Some(request) = self.library_request.as_ref() {
+            request.is_loading()
+        }
+        else {
+            false
+        }
+    }
 
-    entries: Vec<SpotifyId>,
-    entries_data: Arc<RwLock<Vec<PlaylistEntry>>>,
+    pub fn library_error(&self) -> Option<String> {
+        self.library_request.as_ref().and_then(|r| r.error())
+    }
 
-    data_fetched: RwLock<bool>,
-    data_fetching: RwLock<bool>
-}
+    /// Refreshes the saved albums and followed artists.
+    /// Until it's done, the library shows what was cached on the last run.
+    pub fn fetch_library(&mut self) {
+        let saved_albums = self.saved_albums.clone();
+        let followed_artists = self.followed_artists.clone();
 
-impl PlaylistData {
-    pub fn fetch_data(&self, api_handler: Arc<SpotifyAPIHandler>) {
-        if let (Ok(mut fetching), Ok(fetched)) = (self.data_fetching.write(), self.data_fetched.read()) {
-            if *fetched || *fetching {
-                return;
+        self.library_request = Some(self.api_handler.request(String::from("library"), move |api| {
+            let albums = api.get_saved_albums();
+
+            if let (Ok(albums), Ok(mut lock)) = (albums.as_ref(), saved_albums.write()) {
+                *lock = albums.clone();
             }
-            else {
-                *fetching = true;
+
+            let artists = api.get_followed_artists()?;
+
+            if let Ok(mut lock) = followed_artists.write() {
+                *lock = artists;
+            }
+
+            albums.map(|_| ())
+        }));
+    }
+
+    pub fn is_album_saved(&self, album_id: &str) -> bool {
+        if let Ok(lock) = self.saved_albums.read() {
+            lock.iter().any(|a| a.id() == album_id)
+        }
+        else {
+            false
+        }
+    }
+
+    pub fn set_album_saved(&mut self, album_id: &str, saved: bool) {
+        let saved_albums = self.saved_albums.clone();
+        let album_id = album_id.to_string();
+
+        let request = self.api_handler.request(format!("save_album:{}:{}", album_id, saved), move |api| {
+            if let Err(error) = api.set_albums_saved(&[album_id.clone()], saved) {
+                println!("Error updating saved albums: {}", error.to_string());
+                return Err(error);
+            }
+
+            let album = if saved { api.get_album(album_id.clone()) } else { None };
+
+            if let Ok(mut lock) = saved_albums.write() {
+                lock.retain(|a| *a.id() != album_id);
+
+                if let Some(album) = album {
+                    lock.insert(0, album);
+                }
+
+                api.cache_saved_albums(&lock);
             }
+
+            Ok(())
+        });
+
+        self.track_request(request);
+    }
+
+    pub fn is_artist_followed(&self, artist_id: &str) -> bool {
+        if let Ok(lock) = self.followed_artists.read() {
+            lock.iter().any(|a| a.id() == artist_id)
+        }
+        else {
+            false
         }
+    }
+
+    pub fn set_artist_followed(&mut self, artist_id: &str, followed: bool) {
+        let followed_artists = self.followed_artists.clone();
+        let artist_id = artist_id.to_string();
+
+        let request = self.api_handler.request(format!("follow_artist:{}:{}", artist_id, followed), move |api| {
+            if let Err(error) = api.set_artists_followed(&[artist_id.clone()], followed) {
+                println!("Error updating followed artists: {}", error.to_string());
+                return Err(error);
+            }
+
+            let artist = if followed { api.get_artist(artist_id.clone()).ok() } else { None };
+
+            if let Ok(mut lock) = followed_artists.write() {
+                lock.retain(|a| *a.id() != artist_id);
+
+                if let Some(artist) = artist {
+                    lock.push(artist);
+                }
+
+                api.cache_followed_artists(&lock);
+            }
+
+            Ok(())
+        });
 
-        for id in self.entries.iter() {            
-            if let Ok(track_data) = api_handler.get_track(id.to_base62()) {
-                if let Ok(mut lock) = self.entries_data.write() {
-                    let artist_data = track_data.artists()[0].clone();
+        self.track_request(request);
+    }
+
+    /// The user's playlists that tracks can be added to.
+    pub fn get_editable_playlists(&self) -> Vec<Arc<PlaylistData>> {
+        if let Ok(lock) = self.playlist_data.read() {
+            lock.iter().filter(|p| p.is_editable()).cloned().collect()
+        }
+        else {
+            Vec::new()
+        }
+    }
 
-                    let entry = PlaylistEntry {
-                        track: track_data,
-                        artist: artist_data
-                    };
-    
-                    lock.push(entry);
+    /// Finds the latest version of a playlist, since they get replaced whenever they're edited.
+    pub fn find_playlist(&self, kind: &PlaylistKind) -> Option<Arc<PlaylistData>> {
+        match kind {
+            PlaylistKind::LikedSongs => self.get_liked_songs(),
+            PlaylistKind::Playlist(id) => {
+                if let Ok(lock) = self.playlist_data.read() {
+                    lock.iter().find(|p| p.id() == Some(id)).cloned()
+                }
+                else {
+                    None
                 }
             }
         }
+    }
+
+    /// The error of a library or playlist change that failed, until the next change is made.
+    pub fn request_error(&self) -> Option<String> {
+        self.pending_requests.iter().rev().find_map(|r| r.error())
+    }
+
+    fn track_request(&mut self, request: RequestHandle<()>) {
+        self.pending_requests.retain(|r| r.is_loading());
+        self.pending_requests.push(request);
+    }
+
+    /// Removes the tracks at the given positions, leaving other copies of the same tracks alone.
+    /// The removal can be undone for a little while with `undo_last_removal`.
+    pub fn remove_playlist_tracks(&mut self, playlist_id: &SpotifyId, positions: &[usize]) {
+        let playlist_id = *playlist_id;
+
+        let playlist = match self.find_playlist(&PlaylistKind::Playlist(playlist_id)) {
+            Some(playlist) => playlist,
+            None => return
+        };
+
+        let data = playlist.current_data();
+        let snapshot_id = playlist.details().snapshot_id.clone();
+
+        let mut positions: Vec<usize> = positions.iter().cloned().filter(|p| data.get(*p).map(|e| e.is_removable()).unwrap_or(false)).collect();
+        positions.sort_unstable();
+        positions.dedup();
+
+        if positions.is_empty() {
+            return;
+        }
+
+        let removed = RemovedTracks {
+            playlist_id,
+            playlist_title: playlist.title().clone(),
+            entries: positions.iter().map(|p| (*p, data[*p].clone())).collect(),
+            removed_at: Instant::now(),
+            committed: false
+        };
+
+        let removed_at = removed.removed_at;
+        let tracks: Vec<(String, usize)> = removed.entries.iter().map(|(p, e)| (e.uri().clone(), *p)).collect();
 
-        if let (Ok(mut fetching), Ok(mut fetched)) = (self.data_fetching.write(), self.data_fetched.write()) {
-            *fetched = true;
-            *fetching = false;
+        update_playlist(&self.playlist_data, &playlist_id, |playlist| Some(playlist.with_removed_tracks(&positions)));
+
+        if let Ok(mut lock) = self.last_removal.write() {
+            *lock = Some(removed);
         }
+
+        self.queue_playlist_edit(playlist_id, snapshot_id, PlaylistEdit::Remove { tracks, removed_at });
     }
 
-    pub fn entries(&self) -> Vec<SpotifyId> {
-        self.entries.clone()
+    /// The last removal, once Spotify has it and while it's recent enough to still be undone.
+    pub fn get_last_removal(&self) -> Option<RemovedTracks> {
+        let lock = self.last_removal.read().ok()?;
+        lock.as_ref().filter(|r| r.committed && r.removed_at.elapsed() < UNDO_TIMEOUT).cloned()
     }
 
-    /// Get a reference to the playlist data's entries data.
-    pub fn entries_data(&self) -> &Arc<RwLock<Vec<PlaylistEntry>>> {
-        &self.entries_data
+    pub fn dismiss_last_removal(&self) {
+        if let Ok(mut lock) = self.last_removal.write() {
+            *lock = None;
+        }
     }
 
-    /// Get a reference to the playlist data's id.
-    pub fn id(&self) -> &SpotifyId {
-        &self.id
+    /// Puts the last removed tracks back where they were.
+    pub fn undo_last_removal(&mut self) {
+        let removed = match self.get_last_removal() {
+            Some(removed) => removed,
+            None => return
+        };
+
+        self.dismiss_last_removal();
+
+        let playlist_data = self.playlist_data.clone();
+        let playlist_id = removed.playlist_id;
+
+        update_playlist(&playlist_data, &playlist_id, |playlist| Some(playlist.with_restored_tracks(&removed.entries)));
+
+        let key = format!("undo_remove:{}:{:?}", playlist_id.to_base62(), removed.removed_at);
+
+        let request = self.api_handler.request(key, move |api| {
+            // Inserting from the front keeps every position correct, and adjacent tracks can go in together.
+            let mut runs: Vec<(usize, Vec<String>)> = Vec::new();
+
+            for (position, entry) in removed.entries.iter() {
+                if let Some(id) = entry.item.track_id() {
+                    match runs.last_mut() {
+                        Some((start, ids)) if *start + ids.len() == *position => ids.push(id.clone()),
+                        _ => runs.push((*position, vec![id.clone()]))
+                    }
+                }
+            }
+
+            for (start, ids) in runs.iter() {
+                if let Err(error) = api.add_tracks_to_playlist(&playlist_id.to_base62(), ids, Some(*start)) {
+                    if let Err(reload_error) = reload_playlist(api, &playlist_data, &playlist_id) {
+                        println!("Error reloading playlist: {}", reload_error.to_string());
+                    }
+
+                    return Err(error);
+                }
+            }
+
+            refresh_snapshot_id(api, &playlist_data, &playlist_id);
+            Ok(())
+        });
+
+        self.track_request(request);
     }
-}
 
-#[derive(Clone)]
-pub struct PlaylistEntry {
-    track: TrackInfo,
-    artist: String
-}
+    pub fn add_track_to_playlist(&mut self, playlist_id: &SpotifyId, track_id: &str) {
+        let playlist_data = self.playlist_data.clone();
 
-impl PlaylistEntry {
-    pub fn id(&self) -> &String {
-        self.track.id()
+        let playlist_id = *playlist_id;
+        let track_id = track_id.to_string();
+
+        let request = self.api_handler.request(format!("add_track:{}:{}", playlist_id.to_base62(), track_id), move |api| {
+            api.add_tracks_to_playlist(&playlist_id.to_base62(), &[track_id.clone()], None)?;
+
+            update_playlist(&playlist_data, &playlist_id, |playlist| Some(playlist.with_added_track(track_id)));
+
+            refresh_snapshot_id(api, &playlist_data, &playlist_id);
+            Ok(())
+        });
+
+        self.track_request(request);
     }
 
-    pub fn title(&self) -> &String {
-        self.track.name()
+    /// Moves tracks right away, then commits the move to Spotify.
+    pub fn move_playlist_tracks(&mut self, playlist_id: &SpotifyId, range_start: usize, range_length: usize, insert_before: usize) {
+        let playlist_id = *playlist_id;
+
+        let snapshot_id = match self.find_playlist(&PlaylistKind::Playlist(playlist_id)) {
+            Some(playlist) => playlist.details().snapshot_id.clone(),
+            None => return
+        };
+
+        update_playlist(&self.playlist_data, &playlist_id, |playlist| Some(playlist.with_moved_tracks(range_start, range_length, insert_before)));
+        self.queue_playlist_edit(playlist_id, snapshot_id, PlaylistEdit::Move { range_start, range_length, insert_before });
+    }
+
+    /// Sends an edit that's already shown locally, after the ones still on their way for the same playlist.
+    /// Each edit goes out with the snapshot the previous one returned, so its positions mean what they meant here.
+    /// If one fails, likely because the playlist changed elsewhere, the rest are dropped and the playlist gets reloaded.
+    fn queue_playlist_edit(&mut self, playlist_id: SpotifyId, snapshot_id: String, edit: PlaylistEdit) {
+        let playlist_data = self.playlist_data.clone();
+        let pending_edits = self.pending_edits.clone();
+        let last_removal = self.last_removal.clone();
+
+        let is_first = match pending_edits.lock() {
+            Ok(mut lock) => {
+                let edits = lock.entry(playlist_id).or_insert_with(VecDeque::new);
+                edits.push_back(edit);
+
+                edits.len() == 1
+            }
+            Err(_) => return
+        };
+
+        // The request that's already sending edits for this playlist gets to this one once it's done with the others.
+        // Until then `snapshot_id` might be outdated, but it isn't used.
+        if !is_first {
+            return;
+        }
+
+        self.edit_requests += 1;
+        let key = format!("playlist_edits:{}:{}", playlist_id.to_base62(), self.edit_requests);
+
+        let request = self.api_handler.request(key, move |api| {
+            let mut snapshot_id = snapshot_id;
+
+            loop {
+                let edit = match pending_edits.lock() {
+                    Ok(lock) => lock.get(&playlist_id).and_then(|edits| edits.front().cloned()),
+                    Err(_) => None
+                };
+
+                let edit = match edit {
+                    Some(edit) => edit,
+                    None => return Ok(())
+                };
+
+                let result = match &edit {
+                    PlaylistEdit::Move { range_start, range_length, insert_before } => {
+                        api.reorder_playlist_tracks(&playlist_id.to_base62(), *range_start, *range_length, *insert_before, &snapshot_id)
+                    }
+                    PlaylistEdit::Remove { tracks, .. } => api.remove_tracks_from_playlist(&playlist_id.to_base62(), tracks, &snapshot_id)
+                };
+
+                match result {
+                    Ok(new_snapshot_id) => {
+                        snapshot_id = new_snapshot_id;
+                        update_playlist(&playlist_data, &playlist_id, |playlist| Some(playlist.with_snapshot_id(snapshot_id.clone())));
+
+                        // Undoing only works once the tracks are actually gone, and gets the full time from here.
+                        // A newer removal might have taken this one's place already.
+                        if let PlaylistEdit::Remove { removed_at, .. } = edit {
+                            if let Ok(mut lock) = last_removal.write() {
+                                if let Some(removal) = lock.as_mut().filter(|r| r.removed_at == removed_at) {
+                                    removal.committed = true;
+                                    removal.removed_at = Instant::now();
+                                }
+                            }
+                        }
+                    }
+                    Err(error) => {
+                        if let Err(reload_error) = reload_playlist(api, &playlist_data, &playlist_id) {
+                            println!("Error reloading playlist: {}", reload_error.to_string());
+                        }
+
+                        // The edits queued after this one were made on top of it, the reload already undid them.
+                        let dropped = pending_edits.lock().ok().and_then(|mut lock| lock.remove(&playlist_id)).unwrap_or_default();
+
+                        // If the last removal was one of them, nothing was removed, so there's nothing to undo either.
+                        if let Ok(mut lock) = last_removal.write() {
+                            let was_dropped = lock.as_ref().map(|r| dropped.iter().any(|e| e.is_removal(r.removed_at))).unwrap_or(false);
+
+                            if was_dropped {
+                                *lock = None;
+                            }
+                        }
+
+                        return Err(error);
+                    }
+                }
+
+                // Done under the lock, so an edit queued right now either gets picked up here or starts a new request.
+                if let Ok(mut lock) = pending_edits.lock() {
+                    if let Some(edits) = lock.get_mut(&playlist_id) {
+                        edits.pop_front();
+
+                        if edits.is_empty() {
+                            lock.remove(&playlist_id);
+                        }
+                    }
+                }
+            }
+        });
+
+        self.track_request(request);
+    }
+
+    pub fn create_playlist(&mut self, name: String, description: String, public: bool, collaborative: bool) {
+        let playlist_data = self.playlist_data.clone();
+
+        let request = self.api_handler.request(format!("create_playlist:{}", name), move |api| {
+            let playlist = api.create_playlist(&name, &description, public)?;
+            let id = SpotifyId::from_base62(&playlist.id).map_err(|_| anyhow::Error::msg("Spotify returned an invalid playlist id"))?;
+
+            // Playlists can only be made collaborative after they're created.
+            if collaborative {
+                let changes = PlaylistChanges {
+                    collaborative: Some(true),
+                    ..Default::default()
+                };
+
+                api.change_playlist_details(&playlist.id, &changes)?;
+            }
+
+            let details = PlaylistDetails {
+                owner: playlist.owner.id,
+                description: playlist.description.unwrap_or(description),
+                public: playlist.public.unwrap_or(public),
+                collaborative,
+                snapshot_id: playlist.snapshot_id
+            };
+
+            if let Ok(mut lock) = playlist_data.write() {
+                lock.insert(0, Arc::new(PlaylistData::from_playlist(id, playlist.name, Vec::new(), details, true)));
+            }
+
+            Ok(())
+        });
+
+        self.track_request(request);
+    }
+
+    /// Creates a private playlist with these tracks, like a list of recommendations.
+    pub fn save_as_playlist(&mut self, name: String, track_ids: Vec<String>) {
+        let playlist_data = self.playlist_data.clone();
+
+        let request = self.api_handler.request(format!("save_as_playlist:{}", name), move |api| {
+            save_as_playlist(api, &playlist_data, &name, track_ids)
+        });
+
+        self.track_request(request);
+    }
+
+    pub fn change_playlist_details(&mut self, playlist_id: &SpotifyId, changes: PlaylistChanges) {
+        let playlist_data = self.playlist_data.clone();
+        let playlist_id = *playlist_id;
+
+        let request = self.api_handler.request(format!("change_playlist:{}", playlist_id.to_base62()), move |api| {
+            api.change_playlist_details(&playlist_id.to_base62(), &changes)?;
+            update_playlist(&playlist_data, &playlist_id, |playlist| Some(playlist.with_changes(&changes)));
+
+            refresh_snapshot_id(api, &playlist_data, &playlist_id);
+            Ok(())
+        });
+
+        self.track_request(request);
+    }
+
+    pub fn delete_playlist(&mut self, playlist_id: &SpotifyId) {
+        let playlist_data = self.playlist_data.clone();
+        let playlist_id = *playlist_id;
+
+        let request = self.api_handler.request(format!("delete_playlist:{}", playlist_id.to_base62()), move |api| {
+            api.delete_playlist(&playlist_id.to_base62())?;
+            update_playlist(&playlist_data, &playlist_id, |_| None);
+
+            Ok(())
+        });
+
+        self.track_request(request);
+    }
+
+    pub fn get_track(&self, track: SpotifyId) -> RequestHandle<TrackInfo> {
+        let track_id = track.to_base62();
+        self.api_handler.request(format!("track:{}", track_id), move |api| api.get_track(track_id))
+    }
+
+    /// One page of search results. Requests for the same page of the same search share a handle.
+    pub fn search(&self, query: String, search_type: SearchType, market: Option<String>, offset: u32) -> RequestHandle<SearchResult> {
+        let key = format!("search:{}:{}:{}:{}", search_type.as_str(), market.as_deref().unwrap_or(""), offset, query);
+
+        self.api_handler.request(key, move |api| {
+            api.search(&query, search_type, market.as_deref(), SEARCH_PAGE_SIZE, offset)
+        })
+    }
+
+    /// Plays a playlist that isn't one of the user's, like one found through search.
+    pub fn play_playlist(&mut self, playlist_id: String) {
+        let player_handler = self.player_handler.clone();
+
+        let request = self.api_handler.request(format!("play_playlist:{}", playlist_id), move |api| {
+            let items = api.get_playlist_tracks(&playlist_id)?;
+            let tracks = items.iter().filter_map(|i| i.track_id()).filter_map(|id| SpotifyId::from_base62(id).ok()).collect();
+
+            if let Ok(mut lock) = player_handler.lock() {
+                lock.play_context(PlayContext::Tracks, tracks, true, None);
+            }
+
+            Ok(())
+        });
+
+        self.track_request(request);
+    }
+
+    pub fn get_recommendations(&self, seeds: Vec<RecommendationSeed>, attributes: Vec<AttributeRange>) -> RequestHandle<Vec<TrackInfo>> {
+        let seed_key: Vec<String> = seeds.iter().map(|s| s.label()).collect();
+        let attribute_key: Vec<String> = attributes.iter().map(|a| format!("{}={}-{}", a.attribute.name(), a.min, a.max)).collect();
+
+        self.api_handler.request(format!("recommendations:{}:{}", seed_key.join(","), attribute_key.join(",")), move |api| {
+            api.get_recommendations(&seeds, &attributes, RECOMMENDATIONS_LIMIT)
+        })
+    }
+
+    pub fn get_new_releases(&self) -> RequestHandle<Vec<AlbumSummary>> {
+        self.api_handler.request(String::from("new_releases"), |api| api.get_new_releases())
+    }
+
+    pub fn get_featured_playlists(&self) -> RequestHandle<FeaturedPlaylists> {
+        self.api_handler.request(String::from("featured_playlists"), |api| api.get_featured_playlists())
+    }
+
+    pub fn get_categories(&self) -> RequestHandle<Vec<Category>> {
+        self.api_handler.request(String::from("categories"), |api| api.get_categories())
+    }
+
+    pub fn get_category_playlists(&self, category_id: String) -> RequestHandle<Vec<SimplifiedPlaylist>> {
+        self.api_handler.request(format!("category_playlists:{}", category_id), move |api| api.get_category_playlists(&category_id))
+    }
+
+    /// Playlists in a list come without their description, so it's only loaded for the edit form.
+    pub fn get_playlist_description(&self, playlist_id: &SpotifyId) -> RequestHandle<String> {
+        let playlist_id = playlist_id.to_base62();
+        self.api_handler.request(format!("playlist_description:{}", playlist_id), move |api| api.get_playlist_description(&playlist_id))
+    }
+
+    /// Loads a playlist that isn't one of the user's, like a featured one, so it can be shown in a playlist window.
+    pub fn get_other_playlist(&self, playlist: &SimplifiedPlaylist) -> RequestHandle<Arc<PlaylistData>> {
+        let playlist = playlist.clone();
+
+        self.api_handler.request(format!("other_playlist:{}", playlist.id), move |api| {
+            let id = SpotifyId::from_base62(&playlist.id).map_err(|_| anyhow::Error::msg("Invalid playlist id"))?;
+            let items = api.get_playlist_tracks(&playlist.id)?;
+
+            let details = PlaylistDetails {
+                owner: playlist.owner.id,
+                // Only shown when editing, which other playlists can't be.
+                description: String::new(),
+                public: playlist.public.unwrap_or(false),
+                collaborative: playlist.collaborative,
+                snapshot_id: playlist.snapshot_id
+            };
+
+            Ok(Arc::new(PlaylistData::from_playlist(id, playlist.name, items, details, false)))
+        })
+    }
+
+    pub fn get_recently_played(&self) -> RequestHandle<Vec<PlayedTrack>> {
+        self.api_handler.request(String::from("recently_played"), |api| api.get_recently_played())
+    }
+
+    pub fn get_top_tracks(&self, term: TopTerm) -> RequestHandle<Vec<TrackInfo>> {
+        self.api_handler.request(format!("top_tracks:{}", term.name()), move |api| api.get_top_tracks(term))
+    }
+
+    pub fn get_top_artists(&self, term: TopTerm) -> RequestHandle<Vec<ArtistInfo>> {
+        self.api_handler.request(format!("top_artists:{}", term.name()), move |api| api.get_top_artists(term))
+    }
+
+    pub fn get_devices(&self) -> RequestHandle<Vec<ConnectDevice>> {
+        self.api_handler.request(String::from("devices"), |api| api.get_devices())
+    }
+
+    /// Moves playback to another device and keeps it playing there.
+    /// Local playback is paused once the other device took over, so a failed transfer doesn't stop the music.
+    pub fn transfer_playback(&mut self, device_id: String) {
+        let player_handler = self.player_handler.clone();
+        let remote_device = self.remote_device.clone();
+
+        self.remote_request = Some(self.api_handler.request(format!("transfer_playback:{}", device_id), move |api| {
+            api.transfer_playback(&device_id, true)?;
+
+            if let Ok(mut lock) = player_handler.lock() {
+                lock.pause();
+            }
+
+            if let Ok(mut lock) = remote_device.write() {
+                *lock = Some(device_id);
+            }
+
+            Ok(())
+        }));
+    }
+
+    /// The device imguify is a remote for, if playback was moved away from it.
+    pub fn remote_device(&self) -> Option<String> {
+        if let Ok(lock) = self.remote_device.read() {
+            lock.clone()
+        }
+        else {
+            None
+        }
+    }
+
+    /// Goes back to playing on this computer, pausing the other device so they don't both play.
+    /// Playback can't be moved back over Connect, since imguify doesn't show up as a Connect device.
+    pub fn stop_remote(&mut self) {
+        let device_id = match self.remote_device.write() {
+            Ok(mut lock) => lock.take(),
+            Err(_) => None
+        };
+
+        self.remote_request = device_id.map(|device_id| self.api_handler.request(format!("stop_remote:{}", device_id), move |api| {
+            api.remote_command(&device_id, RemoteCommand::Pause)
+        }));
+    }
+
+    pub fn transfer_error(&self) -> Option<String> {
+        self.remote_request.as_ref().and_then(|r| r.error())
+    }
+
+    pub fn get_remote_playback(&self) -> RequestHandle<Option<RemotePlayback>> {
+        self.api_handler.request(String::from("remote_playback"), |api| api.get_remote_playback())
+    }
+
+    pub fn remote_command(&mut self, command: RemoteCommand) {
+        if let Some(device_id) = self.remote_device() {
+            let request = self.api_handler.request(format!("remote_command:{}:{:?}", device_id, command), move |api| {
+                api.remote_command(&device_id, command)
+            });
+
+            self.track_request(request);
+        }
+    }
+
+    /// Audio features of a playlist's tracks, keyed by track id.
+    pub fn get_audio_features(&self, playlist: &PlaylistData) -> RequestHandle<HashMap<String, AudioFeatures>> {
+        let track_ids: Vec<String> = playlist.items().iter().filter_map(|item| match &item.kind {
+            PlaylistItemKind::Track(id) => Some(id.clone()),
+            _ => None
+        }).collect();
+
+        let key = match playlist.id() {
+            Some(id) => format!("audio_features:{}:{}", id.to_base62(), playlist.details().snapshot_id),
+            None => format!("audio_features:{}:{}", playlist.title(), track_ids.len())
+        };
+
+        self.api_handler.request(key, move |api| {
+            let features = api.get_audio_features(&track_ids)?;
+            Ok(features.into_iter().map(|f| (f.id().clone(), f)).collect())
+        })
+    }
+
+    pub fn get_artist_info(&self, artist_id: String) -> RequestHandle<ArtistInfo> {
+        self.api_handler.request(format!("artist:{}", artist_id), move |api| api.get_artist(artist_id))
+    }
+
+    pub fn get_artist_top_tracks(&self, artist_id: String) -> RequestHandle<Vec<TrackInfo>> {
+        self.api_handler.request(format!("artist_top_tracks:{}", artist_id), move |api| api.get_artist_top_tracks(&artist_id))
+    }
+
+    pub fn get_artist_discography(&self, artist_id: String) -> RequestHandle<Vec<AlbumSummary>> {
+        self.api_handler.request(format!("artist_discography:{}", artist_id), move |api| api.get_artist_discography(&artist_id))
+    }
+
+    pub fn get_related_artists(&self, artist_id: String) -> RequestHandle<Vec<ArtistInfo>> {
+        self.api_handler.request(format!("related_artists:{}", artist_id), move |api| api.get_related_artists(&artist_id))
+    }
+}
+
+/// Lists the user's playlists, only getting the items of the ones whose snapshot changed since they were last loaded.
+fn load_user_playlists(api: &SpotifyAPIHandler, playlist_data: &RwLock<Vec<Arc<PlaylistData>>>) -> Result<()> {
+    let playlists = api.get_user_playlists()?;
+    let user_id = api.get_current_user_id().ok();
+    let previous = playlist_data.read().map(|lock| lock.clone()).unwrap_or_default();
+    let mut results = Vec::new();
+
+    for item in playlists.items {
+        // Skipped rather than failing the whole list over one odd id.
+        let id = match SpotifyId::from_base62(&item.id) {
+            Ok(id) => id,
+            Err(_) => continue
+        };
+
+        // Unchanged playlists keep their loaded track data.
+        if let Some(playlist) = previous.iter().find(|p| p.id() == Some(&id) && p.details().snapshot_id == item.snapshot_id) {
+            results.push(playlist.clone());
+            continue;
+        }
+
+        let items = match api.get_cached_playlist_items(&item.id, &item.snapshot_id) {
+            Some(items) => items,
+            None => match api.get_playlist_tracks(&item.id) {
+                Ok(items) => items,
+                Err(error) => {
+                    println!("Error loading playlist {}: {}", item.name, error.to_string());
+
+                    // The playlist is still there, so what was loaded before stays until the next refresh gets through.
+                    if let Some(playlist) = previous.iter().find(|p| p.id() == Some(&id)) {
+                        results.push(playlist.clone());
+                    }
+
+                    continue;
+                }
+            }
+        };
+
+        let editable = item.collaborative || user_id.as_ref() == Some(&item.owner.id);
+
+        let details = PlaylistDetails {
+            owner: item.owner.id,
+            // The list doesn't include it, the edit form loads it when it's opened.
+            description: previous.iter().find(|p| p.id() == Some(&id)).map(|p| p.details().description.clone()).unwrap_or_default(),
+            public: item.public.unwrap_or(false),
+            collaborative: item.collaborative,
+            snapshot_id: item.snapshot_id
+        };
+
+        results.push(Arc::new(PlaylistData::from_playlist(id, item.name, items, details, editable)));
+    }
+
+    if let Ok(mut lock) = playlist_data.write() {
+        // A playlist edited here while the list was loading keeps the edit, it's newer than what was fetched.
+        for playlist in results.iter_mut() {
+            let before = previous.iter().find(|p| p.kind() == playlist.kind());
+            let now = lock.iter().find(|p| p.kind() == playlist.kind());
+
+            if let (Some(before), Some(now)) = (before, now) {
+                if !Arc::ptr_eq(before, now) {
+                    *playlist = now.clone();
+                }
+            }
+        }
+
+        *lock = results.clone();
+    }
+
+    let cached = api.get_cached_playlists();
+    let unchanged = cached.len() == results.len() && cached.iter().zip(results.iter()).all(|(cached, playlist)| {
+        playlist.id().map(|id| id.to_base62()).as_ref() == Some(&cached.id) && playlist.details().snapshot_id == cached.snapshot_id
+    });
+
+    if !unchanged {
+        api.cache_playlists(results.iter().filter_map(|p| cached_playlist(p)).collect());
+    }
+
+    Ok(())
+}
+
+fn cached_playlist(playlist: &PlaylistData) -> Option<CachedPlaylist> {
+    let details = playlist.details();
+
+    Some(
+        CachedPlaylist {
+            id: playlist.id()?.to_base62(),
+            name: playlist.title().clone(),
+            owner: details.owner.clone(),
+            description: details.description.clone(),
+            public: details.public,
+            collaborative: details.collaborative,
+            editable: playlist.is_editable(),
+            snapshot_id: details.snapshot_id.clone(),
+            items: playlist.items().clone()
+        }
+    )
+}
+
+/// The user's playlists as of the last time they were loaded, shown until they're loaded again.
+fn cached_user_playlists(api: &SpotifyAPIHandler) -> Vec<Arc<PlaylistData>> {
+    api.get_cached_playlists().into_iter().filter_map(|cached| {
+        let id = SpotifyId::from_base62(&cached.id).ok()?;
+
+        let details = PlaylistDetails {
+            owner: cached.owner,
+            description: cached.description,
+            public: cached.public,
+            collaborative: cached.collaborative,
+            snapshot_id: cached.snapshot_id
+        };
+
+        Some(Arc::new(PlaylistData::from_playlist(id, cached.name, cached.items, details, cached.editable)))
+    }).collect()
+}
+
+/// Looks for changes made to the user's playlists elsewhere, until the handler is dropped.
+fn spawn_playlist_refresh(api: Weak<SpotifyAPIHandler>, playlist_data: Weak<RwLock<Vec<Arc<PlaylistData>>>>) {
+    std::thread::spawn(move || {
+        loop {
+            std::thread::sleep(PLAYLIST_REFRESH_INTERVAL);
+
+            let (api, playlist_data) = match (api.upgrade(), playlist_data.upgrade()) {
+                (Some(api), Some(playlist_data)) => (api, playlist_data),
+                _ => break
+            };
+
+            if let Err(error) = load_user_playlists(&api, &playlist_data) {
+                println!("Error refreshing playlists: {}", error.to_string());
+            }
+        }
+    });
+}
+
+/// Gets a single playlist's tracks and snapshot again, leaving the rest alone.
+fn reload_playlist(api: &SpotifyAPIHandler, playlist_data: &RwLock<Vec<Arc<PlaylistData>>>, playlist_id: &SpotifyId) -> Result<()> {
+    let snapshot_id = api.get_playlist_snapshot_id(&playlist_id.to_base62())?;
+    let items = api.get_playlist_tracks(&playlist_id.to_base62())?;
+
+    update_playlist(playlist_data, playlist_id, |playlist| Some(playlist.with_items(items).with_snapshot_id(snapshot_id)));
+
+    Ok(())
+}
+
+fn save_as_playlist(api: &SpotifyAPIHandler, playlist_data: &RwLock<Vec<Arc<PlaylistData>>>, name: &str, track_ids: Vec<String>) -> Result<()> {
+    let playlist = api.create_playlist(name, "", false)?;
+    let id = SpotifyId::from_base62(&playlist.id).map_err(|_| anyhow::Error::msg("Spotify returned an invalid playlist id"))?;
+
+    api.add_tracks_to_playlist(&playlist.id, &track_ids, None)?;
+
+    let details = PlaylistDetails {
+        owner: playlist.owner.id,
+        description: playlist.description.unwrap_or_default(),
+        public: false,
+        collaborative: false,
+        snapshot_id: playlist.snapshot_id
+    };
+
+    let items = track_ids.into_iter().map(PlaylistItem::track).collect();
+
+    if let Ok(mut lock) = playlist_data.write() {
+        lock.insert(0, Arc::new(PlaylistData::from_playlist(id, playlist.name, items, details, true)));
+    }
+
+    // Adding the tracks made a new snapshot.
+    refresh_snapshot_id(api, playlist_data, &id);
+    Ok(())
+}
+
+/// The first few top tracks of each artist, skipping artists whose top tracks can't be loaded.
+fn artists_top_track_ids(api: &SpotifyAPIHandler, artist_ids: &[String]) -> Vec<String> {
+    artist_ids.iter()
+        .filter_map(|id| api.get_artist_top_tracks(id).ok())
+        .flat_map(|tracks| tracks.into_iter().take(TOP_TRACKS_PER_ARTIST))
+        .map(|track| track.id().clone())
+        .collect()
+}
+
+/// Edits change the snapshot id, which has to be kept up to date for reordering to work.
+fn refresh_snapshot_id(api: &SpotifyAPIHandler, playlist_data: &RwLock<Vec<Arc<PlaylistData>>>, playlist_id: &SpotifyId) {
+    match api.get_playlist_snapshot_id(&playlist_id.to_base62()) {
+        Ok(snapshot_id) => update_playlist(playlist_data, playlist_id, |playlist| Some(playlist.with_snapshot_id(snapshot_id))),
+        Err(error) => println!("Error getting playlist snapshot: {}", error.to_string())
+    }
+}
+
+/// Replaces a playlist in the list with an updated version, or removes it if `update` returns None.
+fn update_playlist(playlist_data: &RwLock<Vec<Arc<PlaylistData>>>, playlist_id: &SpotifyId, update: impl FnOnce(&PlaylistData) -> Option<PlaylistData>) {
+    if let Ok(mut lock) = playlist_data.write() {
+        if let Some(idx) = lock.iter().position(|p| p.id() == Some(playlist_id)) {
+            match update(&lock[idx]) {
+                Some(playlist) => lock[idx] = Arc::new(playlist),
+                None => {
+                    lock.remove(idx);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum PlaylistKind {
+    Playlist(SpotifyId),
+    LikedSongs
+}
+
+/// Details only actual playlists have.
+#[derive(Clone, Default)]
+pub struct PlaylistDetails {
+    pub owner: String,
+    // The last one seen, playlist lists don't include it. The edit form loads the current one.
+    pub description: String,
+    pub public: bool,
+    pub collaborative: bool,
+    pub snapshot_id: String
+}
+
+/// The track data fetch of a playlist, shared with the copies made of it while it runs.
+#[derive(Default)]
+struct DataFetch {
+    running: bool,
+    // The rows of every copy, so whichever one is current gets the data as it lands.
+    targets: Vec<Weak<RwLock<Vec<PlaylistEntry>>>>
+}
+
+impl DataFetch {
+    fn add_target(&mut self, entries_data: &Arc<RwLock<Vec<PlaylistEntry>>>) {
+        self.targets.retain(|target| target.strong_count() > 0);
+        self.targets.push(Arc::downgrade(entries_data));
+    }
+
+    fn live_targets(&self) -> Vec<Arc<RwLock<Vec<PlaylistEntry>>>> {
+        self.targets.iter().filter_map(|target| target.upgrade()).collect()
+    }
+}
+
+pub struct PlaylistData {
+    kind: PlaylistKind,
+    title: String,
+    details: PlaylistDetails,
+    editable: bool,
+
+    items: Vec<PlaylistItem>,
+    entries_data: Arc<RwLock<Vec<PlaylistEntry>>>,
+
+    data_fetch: Arc<Mutex<DataFetch>>
+}
+
+impl PlaylistData {
+    pub fn new(kind: PlaylistKind, title: String, items: Vec<PlaylistItem>) -> PlaylistData {
+        // Every item gets a row right away, so rows always line up with positions in the playlist.
+        let entries_data = Arc::new(RwLock::new(items.iter().enumerate().map(|(idx, item)| PlaylistEntry::new(idx, item.clone())).collect()));
+
+        let mut data_fetch = DataFetch::default();
+        data_fetch.add_target(&entries_data);
+
+        PlaylistData {
+            kind,
+            title,
+            details: PlaylistDetails::default(),
+            editable: false,
+
+            items,
+            entries_data,
+
+            data_fetch: Arc::new(Mutex::new(data_fetch))
+        }
+    }
+
+    /// A list of tracks that isn't an actual playlist, like Liked Songs.
+    pub fn from_tracks(kind: PlaylistKind, title: String, track_ids: Vec<String>) -> PlaylistData {
+        PlaylistData::new(kind, title, track_ids.into_iter().map(PlaylistItem::track).collect())
+    }
+
+    pub fn from_playlist(id: SpotifyId, title: String, items: Vec<PlaylistItem>, details: PlaylistDetails, editable: bool) -> PlaylistData {
+        PlaylistData::from_playlist_kind(PlaylistKind::Playlist(id), title, items, details, editable)
+    }
+
+    /// A copy of this playlist with different items. The track data has to be fetched again.
+    pub fn with_items(&self, items: Vec<PlaylistItem>) -> PlaylistData {
+        self.derive(self.title.clone(), items, self.details.clone(), None)
+    }
+
+    /// A copy of this playlist with a track added at the end, keeping the data that's already loaded.
+    pub fn with_added_track(&self, track_id: String) -> PlaylistData {
+        let item = PlaylistItem::track(track_id);
+
+        let mut items = self.items.clone();
+        let mut data = self.current_data();
+
+        data.push(PlaylistEntry::new(items.len(), item.clone()));
+        items.push(item);
+
+        self.derive(self.title.clone(), items, self.details.clone(), Some(data))
+    }
+
+    pub fn with_changes(&self, changes: &PlaylistChanges) -> PlaylistData {
+        let mut details = self.details.clone();
+
+        if let Some(description) = changes.description.as_ref() {
+            details.description = description.clone();
+        }
+
+        if let Some(public) = changes.public {
+            details.public = public;
+        }
+
+        if let Some(collaborative) = changes.collaborative {
+            details.collaborative = collaborative;
+        }
+
+        let title = changes.name.clone().unwrap_or_else(|| self.title.clone());
+        self.derive(title, self.items.clone(), details, Some(self.current_data()))
+    }
+
+    /// A copy of this playlist with a range of items moved, like Spotify's reorder endpoint does it.
+    /// The loaded track data is moved along, so nothing has to be fetched again.
+    pub fn with_moved_tracks(&self, range_start: usize, range_length: usize, insert_before: usize) -> PlaylistData {
+        let mut items = self.items.clone();
+        let mut data = self.current_data();
+
+        move_range(&mut items, range_start, range_length, insert_before);
+        move_range(&mut data, range_start, range_length, insert_before);
+
+        self.derive(self.title.clone(), items, self.details.clone(), Some(data))
+    }
+
+    pub fn with_removed_tracks(&self, positions: &[usize]) -> PlaylistData {
+        let keep = |idx: &usize| !positions.contains(idx);
+
+        let items = self.items.iter().enumerate().filter(|(idx, _)| keep(idx)).map(|(_, i)| i.clone()).collect();
+        let data = self.current_data().into_iter().enumerate().filter(|(idx, _)| keep(idx)).map(|(_, e)| e).collect();
+
+        self.derive(self.title.clone(), items, self.details.clone(), Some(data))
+    }
+
+    /// Puts removed entries back at their positions, which have to be sorted.
+    pub fn with_restored_tracks(&self, entries: &[(usize, PlaylistEntry)]) -> PlaylistData {
+        let mut items = self.items.clone();
+        let mut data = self.current_data();
+
+        for (position, entry) in entries.iter() {
+            let position = (*position).min(items.len());
+
+            items.insert(position, entry.item.clone());
+            data.insert(position, entry.clone());
+        }
+
+        self.derive(self.title.clone(), items, self.details.clone(), Some(data))
+    }
+
+    pub fn with_snapshot_id(&self, snapshot_id: String) -> PlaylistData {
+        let mut details = self.details.clone();
+        details.snapshot_id = snapshot_id;
+
+        self.derive(self.title.clone(), self.items.clone(), details, Some(self.current_data()))
+    }
+
+    fn from_playlist_kind(kind: PlaylistKind, title: String, items: Vec<PlaylistItem>, details: PlaylistDetails, editable: bool) -> PlaylistData {
+        let mut playlist = PlaylistData::new(kind, title, items);
+        playlist.details = details;
+        playlist.editable = editable;
+
+        playlist
+    }
+
+    fn derive(&self, title: String, items: Vec<PlaylistItem>, details: PlaylistDetails, data: Option<Vec<PlaylistEntry>>) -> PlaylistData {
+        let mut playlist = PlaylistData::from_playlist_kind(self.kind, title, items, details, self.editable);
+
+        if let Some(mut data) = data {
+            // Edits change where items are, and the playlist's own order is what "#" sorts by.
+            for (idx, entry) in data.iter_mut().enumerate() {
+                entry.index = idx;
+            }
+
+            if let Ok(mut entries_data) = playlist.entries_data.write() {
+                *entries_data = data;
+            }
+        }
+
+        // A fetch that's still running fills in the copy too, instead of a second one starting over.
+        if let Ok(mut data_fetch) = self.data_fetch.lock() {
+            data_fetch.add_target(&playlist.entries_data);
+        }
+
+        playlist.data_fetch = self.data_fetch.clone();
+        playlist
+    }
+
+    fn current_data(&self) -> Vec<PlaylistEntry> {
+        if let Ok(lock) = self.entries_data.read() {
+            lock.clone()
+        }
+        else {
+            self.items.iter().enumerate().map(|(idx, item)| PlaylistEntry::new(idx, item.clone())).collect()
+        }
+    }
+
+    /// Whether no row is waiting on its track data anymore.
+    pub fn is_fully_loaded(&self) -> bool {
+        if let Ok(lock) = self.entries_data.read() {
+            lock.iter().all(|e| !e.is_loading())
+        }
+        else {
+            false
+        }
+    }
+
+    /// Fills in the track data, taking what it can from the cache and fetching the rest in batches.
+    /// A couple of batches run at a time on the request executor, and rows show up as each one lands.
+    /// Copies of the playlist made during the fetch get filled in too, so they don't need their own.
+    pub fn fetch_data(&self, api_handler: Arc<SpotifyAPIHandler>) {
+        if let Ok(mut data_fetch) = self.data_fetch.lock() {
+            if data_fetch.running || self.is_fully_loaded() {
+                return;
+            }
+            else {
+                data_fetch.running = true;
+            }
+        }
+
+        let mut fetched = HashMap::new();
+        let mut attempted = HashSet::new();
+
+        loop {
+            // Tracks added to a copy in the meantime get their own round.
+            let missing = self.missing_tracks(&fetched, &attempted);
+
+            if missing.is_empty() {
+                break;
+            }
+
+            attempted.extend(missing.iter().cloned());
+
+            let cached: Vec<TrackInfo> = api_handler.get_cached_tracks(&missing).into_iter().flatten().collect();
+            let uncached = missing.into_iter().filter(|id| !cached.iter().any(|t| t.id() == id)).collect();
+
+            self.add_fetched(&mut fetched, cached);
+            self.fetch_batches(&api_handler, uncached, &mut fetched);
+        }
+    }
+
+    fn fetch_batches(&self, api_handler: &Arc<SpotifyAPIHandler>, missing: Vec<String>, fetched: &mut HashMap<String, TrackInfo>) {
+        let mut batches: VecDeque<Vec<String>> = missing.chunks(TRACK_BATCH_SIZE).map(|chunk| chunk.to_vec()).collect();
+        let mut requests: Vec<RequestHandle<Vec<TrackInfo>>> = Vec::new();
+
+        while !requests.is_empty() || !batches.is_empty() {
+            // A big playlist would otherwise take up every worker, and everything else would wait on it.
+            while requests.len() < MAX_TRACK_BATCHES_IN_FLIGHT {
+                match batches.pop_front() {
+                    Some(chunk) => requests.push(api_handler.request(format!("tracks:{}", chunk.join(",")), move |api| api.fetch_tracks(&chunk))),
+                    None => break
+                }
+            }
+
+            let mut landed = Vec::new();
+
+            requests.retain(|request| {
+                if let Some(tracks) = request.result() {
+                    landed.extend(tracks);
+                    false
+                }
+                else if let Some(error) = request.error() {
+                    println!("Error fetching playlist tracks: {}", error);
+                    false
+                }
+                else {
+                    true
+                }
+            });
+
+            if landed.is_empty() {
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            else {
+                self.add_fetched(fetched, landed);
+            }
+        }
+    }
+
+    /// Ids of the tracks that still have to be fetched for any copy of the playlist, without duplicates.
+    /// Rows of tracks fetched earlier are filled in on the way. Once nothing is left to fetch,
+    /// whatever didn't come back is marked as unavailable and the fetch is done.
+    fn missing_tracks(&self, fetched: &HashMap<String, TrackInfo>, attempted: &HashSet<String>) -> Vec<String> {
+        let mut missing = Vec::new();
+
+        if let Ok(mut data_fetch) = self.data_fetch.lock() {
+            let targets = data_fetch.live_targets();
+
+            for target in targets.iter() {
+                if let Ok(mut lock) = target.write() {
+                    for entry in lock.iter_mut().filter(|e| e.is_loading()) {
+                        match entry.item.track_id() {
+                            Some(id) if fetched.contains_key(id) => entry.state = EntryState::Loaded(fetched[id].clone()),
+                            Some(id) if !attempted.contains(id) => missing.push(id.clone()),
+                            _ => ()
+                        }
+                    }
+                }
+            }
+
+            missing.sort_unstable();
+            missing.dedup();
+
+            if missing.is_empty() {
+                for target in targets.iter() {
+                    if let Ok(mut lock) = target.write() {
+                        for entry in lock.iter_mut().filter(|e| e.is_loading()) {
+                            entry.state = EntryState::Unavailable;
+                        }
+                    }
+                }
+
+                data_fetch.running = false;
+            }
+        }
+
+        missing
+    }
+
+    /// Fills in the rows of every copy of the playlist as tracks land.
+    fn add_fetched(&self, fetched: &mut HashMap<String, TrackInfo>, tracks: Vec<TrackInfo>) {
+        fetched.extend(tracks.into_iter().map(|t| (t.id().clone(), t)));
+
+        let targets = match self.data_fetch.lock() {
+            Ok(data_fetch) => data_fetch.live_targets(),
+            Err(_) => vec![self.entries_data.clone()]
+        };
+
+        for target in targets.iter() {
+            if let Ok(mut lock) = target.write() {
+                for entry in lock.iter_mut().filter(|e| e.is_loading()) {
+                    if let Some(track) = entry.item.track_id().and_then(|id| fetched.get(id)) {
+                        entry.state = EntryState::Loaded(track.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    /// How many rows are done loading, out of how many there are.
+    pub fn fetch_progress(&self) -> (usize, usize) {
+        if let Ok(lock) = self.entries_data.read() {
+            (lock.iter().filter(|e| !e.is_loading()).count(), lock.len())
+        }
+        else {
+            (0, self.items.len())
+        }
+    }
+
+    /// The playable tracks, in playlist order.
+    pub fn entries(&self) -> Vec<SpotifyId> {
+        self.items.iter().filter_map(|i| i.track_id()).filter_map(|id| SpotifyId::from_base62(id).ok()).collect()
+    }
+
+    pub fn items(&self) -> &Vec<PlaylistItem> {
+        &self.items
+    }
+
+    pub fn is_fetching(&self) -> bool {
+        if let Ok(lock) = self.data_fetch.lock() {
+            lock.running
+        }
+        else {
+            false
+        }
+    }
+
+    /// Get a reference to the playlist data's entries data.
+    pub fn entries_data(&self) -> &Arc<RwLock<Vec<PlaylistEntry>>> {
+        &self.entries_data
+    }
+
+    pub fn kind(&self) -> &PlaylistKind {
+        &self.kind
+    }
+
+    /// Whether the user owns the playlist or it's collaborative, so its tracks can be changed.
+    pub fn is_editable(&self) -> bool {
+        self.editable
+    }
+
+    pub fn details(&self) -> &PlaylistDetails {
+        &self.details
+    }
+
+    /// Get the playlist's id, if it's an actual playlist.
+    pub fn id(&self) -> Option<&SpotifyId> {
+        if let PlaylistKind::Playlist(id) = &self.kind {
+            Some(id)
+        }
+        else {
+            None
+        }
+    }
+
+    pub fn title(&self) -> &String {
+        &self.title
+    }
+}
+
+/// Results fetched per search request, and per "Load more".
+pub const SEARCH_PAGE_SIZE: u32 = 20;
+
+/// Tracks asked for per recommendations request, Spotify gives at most 100.
+const RECOMMENDATIONS_LIMIT: u32 = 50;
+
+/// Tracks taken from each artist when playing or saving a list of artists.
+const TOP_TRACKS_PER_ARTIST: usize = 3;
+
+/// Tracks fetched per request when filling in a playlist, the most Spotify takes at once.
+const TRACK_BATCH_SIZE: usize = 50;
+
+/// Track batches fetched at once per playlist, leaving the rest of the request executor's workers free.
+const MAX_TRACK_BATCHES_IN_FLIGHT: usize = 2;
+
+/// How long a removal can be undone for.
+pub const UNDO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often the user's playlists are checked for changes made on other devices.
+const PLAYLIST_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A change to a playlist's order, sent after the ones before it.
+#[derive(Clone)]
+enum PlaylistEdit {
+    Move {
+        range_start: usize,
+        range_length: usize,
+        insert_before: usize
+    },
+    /// The uris and positions of the removed tracks. `removed_at` tells which `RemovedTracks` it belongs to.
+    Remove {
+        tracks: Vec<(String, usize)>,
+        removed_at: Instant
+    }
+}
+
+impl PlaylistEdit {
+    fn is_removal(&self, at: Instant) -> bool {
+        match self {
+            PlaylistEdit::Remove { removed_at, .. } => *removed_at == at,
+            PlaylistEdit::Move { .. } => false
+        }
+    }
+}
+
+/// Liked Songs changes made here, so a list fetched before they reached Spotify doesn't undo them.
+#[derive(Default)]
+struct TrackSaves {
+    // Whether each track should end up saved, for tracks with a request still on its way.
+    pending: HashMap<String, bool>,
+    // When each track was last saved or removed here.
+    changed_at: HashMap<String, Instant>
+}
+
+/// Tracks removed from a playlist, kept around so the removal can be undone.
+#[derive(Clone)]
+pub struct RemovedTracks {
+    playlist_id: SpotifyId,
+    playlist_title: String,
+    entries: Vec<(usize, PlaylistEntry)>,
+    removed_at: Instant,
+    // Whether Spotify has the removal yet. Until then there's nothing to undo.
+    committed: bool
+}
+
+impl RemovedTracks {
+    pub fn playlist_title(&self) -> &String {
+        &self.playlist_title
+    }
+
+    pub fn track_count(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+/// Moves `range_length` items starting at `range_start` so they end up before the item at `insert_before`.
+/// Positions are from before the move, same as Spotify's reorder endpoint.
+pub fn move_range<T>(items: &mut Vec<T>, range_start: usize, range_length: usize, insert_before: usize) {
+    let range_end = range_start + range_length;
+
+    // Moving a range in front of itself or into itself doesn't change anything.
+    if range_end > items.len() || insert_before > items.len() || (insert_before >= range_start && insert_before <= range_end) {
+        return;
+    }
+
+    let moved: Vec<T> = items.drain(range_start..range_end).collect();
+    let insert_at = if insert_before > range_start { insert_before - range_length } else { insert_before };
+
+    items.splice(insert_at..insert_at, moved);
+}
+
+#[derive(Clone)]
+pub enum EntryState {
+    Loading,
+    Loaded(TrackInfo),
+    Unavailable,
+    LocalFile,
+    Episode
+}
+
+/// A row in a playlist. There's one for every item, whether its data could be loaded or not.
+#[derive(Clone)]
+pub struct PlaylistEntry {
+    index: usize,
+    item: PlaylistItem,
+    state: EntryState
+}
+
+impl PlaylistEntry {
+    fn new(index: usize, item: PlaylistItem) -> PlaylistEntry {
+        let state = match item.kind {
+            PlaylistItemKind::Track(_) => EntryState::Loading,
+            PlaylistItemKind::Episode(_) => EntryState::Episode,
+            PlaylistItemKind::Local => EntryState::LocalFile,
+            PlaylistItemKind::Unavailable => EntryState::Unavailable
+        };
+
+        PlaylistEntry {
+            index,
+            item,
+            state
+        }
+    }
+
+    /// The track's id, only there for tracks that can be played.
+    pub fn id(&self) -> Option<&String> {
+        if let EntryState::Loaded(track) = &self.state {
+            Some(track.id())
+        }
+        else {
+            None
+        }
+    }
+
+    /// Where the item is in the playlist.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn state(&self) -> &EntryState {
+        &self.state
+    }
+
+    pub fn is_loading(&self) -> bool {
+        matches!(self.state, EntryState::Loading)
+    }
+
+    /// Only tracks can be removed through the API, local files and episodes can't.
+    pub fn is_removable(&self) -> bool {
+        self.item.track_id().is_some()
+    }
+
+    pub fn title(&self) -> &str {
+        match &self.state {
+            EntryState::Loaded(track) => track.name(),
+            EntryState::Loading if self.item.name.is_empty() => "Loading...",
+            EntryState::Unavailable if self.item.name.is_empty() => "Unavailable",
+            _ => &self.item.name
+        }
+    }
+
+    pub fn artist(&self) -> &str {
+        if let EntryState::Loaded(track) = &self.state {
+            track.artists().first().map(|a| a.as_str()).unwrap_or("")
+        }
+        else {
+            ""
+        }
+    }
+
+    pub fn duration(&self) -> u32 {
+        if let EntryState::Loaded(track) = &self.state {
+            *track.duration()
+        }
+        else {
+            0
+        }
+    }
+
+    /// The id of the track's album, if its data is loaded.
+    pub fn album(&self) -> Option<&String> {
+        if let EntryState::Loaded(track) = &self.state {
+            Some(track.album())
+        }
+        else {
+            None
+        }
+    }
+
+    pub fn album_name(&self) -> &str {
+        if let EntryState::Loaded(track) = &self.state {
+            track.album_name()
+        }
+        else {
+            ""
+        }
+    }
+
+    pub fn added_at(&self) -> Option<&String> {
+        self.item.added_at.as_ref()
+    }
+
+    pub fn added_by(&self) -> Option<&String> {
+        self.item.added_by.as_ref()
+    }
+
+    pub fn uri(&self) -> &String {
+        &self.item.uri
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::move_range;
+
+    fn moved(range_start: usize, range_length: usize, insert_before: usize) -> Vec<u32> {
+        let mut items = vec![0, 1, 2, 3, 4];
+        move_range(&mut items, range_start, range_length, insert_before);
+
+        items
+    }
+
+    #[test]
+    fn move_range_forwards_and_backwards() {
+        assert_eq!(moved(0, 2, 3), vec![2, 0, 1, 3, 4]);
+        assert_eq!(moved(3, 2, 1), vec![0, 3, 4, 1, 2]);
+        assert_eq!(moved(4, 1, 0), vec![4, 0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn move_range_to_the_end() {
+        assert_eq!(moved(0, 2, 5), vec![2, 3, 4, 0, 1]);
+        assert_eq!(moved(2, 1, 5), vec![0, 1, 3, 4, 2]);
     }
 
-    pub fn artist(&self) -> &String {
-        &self.artist
+    #[test]
+    fn move_range_into_itself_does_nothing() {
+        assert_eq!(moved(1, 3, 1), vec![0, 1, 2, 3, 4]);
+        assert_eq!(moved(1, 3, 2), vec![0, 1, 2, 3, 4]);
+        assert_eq!(moved(1, 3, 4), vec![0, 1, 2, 3, 4]);
     }
 
-    pub fn duration(&self) -> &u32 {
-        self.track.duration()
+    #[test]
+    fn move_range_out_of_range_does_nothing() {
+        assert_eq!(moved(4, 2, 0), vec![0, 1, 2, 3, 4]);
+        assert_eq!(moved(6, 1, 0), vec![0, 1, 2, 3, 4]);
+        assert_eq!(moved(0, 1, 6), vec![0, 1, 2, 3, 4]);
     }
 }
@@ -20,10 +20,29 @@ pub enum PlayerCommand {
     StartPlaylist(Vec<SpotifyId>)
 }
 
+/// Where the tracks in the queue came from.
+#[derive(Clone, Copy, PartialEq)]
+pub enum PlayContext {
+    Tracks,
+    Album(SpotifyId),
+    /// An artist's top tracks.
+    Artist(SpotifyId),
+    Recommendations
+}
+
+impl Default for PlayContext {
+    fn default() -> PlayContext {
+        PlayContext::Tracks
+    }
+}
+
 #[derive(Default)]
 pub struct PlayerQueue {
     position: usize,
-    tracks: Vec<SpotifyId>
+    tracks: Vec<SpotifyId>,
+
+    context: PlayContext,
+    in_order: bool
 }
 
 impl PlayerQueue {
@@ -33,6 +52,24 @@ impl PlayerQueue {
         
         self.position = 0;
         self.tracks = tracks;
+
+        self.context = PlayContext::Tracks;
+        self.in_order = false;
+    }
+
+    /// Queues the tracks of an album or another context, in their own order unless `shuffle` is set.
+    pub fn init_context(&mut self, context: PlayContext, tracks: Vec<SpotifyId>, shuffle: bool) {
+        let mut tracks = tracks;
+
+        if shuffle {
+            tracks.shuffle(&mut thread_rng());
+        }
+
+        self.position = 0;
+        self.tracks = tracks;
+
+        self.context = context;
+        self.in_order = !shuffle;
     }
 
     pub fn set_position_with_id(&mut self, id: SpotifyId) {
@@ -47,9 +84,13 @@ impl PlayerQueue {
         }
     }
 
+    /// Starts over once the queue runs out. Contexts played in order just go back to the first track.
     pub fn reshuffle_tracks(&mut self) {
         self.position = 0;
-        self.tracks.shuffle(&mut thread_rng());
+
+        if !self.in_order {
+            self.tracks.shuffle(&mut thread_rng());
+        }
     }
 }
 
@@ -182,12 +223,25 @@ impl PlayerHandler {
         self.player_queue.tracks.get(self.player_queue.position).cloned()
     }
 
+    pub fn get_context(&self) -> PlayContext {
+        self.player_queue.context
+    }
+
     pub fn is_queue_loaded(&self) -> bool {
         !self.player_queue.tracks.is_empty()
     }
 
+    /// Pauses without touching the queue, so playback can pick up where it was.
+    pub fn pause(&mut self) {
+        if self.track_playing {
+            self.player.pause();
+            self.track_playing = false;
+        }
+    }
+
     pub fn play_single_track(&mut self, track: SpotifyId) {
         self.player_queue.tracks = vec![track];
+        self.player_queue.context = PlayContext::Tracks;
         self.load_track_and_play();
     }
 
@@ -198,6 +252,17 @@ impl PlayerHandler {
         self.load_track_and_play();
     }
 
+    /// Plays the tracks of a context, from `start` if it's given or from the first track otherwise.
+    pub fn play_context(&mut self, context: PlayContext, tracks: Vec<SpotifyId>, shuffle: bool, start: Option<SpotifyId>) {
+        self.player_queue.init_context(context, tracks, shuffle);
+
+        if let Some(track) = start {
+            self.player_queue.set_position_with_id(track);
+        }
+
+        self.load_track_and_play();
+    }
+
     fn load_track_and_play(&mut self) {
         if let Some(track_id) = self.player_queue.tracks.get(self.player_queue.position) {
             self.player.load(*track_id, true, 0);
@@ -0,0 +1,215 @@
+/// A search split into the field filters Spotify's search understands, and whatever text is left.
+#[derive(Clone, Default, PartialEq)]
+pub struct SearchQuery {
+    pub text: String,
+    pub artist: String,
+    pub album: String,
+    pub year: String,
+    pub genre: String,
+    pub tag_new: bool,
+    pub tag_hipster: bool
+}
+
+impl SearchQuery {
+    /// Picks the filters out of a typed query, like `artist:"daft punk" year:2000-2005 tag:new around`.
+    /// Anything that isn't a known filter stays in the text.
+    pub fn parse(query: &str) -> SearchQuery {
+        let mut parsed = SearchQuery::default();
+        let mut text = Vec::new();
+
+        for token in tokenize(query) {
+            let (key, value) = match token.find(':') {
+                Some(split) => (token[..split].to_lowercase(), unquote(&token[split + 1..])),
+                None => {
+                    text.push(token);
+                    continue;
+                }
+            };
+
+            match (key.as_str(), value.to_lowercase().as_str()) {
+                ("artist", _) => parsed.artist = value,
+                ("album", _) => parsed.album = value,
+                ("year", _) => parsed.year = value,
+                ("genre", _) => parsed.genre = value,
+                ("tag", "new") => parsed.tag_new = true,
+                ("tag", "hipster") => parsed.tag_hipster = true,
+                _ => text.push(token)
+            }
+        }
+
+        parsed.text = text.join(" ");
+        parsed
+    }
+
+    /// The query string to send to Spotify.
+    pub fn to_query_string(&self) -> String {
+        let mut parts = Vec::new();
+
+        if !self.text.trim().is_empty() {
+            parts.push(self.text.trim().to_string());
+        }
+
+        for (key, value) in [("artist", &self.artist), ("album", &self.album), ("year", &self.year), ("genre", &self.genre)].iter() {
+            let value = value.trim();
+
+            if value.is_empty() {
+                continue;
+            }
+
+            if value.contains(' ') {
+                parts.push(format!("{}:\"{}\"", key, value));
+            }
+            else {
+                parts.push(format!("{}:{}", key, value));
+            }
+        }
+
+        if self.tag_new {
+            parts.push(String::from("tag:new"));
+        }
+
+        if self.tag_hipster {
+            parts.push(String::from("tag:hipster"));
+        }
+
+        parts.join(" ")
+    }
+
+    /// Why the year filter wouldn't work, if it wouldn't. It has to be a year or a range like `1990-1999`.
+    pub fn year_error(&self) -> Option<String> {
+        let year = self.year.trim();
+
+        if year.is_empty() {
+            return None;
+        }
+
+        let parse_year = |year: &str| year.trim().parse::<u32>().ok().filter(|y| (1000..=9999).contains(y));
+
+        match year.split_once('-') {
+            Some((start, end)) => match (parse_year(start), parse_year(end)) {
+                (Some(start), Some(end)) if start <= end => None,
+                (Some(_), Some(_)) => Some(String::from("The range has to start before it ends")),
+                _ => Some(String::from("Years have to look like 1995 or 1990-1999"))
+            },
+            None if parse_year(year).is_some() => None,
+            None => Some(String::from("Years have to look like 1995 or 1990-1999"))
+        }
+    }
+}
+
+/// Why a market override wouldn't work, if it wouldn't. Markets are two letter country codes like `DE`.
+pub fn market_error(market: &str) -> Option<String> {
+    if market.is_empty() || (market.len() == 2 && market.chars().all(|c| c.is_ascii_alphabetic())) {
+        None
+    }
+    else {
+        Some(String::from("Markets are two letter country codes, like DE"))
+    }
+}
+
+/// Splits on whitespace, except inside double quotes.
+fn tokenize(query: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+
+    for c in query.chars() {
+        if c == '"' {
+            quoted = !quoted;
+            current.push(c);
+        }
+        else if c.is_whitespace() && !quoted {
+            if !current.is_empty() {
+                tokens.push(std::mem::take(&mut current));
+            }
+        }
+        else {
+            current.push(c);
+        }
+    }
+
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    tokens
+}
+
+fn unquote(value: &str) -> String {
+    value.trim_matches('"').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_filters_and_quoted_values() {
+        let query = SearchQuery::parse(r#"around artist:"daft punk" year:1997-2001 tag:new the world"#);
+
+        assert_eq!(query.text, "around the world");
+        assert_eq!(query.artist, "daft punk");
+        assert_eq!(query.year, "1997-2001");
+        assert!(query.tag_new);
+        assert!(!query.tag_hipster);
+    }
+
+    #[test]
+    fn unknown_filters_stay_in_the_text() {
+        let query = SearchQuery::parse("label:warp tag:old Album:selected");
+
+        assert_eq!(query.text, "label:warp tag:old");
+        assert_eq!(query.album, "selected");
+    }
+
+    #[test]
+    fn query_string_quotes_values_with_spaces() {
+        let query = SearchQuery {
+            text: String::from(" windowlicker "),
+            artist: String::from("aphex twin"),
+            genre: String::from("idm"),
+            tag_hipster: true,
+            ..Default::default()
+        };
+
+        assert_eq!(query.to_query_string(), r#"windowlicker artist:"aphex twin" genre:idm tag:hipster"#);
+    }
+
+    #[test]
+    fn query_string_round_trips() {
+        let query = SearchQuery {
+            text: String::from("one more time"),
+            artist: String::from("daft punk"),
+            album: String::from("discovery"),
+            year: String::from("2001"),
+            genre: String::from("french house"),
+            tag_new: true,
+            tag_hipster: true
+        };
+
+        assert!(SearchQuery::parse(&query.to_query_string()) == query);
+        assert!(SearchQuery::parse("") == SearchQuery::default());
+    }
+
+    #[test]
+    fn year_errors() {
+        let year_error = |year: &str| SearchQuery { year: String::from(year), ..Default::default() }.year_error();
+
+        assert_eq!(year_error(""), None);
+        assert_eq!(year_error("1995"), None);
+        assert_eq!(year_error("1990-1999"), None);
+        assert_eq!(year_error("1999-1999"), None);
+        assert_eq!(year_error("1999-1990"), Some(String::from("The range has to start before it ends")));
+        assert!(year_error("95").is_some());
+        assert!(year_error("1990-").is_some());
+        assert!(year_error("nineties").is_some());
+    }
+
+    #[test]
+    fn market_errors() {
+        assert_eq!(market_error(""), None);
+        assert_eq!(market_error("DE"), None);
+        assert!(market_error("DEU").is_some());
+        assert!(market_error("D1").is_some());
+    }
+}
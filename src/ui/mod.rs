@@ -1,3 +1,5 @@
+mod search_history;
+mod widgets;
 mod windows;
 
 use std::sync::Arc;
@@ -14,29 +16,37 @@ use glium::glutin::window::WindowBuilder;
 use glium::glutin::event::{Event, WindowEvent};
 use glium::glutin::event_loop::{ControlFlow, EventLoop};
 
-use rspotify::model::track::FullTrack;
-use rspotify::model::artist::FullArtist;
-
+use windows::album_window::AlbumWindow;
 use windows::artist_window::ArtistWindow;
+use windows::browse_window::BrowseWindow;
+use windows::devices_window::DevicesWindow;
 use windows::login_window::LoginWindow;
 use windows::main_window::MainWindow;
 use windows::player_window::PlayerWindow;
 use windows::playlist_window::PlaylistWindow;
+use windows::recommendations_window::RecommendationsWindow;
+use windows::search_window::SearchWindow;
+use windows::stats_window::StatsWindow;
 
 use crate::spotify::player::PlayerCommand;
-use crate::spotify::api::cache::TrackInfo;
 use crate::spotify::{SpotifyHandler, PlaylistData};
+use crate::spotify::api::provider::RecommendationSeed;
 
 pub struct AppState {
-    search_query: String,
-    search_results_tracks: Vec<FullTrack>,
-    search_results_artists: Vec<FullArtist>,
-    search_artist_page_tracks: Vec<TrackInfo>,
+    artist_page: Option<(String, String)>,
+    album_page: Option<String>,
 
+    show_album_window: bool,
     show_artist_window: bool,
+    show_browse_window: bool,
+    show_devices_window: bool,
     show_player_window: bool,
     show_search_window: bool,
+    show_stats_window: bool,
     show_playlist_window: bool,
+    show_recommendations_window: bool,
+
+    recommendation_seeds: Vec<RecommendationSeed>,
 
     playlist_data: Option<Arc<PlaylistData>>,
     spotify_handler: Option<SpotifyHandler>,
@@ -46,21 +56,36 @@ pub struct AppState {
 impl AppState {
     pub fn new() -> AppState {
         AppState {
-            search_query: String::new(),
-            search_results_tracks: Vec::new(),
-            search_results_artists: Vec::new(),
-            search_artist_page_tracks: Vec::new(),
+            artist_page: None,
+            album_page: None,
 
+            show_album_window: false,
             show_artist_window: false,
+            show_browse_window: false,
+            show_devices_window: false,
             show_player_window: false,
             show_search_window: false,
+            show_stats_window: false,
             show_playlist_window: false,
+            show_recommendations_window: false,
+
+            recommendation_seeds: Vec::new(),
 
             playlist_data: None,
             spotify_handler: None,
             player_tx: None
         }
     }
+
+    pub fn open_artist(&mut self, artist_id: String, artist_name: String) {
+        self.artist_page = Some((artist_id, artist_name));
+        self.show_artist_window = true;
+    }
+
+    pub fn open_album(&mut self, album_id: String) {
+        self.album_page = Some(album_id);
+        self.show_album_window = true;
+    }
 }
 
 pub struct App {
@@ -114,10 +139,16 @@ impl App {
 
         let mut login_window = LoginWindow::init();
         
+        let mut album_window: Option<AlbumWindow> = None;
         let mut artist_window: Option<ArtistWindow> = None;
         let mut main_window: Option<MainWindow> = None;
         let mut player_window: Option<PlayerWindow> = None;
         let mut playlist_window: Option<PlaylistWindow> = None;
+        let mut browse_window = BrowseWindow::init();
+        let mut search_window = SearchWindow::init();
+        let mut recommendations_window = RecommendationsWindow::init();
+        let mut stats_window = StatsWindow::init();
+        let mut devices_window = DevicesWindow::init();
 
         let ch_font = std::fs::read("fonts/chinese.otf").unwrap_or_else(|_| Vec::new());
         let jp_font = std::fs::read("fonts/japanese.otf").unwrap_or_else(|_| Vec::new());
@@ -204,17 +235,13 @@ impl App {
                     let username = login_window.draw(&ui, &mut app_state);
 
                     if !username.is_empty() {
-                        let playlists = {
-                            if let Some(handler) = app_state.spotify_handler.as_mut() {
-                                handler.fetch_user_playlists();
-                                handler.get_playlists_names()
-                            }
-                            else {
-                                Vec::new()
-                            }
-                        };
-
-                        main_window = Some(MainWindow::init(username, playlists));
+                        if let Some(handler) = app_state.spotify_handler.as_mut() {
+                            handler.fetch_user_playlists();
+                            handler.fetch_liked_songs();
+                            handler.fetch_library();
+                        }
+
+                        main_window = Some(MainWindow::init(username));
                     }
                 }
                 else {
@@ -222,17 +249,48 @@ impl App {
                         window.draw(&ui, &mut app_state);
                     }
 
+                    if let Some((artist_id, artist_name)) = app_state.artist_page.take() {
+                        if let Some(handler) = app_state.spotify_handler.as_ref() {
+                            artist_window = Some(ArtistWindow::init(artist_id, artist_name, handler));
+                        }
+                    }
+
                     if app_state.show_artist_window {
                         if let Some(window) = artist_window.as_mut() {
                             window.draw(&ui, &mut app_state);
                         }
-                        else {
-                            artist_window = Some(ArtistWindow::init(app_state.search_query.clone(), app_state.search_artist_page_tracks.clone()));
+                    }
+
+                    if let Some(album_id) = app_state.album_page.take() {
+                        if let Some(handler) = app_state.spotify_handler.as_ref() {
+                            album_window = Some(AlbumWindow::init(handler.get_album(album_id)));
                         }
                     }
 
+                    if app_state.show_album_window {
+                        if let Some(window) = album_window.as_mut() {
+                            window.draw(&ui, &mut app_state);
+                        }
+                    }
+
+                    if app_state.show_browse_window {
+                        browse_window.draw(&ui, &mut app_state);
+                    }
+
                     if app_state.show_search_window {
-                        windows::search_window::build(&ui, &mut app_state);
+                        search_window.draw(&ui, &mut app_state);
+                    }
+
+                    if app_state.show_recommendations_window {
+                        recommendations_window.draw(&ui, &mut app_state);
+                    }
+
+                    if app_state.show_stats_window {
+                        stats_window.draw(&ui, &mut app_state);
+                    }
+
+                    if app_state.show_devices_window {
+                        devices_window.draw(&ui, &mut app_state);
                     }
 
                     if app_state.show_player_window {
@@ -244,13 +302,16 @@ impl App {
                         }
                     }
 
+                    widgets::undo_toast(&ui, &mut app_state.spotify_handler);
+
+                    if let Some(playlist) = app_state.playlist_data.take() {
+                        playlist_window = Some(PlaylistWindow::init(playlist));
+                    }
+
                     if app_state.show_playlist_window {
                         if let Some(window) = playlist_window.as_mut() {
                             window.draw(&ui, &mut app_state);
                         }
-                        else if let Some(playlist) = app_state.playlist_data.as_ref() {
-                            playlist_window = Some(PlaylistWindow::init(playlist.clone()));
-                        }
                     }
                 }
 
@@ -270,6 +331,12 @@ impl App {
             Event::WindowEvent { event: WindowEvent::CloseRequested, ..} => {
                 *control_flow = ControlFlow::Exit
             }
+            Event::LoopDestroyed => {
+                // The process exits right after this, without dropping anything.
+                if let Some(handler) = app_state.spotify_handler.as_ref() {
+                    handler.flush_cache();
+                }
+            }
             event => {
                 let gl_window = display.gl_window();
                 platform.handle_event(imgui.io_mut(), gl_window.window(), &event);
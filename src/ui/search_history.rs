@@ -0,0 +1,86 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+const MAX_RECENT: usize = 20;
+
+/// Recent and pinned search queries, saved next to the rest of imguify's data.
+#[derive(Default, Deserialize, Serialize)]
+pub struct SearchHistory {
+    recent: Vec<String>,
+    pinned: Vec<String>
+}
+
+impl SearchHistory {
+    pub fn load() -> SearchHistory {
+        history_path().and_then(|path| serde_any::from_file(path).ok()).unwrap_or_default()
+    }
+
+    fn save(&self) {
+        if let Some(path) = history_path() {
+            if let Some(parent) = path.parent() {
+                if let Err(error) = std::fs::create_dir_all(parent) {
+                    println!("Error saving search history: {}", error.to_string());
+                    return;
+                }
+            }
+
+            if let Err(error) = serde_any::to_file(path, self) {
+                println!("Error saving search history: {}", error.to_string());
+            }
+        }
+    }
+
+    pub fn recent(&self) -> &Vec<String> {
+        &self.recent
+    }
+
+    pub fn pinned(&self) -> &Vec<String> {
+        &self.pinned
+    }
+
+    pub fn is_pinned(&self, query: &str) -> bool {
+        self.pinned.iter().any(|q| q == query)
+    }
+
+    /// Moves a query to the top of the recent searches. Pinned queries stay where they are.
+    pub fn add(&mut self, query: &str) {
+        if query.is_empty() || self.is_pinned(query) {
+            return;
+        }
+
+        self.recent.retain(|q| q != query);
+        self.recent.insert(0, query.to_string());
+        self.recent.truncate(MAX_RECENT);
+
+        self.save();
+    }
+
+    pub fn pin(&mut self, query: &str) {
+        if self.is_pinned(query) {
+            return;
+        }
+
+        self.recent.retain(|q| q != query);
+        self.pinned.push(query.to_string());
+
+        self.save();
+    }
+
+    pub fn unpin(&mut self, query: &str) {
+        self.pinned.retain(|q| q != query);
+        self.add(query);
+    }
+
+    pub fn remove(&mut self, query: &str) {
+        self.recent.retain(|q| q != query);
+        self.save();
+    }
+}
+
+fn history_path() -> Option<PathBuf> {
+    let mut path = dirs::cache_dir()?;
+    path.push("imguify/data/search_history.ron");
+
+    Some(path)
+}
@@ -0,0 +1,173 @@
+use imgui::*;
+
+use crate::spotify::SpotifyHandler;
+use crate::spotify::api::executor::RequestHandle;
+use crate::spotify::api::provider::{RecommendationSeed, MAX_SEEDS};
+
+/// A heart toggling whether a track is in the user's Liked Songs.
+/// Takes the handler directly, so it can be used while iterating over other parts of the app state.
+pub fn like_button(ui: &Ui, spotify_handler: &mut Option<SpotifyHandler>, track_id: &str) {
+    if let Some(handler) = spotify_handler.as_mut() {
+        let saved = handler.is_track_saved(track_id);
+        let size = ui.frame_height();
+        let pos = ui.cursor_screen_pos();
+
+        if ui.invisible_button(format!("##like_{}", track_id), [size, size]) {
+            handler.set_track_saved(track_id, !saved);
+        }
+
+        let hovered = ui.is_item_hovered();
+
+        if hovered {
+            ui.tooltip_text(if saved { "Remove from Liked Songs" } else { "Save to Liked Songs" });
+        }
+
+        let color = match (saved, hovered) {
+            (true, false) => [0.11, 0.73, 0.33, 1.0],
+            (true, true) => [0.2, 0.85, 0.42, 1.0],
+            (false, false) => [0.45, 0.45, 0.45, 1.0],
+            (false, true) => [0.7, 0.7, 0.7, 1.0]
+        };
+
+        draw_heart(ui, [pos[0] + size / 2.0, pos[1] + size / 2.0], size * 0.2, color);
+    }
+}
+
+/// The default font has no heart glyph, so it's drawn as two circles on top of a triangle.
+fn draw_heart(ui: &Ui, center: [f32; 2], radius: f32, color: [f32; 4]) {
+    let draw_list = ui.get_window_draw_list();
+    let [x, y] = center;
+    let top = y - radius * 0.5;
+
+    draw_list.add_circle([x - radius, top], radius, color).filled(true).build();
+    draw_list.add_circle([x + radius, top], radius, color).filled(true).build();
+    draw_list.add_triangle([x - radius * 1.95, top + radius * 0.3], [x + radius * 1.95, top + radius * 0.3], [x, y + radius * 2.0], color).filled(true).build();
+}
+
+/// A button toggling whether the user follows an artist.
+pub fn follow_button(ui: &Ui, spotify_handler: &mut Option<SpotifyHandler>, artist_id: &str) {
+    if let Some(handler) = spotify_handler.as_mut() {
+        let followed = handler.is_artist_followed(artist_id);
+        let label = if followed { "Unfollow" } else { "Follow" };
+
+        if ui.button(format!("{}##{}", label, artist_id)) {
+            handler.set_artist_followed(artist_id, !followed);
+        }
+    }
+}
+
+/// A button toggling whether an album is saved to the user's library.
+pub fn save_album_button(ui: &Ui, spotify_handler: &mut Option<SpotifyHandler>, album_id: &str) {
+    if let Some(handler) = spotify_handler.as_mut() {
+        let saved = handler.is_album_saved(album_id);
+        let label = if saved { "Remove from Library" } else { "Save to Library" };
+
+        if ui.button(format!("{}##{}", label, album_id)) {
+            handler.set_album_saved(album_id, !saved);
+        }
+    }
+}
+
+/// An album's name that can be clicked to open the album. Returns whether it was clicked.
+pub fn album_link(ui: &Ui, album_name: &str, album_id: &str) -> bool {
+    let label = if album_name.is_empty() { "View album" } else { album_name };
+    Selectable::new(format!("{}##album_{}", label, album_id)).build(ui)
+}
+
+/// A button adding a track, artist or genre to the recommendation seeds, or taking it out again.
+pub fn seed_button(ui: &Ui, seeds: &mut Vec<RecommendationSeed>, seed: RecommendationSeed) {
+    let seeded = seeds.contains(&seed);
+    let label = if seeded { "Unseed" } else { "Seed" };
+
+    if ui.button(format!("{}##seed_{}", label, seed.id())) {
+        if seeded {
+            seeds.retain(|s| *s != seed);
+        }
+        else if seeds.len() < MAX_SEEDS {
+            seeds.push(seed);
+        }
+    }
+
+    if !seeded && seeds.len() >= MAX_SEEDS && ui.is_item_hovered() {
+        ui.tooltip_text(format!("Recommendations take at most {} seeds", MAX_SEEDS));
+    }
+}
+
+/// A button opening a menu of the user's editable playlists to add a track to.
+pub fn add_to_playlist_button(ui: &Ui, spotify_handler: &mut Option<SpotifyHandler>, track_id: &str) {
+    if let Some(handler) = spotify_handler.as_mut() {
+        let popup_id = format!("add_to_playlist##{}", track_id);
+
+        if ui.button(format!("Add to...##{}", track_id)) {
+            ui.open_popup(&popup_id);
+        }
+
+        ui.popup(&popup_id, || {
+            ui.menu("Add to playlist", || {
+                let playlists = handler.get_editable_playlists();
+
+                if playlists.is_empty() {
+                    MenuItem::new("No editable playlists").enabled(false).build(ui);
+                }
+
+                for playlist in playlists.iter() {
+                    if let Some(playlist_id) = playlist.id() {
+                        if MenuItem::new(format!("{}##{}", playlist.title(), playlist_id.to_base62())).build(ui) {
+                            handler.add_track_to_playlist(playlist_id, track_id);
+                        }
+                    }
+                }
+            });
+        });
+    }
+}
+
+/// A small notice in the corner after tracks were removed from a playlist, with a button to put them back.
+pub fn undo_toast(ui: &Ui, spotify_handler: &mut Option<SpotifyHandler>) {
+    if let Some(handler) = spotify_handler.as_mut() {
+        if let Some(removed) = handler.get_last_removal() {
+            let [width, height] = ui.io().display_size;
+
+            let flags = WindowFlags::NO_DECORATION
+                | WindowFlags::ALWAYS_AUTO_RESIZE
+                | WindowFlags::NO_MOVE
+                | WindowFlags::NO_SAVED_SETTINGS
+                | WindowFlags::NO_FOCUS_ON_APPEARING
+                | WindowFlags::NO_NAV
+            ;
+
+            Window::new("Undo Removal")
+                .position([width - 20.0, height - 20.0], Condition::Always)
+                .position_pivot([1.0, 1.0])
+                .flags(flags)
+                .build(ui, || {
+                    let tracks = if removed.track_count() == 1 { String::from("1 track") } else { format!("{} tracks", removed.track_count()) };
+                    ui.text(format!("Removed {} from {}", tracks, removed.playlist_title()));
+
+                    ui.same_line();
+
+                    if ui.button("Undo") {
+                        handler.undo_last_removal();
+                    }
+
+                    ui.same_line();
+
+                    if ui.button("Dismiss") {
+                        handler.dismiss_last_removal();
+                    }
+                });
+        }
+    }
+}
+
+/// Shows whether a section is still loading, or why it couldn't be loaded.
+pub fn request_status<T: Clone>(ui: &Ui, request: &Option<RequestHandle<T>>, section: &str) {
+    if let Some(request) = request.as_ref() {
+        if let Some(error) = request.error() {
+            ui.text_colored([1.0, 0.0, 0.0, 1.0], format!("Couldn't load {}: {}", section, error));
+        }
+        else {
+            ui.text("Loading...");
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use crate::ui::AppState;
+use crate::ui::widgets;
+use crate::spotify::api::cache::AlbumInfo;
+use crate::spotify::api::executor::RequestHandle;
+use crate::spotify::api::provider::RecommendationSeed;
+
+use imgui::*;
+
+pub struct AlbumWindow {
+    album: Option<AlbumInfo>,
+    album_request: Option<RequestHandle<AlbumInfo>>
+}
+
+impl AlbumWindow {
+    pub fn init(album_request: RequestHandle<AlbumInfo>) -> AlbumWindow {
+        AlbumWindow {
+            album: None,
+            album_request: Some(album_request)
+        }
+    }
+
+    pub fn draw(&mut self, ui: &Ui, app_state: &mut AppState) {
+        let mut show_window = app_state.show_album_window;
+
+        if let Some(album) = self.album_request.as_ref().and_then(|r| r.result()) {
+            self.album = Some(album);
+            self.album_request = None;
+        }
+
+        let title = self.album.as_ref().map(|a| a.name().clone()).unwrap_or_else(|| String::from("Album"));
+
+        Window::new(format!("{}###Album", title)).size([600.0, 400.0], Condition::FirstUseEver).opened(&mut show_window).build(ui, || {
+            if let Some(request) = self.album_request.as_ref() {
+                if let Some(error) = request.error() {
+                    ui.text_colored([1.0, 0.0, 0.0, 1.0], format!("Couldn't load album: {}", error));
+                }
+                else {
+                    ui.text("Loading...");
+                }
+            }
+
+            let album = match self.album.as_ref() {
+                Some(album) => album,
+                None => return
+            };
+
+            ui.text(album.artists().join(", "));
+
+            if !album.release_date().is_empty() {
+                ui.text(format!("Released {}", album.release_date()));
+            }
+
+            if !album.label().is_empty() {
+                ui.text_disabled(album.label());
+            }
+
+            let total_duration: u32 = album.tracklist().iter().map(|t| t.duration()).sum();
+            ui.text(format!("{} tracks, {} min", album.tracklist().len(), total_duration / 60000));
+
+            let mut play = None;
+
+            if ui.button("Play") {
+                play = Some((false, None));
+            }
+
+            ui.same_line();
+
+            if ui.button("Shuffle") {
+                play = Some((true, None));
+            }
+
+            ui.same_line();
+            widgets::save_album_button(ui, &mut app_state.spotify_handler, album.id());
+
+            ui.separator();
+
+            // Disc numbers are only worth a column when there's more than one disc.
+            let multiple_discs = album.tracklist().iter().any(|t| *t.disc_number() > 1);
+
+            let token = ui.begin_table_header_with_flags(
+                "Album Tracks",
+                [
+                    TableColumnSetup::new("#"),
+                    TableColumnSetup::new("Title"),
+                    TableColumnSetup::new("Artist"),
+                    TableColumnSetup::new("Duration"),
+                    TableColumnSetup::new("Actions")
+                ],
+                TableFlags::BORDERS | TableFlags::RESIZABLE
+            );
+
+            if let Some(_t) = token {
+                for track in album.tracklist().iter() {
+                    let seconds = track.duration() / 1000;
+                    let minutes = seconds / 60;
+                    let seconds = seconds % 60;
+
+                    ui.table_next_column();
+
+                    if multiple_discs {
+                        ui.text(format!("{}-{}", track.disc_number(), track.track_number()));
+                    }
+                    else {
+                        ui.text(format!("{}", track.track_number()));
+                    }
+
+                    ui.table_next_column();
+                    ui.text(track.name());
+
+                    ui.table_next_column();
+                    ui.text(track.artists().join(", "));
+
+                    ui.table_next_column();
+                    ui.text(format!("{}:{:02}", minutes, seconds));
+
+                    ui.table_next_column();
+
+                    if ui.button(format!("Play##{}", track.id())) {
+                        play = Some((false, Some(track.id().clone())));
+                    }
+
+                    ui.same_line();
+                    widgets::like_button(ui, &mut app_state.spotify_handler, track.id());
+
+                    ui.same_line();
+                    widgets::add_to_playlist_button(ui, &mut app_state.spotify_handler, track.id());
+
+                    ui.same_line();
+                    widgets::seed_button(ui, &mut app_state.recommendation_seeds, RecommendationSeed::Track { id: track.id().clone(), name: track.name().clone() });
+                }
+            }
+
+            if let Some((shuffle, start)) = play {
+                if let Some(handler) = app_state.spotify_handler.as_mut() {
+                    handler.play_album(album, shuffle, start.as_deref());
+                    app_state.show_player_window = true;
+                }
+            }
+        });
+
+        app_state.show_album_window = show_window;
+    }
+}
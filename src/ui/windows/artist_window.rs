@@ -1,66 +1,219 @@
 use crate::ui::AppState;
-use crate::spotify::api::cache::TrackInfo;
+use crate::ui::widgets;
+use crate::spotify::SpotifyHandler;
+use crate::spotify::api::cache::{ArtistInfo, TrackInfo};
+use crate::spotify::api::executor::RequestHandle;
+use crate::spotify::api::provider::{AlbumGroup, AlbumSummary, RecommendationSeed};
 
 use imgui::*;
-use librespot::core::spotify_id::SpotifyId;
 
 pub struct ArtistWindow {
+    artist_id: String,
     artist_name: String,
-    artist_tracks: Vec<TrackInfo>
+
+    artist: Option<ArtistInfo>,
+    artist_request: Option<RequestHandle<ArtistInfo>>,
+
+    top_tracks: Vec<TrackInfo>,
+    top_tracks_request: Option<RequestHandle<Vec<TrackInfo>>>,
+
+    discography: Vec<AlbumSummary>,
+    discography_request: Option<RequestHandle<Vec<AlbumSummary>>>,
+
+    related_artists: Vec<ArtistInfo>,
+    related_request: Option<RequestHandle<Vec<ArtistInfo>>>
 }
 
 impl ArtistWindow {
-    pub fn init(artist_name: String, artist_tracks: Vec<TrackInfo>) -> ArtistWindow {
+    pub fn init(artist_id: String, artist_name: String, handler: &SpotifyHandler) -> ArtistWindow {
         ArtistWindow {
-            artist_name,
-            artist_tracks
+            artist: None,
+            artist_request: Some(handler.get_artist_info(artist_id.clone())),
+
+            top_tracks: Vec::new(),
+            top_tracks_request: Some(handler.get_artist_top_tracks(artist_id.clone())),
+
+            discography: Vec::new(),
+            discography_request: Some(handler.get_artist_discography(artist_id.clone())),
+
+            related_artists: Vec::new(),
+            related_request: Some(handler.get_related_artists(artist_id.clone())),
+
+            artist_id,
+            artist_name
+        }
+    }
+
+    fn update_requests(&mut self) {
+        if let Some(artist) = self.artist_request.as_ref().and_then(|r| r.result()) {
+            self.artist = Some(artist);
+            self.artist_request = None;
+        }
+
+        if let Some(tracks) = self.top_tracks_request.as_ref().and_then(|r| r.result()) {
+            self.top_tracks = tracks;
+            self.top_tracks_request = None;
+        }
+
+        if let Some(albums) = self.discography_request.as_ref().and_then(|r| r.result()) {
+            self.discography = albums;
+            self.discography_request = None;
+        }
+
+        if let Some(artists) = self.related_request.as_ref().and_then(|r| r.result()) {
+            self.related_artists = artists;
+            self.related_request = None;
         }
     }
 
     pub fn draw(&mut self, ui: &Ui, app_state: &mut AppState) {
         let mut show_window = app_state.show_artist_window;
 
-        Window::new(&self.artist_name).size([420.0, 300.0], Condition::FirstUseEver).opened(&mut show_window).build(ui, || {
-            ui.bullet_text("Tracks");
-
-            let token = ui.begin_table_header_with_flags(
-                "Artist Tracks",
-                [
-                    TableColumnSetup::new("Title"),
-                    TableColumnSetup::new("Artist"),
-                    TableColumnSetup::new("Duration"),
-                    TableColumnSetup::new("Actions")
-                ],
-                TableFlags::BORDERS | TableFlags::RESIZABLE
-            );
-            
-            if let Some(_t) = token {
-                for entry in self.artist_tracks.iter() {
-                    let seconds = entry.duration() / 1000;
-                    let minutes = seconds / 60;
-                    let seconds = seconds % 60;
-                    
-                    ui.table_next_column();
-                    ui.text(entry.name());
-
-                    ui.table_next_column();
-                    ui.text(&entry.artists()[0]);
-
-                    ui.table_next_column();
-                    ui.text(format!("{}:{:02}", minutes, seconds));
-
-                    ui.table_next_column();
-
-                    if ui.button(format!("Play##{}", entry.id())) {
-                        if let Some(handler) = app_state.spotify_handler.as_mut() {
-                            app_state.show_player_window = true;
-                            handler.play_single_track(SpotifyId::from_base62(entry.id()).unwrap());
+        self.update_requests();
+
+        Window::new(format!("{}###Artist", self.artist_name)).size([600.0, 500.0], Condition::FirstUseEver).opened(&mut show_window).build(ui, || {
+            let mut play_track = None;
+            let mut open_album = None;
+            let mut open_artist = None;
+
+            if let Some(artist) = self.artist.as_ref() {
+                ui.text(format!("{} followers", artist.followers()));
+
+                if !artist.genres().is_empty() {
+                    ui.text_disabled(artist.genres().join(", "));
+                }
+            }
+            else {
+                widgets::request_status(ui, &self.artist_request, "artist");
+            }
+
+            widgets::follow_button(ui, &mut app_state.spotify_handler, &self.artist_id);
+
+            ui.same_line();
+            widgets::seed_button(ui, &mut app_state.recommendation_seeds, RecommendationSeed::Artist { id: self.artist_id.clone(), name: self.artist_name.clone() });
+
+            if !self.top_tracks.is_empty() {
+                ui.same_line();
+
+                if ui.button("Play top tracks") {
+                    play_track = Some(None);
+                }
+            }
+
+            ui.separator();
+
+            if CollapsingHeader::new("Top Tracks").default_open(true).build(ui) {
+                widgets::request_status(ui, &self.top_tracks_request, "top tracks");
+
+                let token = ui.begin_table_header_with_flags(
+                    "Artist Top Tracks",
+                    [
+                        TableColumnSetup::new("Title"),
+                        TableColumnSetup::new("Album"),
+                        TableColumnSetup::new("Duration"),
+                        TableColumnSetup::new("Actions")
+                    ],
+                    TableFlags::BORDERS | TableFlags::RESIZABLE
+                );
+
+                if let Some(_t) = token {
+                    for entry in self.top_tracks.iter() {
+                        let seconds = entry.duration() / 1000;
+                        let minutes = seconds / 60;
+                        let seconds = seconds % 60;
+
+                        let id_token = ui.push_id(entry.id().as_str());
+
+                        ui.table_next_column();
+                        ui.text(entry.name());
+
+                        ui.table_next_column();
+
+                        if widgets::album_link(ui, entry.album_name(), entry.album()) {
+                            open_album = Some(entry.album().clone());
+                        }
+
+                        ui.table_next_column();
+                        ui.text(format!("{}:{:02}", minutes, seconds));
+
+                        ui.table_next_column();
+
+                        if ui.button("Play") {
+                            play_track = Some(Some(entry.id().clone()));
+                        }
+
+                        ui.same_line();
+                        widgets::like_button(ui, &mut app_state.spotify_handler, entry.id());
+
+                        ui.same_line();
+                        widgets::add_to_playlist_button(ui, &mut app_state.spotify_handler, entry.id());
+
+                        ui.same_line();
+                        widgets::seed_button(ui, &mut app_state.recommendation_seeds, RecommendationSeed::Track { id: entry.id().clone(), name: entry.name().clone() });
+
+                        id_token.pop();
+                    }
+                }
+            }
+
+            if CollapsingHeader::new("Discography").default_open(true).build(ui) {
+                widgets::request_status(ui, &self.discography_request, "discography");
+
+                for group in AlbumGroup::ALL.iter() {
+                    let albums: Vec<&AlbumSummary> = self.discography.iter().filter(|a| a.group == *group).collect();
+
+                    if albums.is_empty() {
+                        continue;
+                    }
+
+                    TreeNode::new(format!("{} ({})", group.label(), albums.len())).build(ui, || {
+                        for album in albums.iter() {
+                            if widgets::album_link(ui, &album.name, &album.id) {
+                                open_album = Some(album.id.clone());
+                            }
+
+                            ui.same_line_with_pos(350.0);
+                            ui.text_disabled(&album.release_date);
+
+                            // Other artists' albums say whose they are.
+                            if *group == AlbumGroup::AppearsOn {
+                                ui.same_line();
+                                ui.text_disabled(album.artists.join(", "));
+                            }
                         }
+                    });
+                }
+            }
+
+            if CollapsingHeader::new("Related Artists").build(ui) {
+                widgets::request_status(ui, &self.related_request, "related artists");
+
+                for artist in self.related_artists.iter() {
+                    if Selectable::new(format!("{}##{}", artist.name(), artist.id())).build(ui) {
+                        open_artist = Some((artist.id().clone(), artist.name().clone()));
                     }
+
+                    ui.same_line_with_pos(250.0);
+                    widgets::follow_button(ui, &mut app_state.spotify_handler, artist.id());
                 }
             }
+
+            if let Some(start) = play_track {
+                if let Some(handler) = app_state.spotify_handler.as_mut() {
+                    app_state.show_player_window = true;
+                    handler.play_artist_top_tracks(&self.artist_id, &self.top_tracks, start.as_deref());
+                }
+            }
+
+            if let Some(album_id) = open_album {
+                app_state.open_album(album_id);
+            }
+
+            if let Some((artist_id, artist_name)) = open_artist {
+                app_state.open_artist(artist_id, artist_name);
+            }
         });
-    
+
         app_state.show_artist_window = show_window;
     }
 }
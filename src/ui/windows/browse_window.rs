@@ -0,0 +1,231 @@
+use std::sync::Arc;
+
+use imgui::*;
+
+use rspotify::model::category::Category;
+use rspotify::model::playlist::SimplifiedPlaylist;
+
+use crate::ui::AppState;
+use crate::ui::widgets;
+use crate::spotify::{PlaylistData, SpotifyHandler};
+use crate::spotify::api::executor::RequestHandle;
+use crate::spotify::api::provider::{AlbumSummary, FeaturedPlaylists};
+
+pub struct BrowseWindow {
+    new_releases: Vec<AlbumSummary>,
+    new_releases_request: Option<RequestHandle<Vec<AlbumSummary>>>,
+
+    featured: Option<FeaturedPlaylists>,
+    featured_request: Option<RequestHandle<FeaturedPlaylists>>,
+
+    categories: Vec<Category>,
+    categories_request: Option<RequestHandle<Vec<Category>>>,
+
+    // The category whose playlists are shown, by id and name.
+    category: Option<(String, String)>,
+    category_playlists: Vec<SimplifiedPlaylist>,
+    category_request: Option<RequestHandle<Vec<SimplifiedPlaylist>>>,
+
+    playlist_request: Option<RequestHandle<Arc<PlaylistData>>>,
+    loaded: bool
+}
+
+impl BrowseWindow {
+    pub fn init() -> BrowseWindow {
+        BrowseWindow {
+            new_releases: Vec::new(),
+            new_releases_request: None,
+
+            featured: None,
+            featured_request: None,
+
+            categories: Vec::new(),
+            categories_request: None,
+
+            category: None,
+            category_playlists: Vec::new(),
+            category_request: None,
+
+            playlist_request: None,
+            loaded: false
+        }
+    }
+
+    /// Asks for every page again. Anything fetched recently still comes from the cache.
+    fn load(&mut self, handler: &SpotifyHandler) {
+        self.new_releases_request = Some(handler.get_new_releases());
+        self.featured_request = Some(handler.get_featured_playlists());
+        self.categories_request = Some(handler.get_categories());
+
+        if let Some((category_id, _)) = self.category.as_ref() {
+            self.category_request = Some(handler.get_category_playlists(category_id.clone()));
+        }
+
+        self.loaded = true;
+    }
+
+    fn update_requests(&mut self, app_state: &mut AppState) {
+        if let Some(albums) = self.new_releases_request.as_ref().and_then(|r| r.result()) {
+            self.new_releases = albums;
+            self.new_releases_request = None;
+        }
+
+        if let Some(featured) = self.featured_request.as_ref().and_then(|r| r.result()) {
+            self.featured = Some(featured);
+            self.featured_request = None;
+        }
+
+        if let Some(categories) = self.categories_request.as_ref().and_then(|r| r.result()) {
+            self.categories = categories;
+            self.categories_request = None;
+        }
+
+        if let Some(playlists) = self.category_request.as_ref().and_then(|r| r.result()) {
+            self.category_playlists = playlists;
+            self.category_request = None;
+        }
+
+        if let Some(playlist) = self.playlist_request.as_ref().and_then(|r| r.result()) {
+            if let Some(handler) = app_state.spotify_handler.as_ref() {
+                let api_handler = handler.get_api_handler();
+                let playlist_fetch = playlist.clone();
+
+                std::thread::spawn(move || {
+                    playlist_fetch.fetch_data(api_handler);
+                });
+            }
+
+            app_state.playlist_data = Some(playlist);
+            app_state.show_playlist_window = true;
+            self.playlist_request = None;
+        }
+    }
+
+    pub fn draw(&mut self, ui: &Ui, app_state: &mut AppState) {
+        let mut show_window = app_state.show_browse_window;
+
+        if !self.loaded {
+            if let Some(handler) = app_state.spotify_handler.as_ref() {
+                self.load(handler);
+            }
+        }
+
+        self.update_requests(app_state);
+
+        Window::new("Browse").size([700.0, 500.0], Condition::FirstUseEver).opened(&mut show_window).build(ui, || {
+            if ui.button("Refresh") {
+                if let Some(handler) = app_state.spotify_handler.as_ref() {
+                    self.load(handler);
+                }
+            }
+
+            if let Some(request) = self.playlist_request.as_ref() {
+                ui.same_line();
+
+                if let Some(error) = request.error() {
+                    ui.text_colored([1.0, 0.0, 0.0, 1.0], format!("Couldn't open playlist: {}", error));
+                }
+                else {
+                    ui.text("Opening playlist...");
+                }
+            }
+
+            let mut open_album = None;
+            let mut open_playlist = None;
+            let mut open_category = None;
+
+            TabBar::new("Browse Tabs").build(ui, || {
+                TabItem::new("New Releases").build(ui, || {
+                    widgets::request_status(ui, &self.new_releases_request, "new releases");
+
+                    for album in self.new_releases.iter() {
+                        if widgets::album_link(ui, &album.name, &album.id) {
+                            open_album = Some(album.id.clone());
+                        }
+
+                        ui.same_line_with_pos(300.0);
+                        ui.text_disabled(album.artists.join(", "));
+
+                        ui.same_line_with_pos(550.0);
+                        ui.text_disabled(&album.release_date);
+                    }
+                });
+
+                TabItem::new("Featured").build(ui, || {
+                    widgets::request_status(ui, &self.featured_request, "featured playlists");
+
+                    if let Some(featured) = self.featured.as_ref() {
+                        ui.text(&featured.message);
+                        ui.separator();
+
+                        playlist_list(ui, &featured.playlists, app_state, &mut open_playlist);
+                    }
+                });
+
+                TabItem::new("Categories").build(ui, || {
+                    if let Some((_, category_name)) = self.category.as_ref() {
+                        if ui.button("Back") {
+                            open_category = Some(None);
+                        }
+
+                        ui.same_line();
+                        ui.text(category_name);
+                        ui.separator();
+
+                        widgets::request_status(ui, &self.category_request, "playlists");
+                        playlist_list(ui, &self.category_playlists, app_state, &mut open_playlist);
+                    }
+                    else {
+                        widgets::request_status(ui, &self.categories_request, "categories");
+
+                        for category in self.categories.iter() {
+                            if Selectable::new(format!("{}##{}", category.name, category.id)).build(ui) {
+                                open_category = Some(Some((category.id.clone(), category.name.clone())));
+                            }
+                        }
+                    }
+                });
+            });
+
+            if let Some(album_id) = open_album {
+                app_state.open_album(album_id);
+            }
+
+            if let Some(handler) = app_state.spotify_handler.as_ref() {
+                if let Some(playlist) = open_playlist {
+                    self.playlist_request = Some(handler.get_other_playlist(&playlist));
+                }
+
+                if let Some(category) = open_category {
+                    self.category_playlists.clear();
+                    self.category_request = category.as_ref().map(|(id, _)| handler.get_category_playlists(id.clone()));
+                    self.category = category;
+                }
+            }
+        });
+
+        app_state.show_browse_window = show_window;
+    }
+}
+
+/// Playlists that can be opened in a playlist window, or played right away.
+fn playlist_list(ui: &Ui, playlists: &[SimplifiedPlaylist], app_state: &mut AppState, open_playlist: &mut Option<SimplifiedPlaylist>) {
+    for playlist in playlists.iter() {
+        let id_token = ui.push_id(playlist.id.as_str());
+
+        if Selectable::new(&playlist.name).build(ui) {
+            *open_playlist = Some(playlist.clone());
+        }
+
+        ui.same_line_with_pos(400.0);
+
+        if ui.button("Play") {
+            if let Some(handler) = app_state.spotify_handler.as_mut() {
+                handler.play_playlist(playlist.id.clone());
+                app_state.show_player_window = true;
+            }
+        }
+
+        id_token.pop();
+    }
+}
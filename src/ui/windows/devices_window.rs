@@ -0,0 +1,112 @@
+use imgui::*;
+
+use crate::ui::AppState;
+use crate::ui::widgets;
+use crate::spotify::api::executor::RequestHandle;
+use crate::spotify::api::provider::ConnectDevice;
+
+pub struct DevicesWindow {
+    devices: Vec<ConnectDevice>,
+    request: Option<RequestHandle<Vec<ConnectDevice>>>,
+    loaded: bool
+}
+
+impl DevicesWindow {
+    pub fn init() -> DevicesWindow {
+        DevicesWindow {
+            devices: Vec::new(),
+            request: None,
+            loaded: false
+        }
+    }
+
+    pub fn draw(&mut self, ui: &Ui, app_state: &mut AppState) {
+        let mut show_window = app_state.show_devices_window;
+
+        if !self.loaded {
+            if let Some(handler) = app_state.spotify_handler.as_ref() {
+                self.request = Some(handler.get_devices());
+                self.loaded = true;
+            }
+        }
+
+        if let Some(devices) = self.request.as_ref().and_then(|r| r.result()) {
+            self.devices = devices;
+            self.request = None;
+        }
+
+        Window::new("Devices").size([500.0, 300.0], Condition::FirstUseEver).opened(&mut show_window).build(ui, || {
+            if ui.button("Refresh") {
+                if let Some(handler) = app_state.spotify_handler.as_ref() {
+                    self.request = Some(handler.get_devices());
+                }
+            }
+
+            widgets::request_status(ui, &self.request, "devices");
+
+            let remote_device = app_state.spotify_handler.as_ref().and_then(|h| h.remote_device());
+
+            if let Some(error) = app_state.spotify_handler.as_ref().and_then(|h| h.transfer_error()) {
+                ui.text_colored([1.0, 0.0, 0.0, 1.0], format!("Couldn't move playback: {}", error));
+            }
+
+            if self.devices.is_empty() && self.request.is_none() {
+                ui.text_disabled("No devices found. Open Spotify on another device and refresh.");
+            }
+
+            let mut transfer = None;
+
+            let token = ui.begin_table_header_with_flags(
+                "Connect Devices",
+                [
+                    TableColumnSetup::new("Name"),
+                    TableColumnSetup::new("Type"),
+                    TableColumnSetup::new("Volume"),
+                    TableColumnSetup::new("Actions")
+                ],
+                TableFlags::BORDERS | TableFlags::RESIZABLE
+            );
+
+            if let Some(_t) = token {
+                for device in self.devices.iter() {
+                    let id_token = ui.push_id(device.id.as_str());
+
+                    ui.table_next_column();
+
+                    if device.is_active {
+                        ui.text_colored([0.2, 1.0, 0.0, 1.0], &device.name);
+                    }
+                    else {
+                        ui.text(&device.name);
+                    }
+
+                    ui.table_next_column();
+                    ui.text(&device.kind);
+
+                    ui.table_next_column();
+                    ui.text(format!("{}%", device.volume));
+
+                    ui.table_next_column();
+
+                    if remote_device.as_deref() == Some(device.id.as_str()) {
+                        ui.text_disabled("Playing here");
+                    }
+                    else if ui.button("Play here") {
+                        transfer = Some(device.id.clone());
+                    }
+
+                    id_token.pop();
+                }
+            }
+
+            if let Some(device_id) = transfer {
+                if let Some(handler) = app_state.spotify_handler.as_mut() {
+                    handler.transfer_playback(device_id);
+                    app_state.show_player_window = true;
+                }
+            }
+        });
+
+        app_state.show_devices_window = show_window;
+    }
+}
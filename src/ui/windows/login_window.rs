@@ -1,7 +1,14 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
 use imgui::*;
 
 use crate::ui::AppState;
 use crate::spotify::SpotifyHandler;
+use crate::spotify::api::auth::{self, APIToken, AuthFlow};
+use crate::spotify::api::provider::MetadataProvider;
+use crate::spotify::api::web_provider::WebAPIProvider;
+use crate::spotify::api::fixture_provider::FixtureProvider;
 
 pub struct LoginWindow {
     username: String,
@@ -10,8 +17,20 @@ pub struct LoginWindow {
     login_failed: bool,
     save_username: bool,
     keyring_login: bool,
+    pending_login: bool,
+
+    saved_usernames: Vec<String>,
+
+    api_token: Option<APIToken>,
+    auth_flow: Option<AuthFlow>,
+    auth_error: Option<String>,
 
-    saved_usernames: Vec<String>
+    // Without a client id, imguify runs on librespot alone and the Web API features are disabled.
+    web_api_enabled: bool,
+    // Demo mode, metadata comes from the fixtures in this directory instead of the Web API.
+    fixtures_path: Option<PathBuf>,
+    // Sends Web API requests here instead of to Spotify, e.g. a local stand-in for the player endpoints.
+    api_prefix: Option<String>
 }
 
 impl LoginWindow {
@@ -39,15 +58,77 @@ impl LoginWindow {
             login_failed: false,
             save_username: false,
             keyring_login: false,
+            pending_login: false,
+
+            saved_usernames,
+
+            api_token: APIToken::load(),
+            auth_flow: None,
+            auth_error: None,
+
+            web_api_enabled: auth::is_configured(),
+            fixtures_path: std::env::var_os("IMGUIFY_FIXTURES").map(PathBuf::from),
+            api_prefix: std::env::var("IMGUIFY_API_PREFIX").ok()
+        }
+    }
+
+    fn needs_authorization(&self) -> bool {
+        self.fixtures_path.is_none() && self.web_api_enabled && self.api_token.is_none()
+    }
+
+    /// The metadata provider librespot falls back to, and the Web API for making changes if it's authorized.
+    fn build_providers(&self) -> anyhow::Result<(Option<Arc<dyn MetadataProvider>>, Option<Arc<WebAPIProvider>>)> {
+        if let Some(path) = self.fixtures_path.as_ref() {
+            let fixtures: Arc<dyn MetadataProvider> = Arc::new(FixtureProvider::init(path.clone())?);
+            Ok((Some(fixtures), None))
+        }
+        else if !self.web_api_enabled {
+            Ok((None, None))
+        }
+        else if let Some(token) = self.api_token.clone() {
+            let web_api = Arc::new(WebAPIProvider::init(token, self.api_prefix.clone()));
+            let metadata: Arc<dyn MetadataProvider> = web_api.clone();
+
+            Ok((Some(metadata), Some(web_api)))
+        }
+        else {
+            Err(anyhow::Error::msg("The Web API wasn't authorized"))
+        }
+    }
+
+    fn start_auth_flow(&mut self) {
+        match AuthFlow::start() {
+            Ok(flow) => {
+                self.auth_flow = Some(flow);
+                self.auth_error = None;
+            }
+            Err(error) => {
+                self.pending_login = false;
+                self.auth_error = Some(error.to_string());
+            }
+        }
+    }
+
+    fn poll_auth_flow(&mut self) {
+        if let Some(result) = self.auth_flow.as_ref().and_then(|flow| flow.poll()) {
+            self.auth_flow = None;
 
-            saved_usernames
+            match result {
+                Ok(token) => self.api_token = Some(token),
+                Err(error) => {
+                    self.pending_login = false;
+                    self.auth_error = Some(error.to_string());
+                }
+            }
         }
     }
 
     pub fn draw(&mut self, ui: &Ui, app_state: &mut AppState) -> String {
         let mut username_to_use = String::new();
 
-        Window::new("Login to Spotify").size([600.0, 130.0], Condition::Always).build(ui, || {
+        self.poll_auth_flow();
+
+        Window::new("Login to Spotify").size([600.0, 160.0], Condition::Always).build(ui, || {
             ui.columns(2, "login_cols", true);
     
             ui.bullet_text("Login");
@@ -67,6 +148,26 @@ impl LoginWindow {
     
             ui.same_line();
             ui.checkbox("Remember me", &mut self.save_username);
+
+            if self.fixtures_path.is_some() {
+                ui.text_colored([1.0, 0.5, 0.0, 1.0], "Demo mode, using metadata fixtures");
+            }
+            else if let Some(flow) = self.auth_flow.as_ref() {
+                ui.text_colored([1.0, 0.5, 0.0, 1.0], "Waiting for authorization in your browser...");
+
+                if ui.small_button("Copy link") {
+                    ui.set_clipboard_text(flow.auth_url());
+                }
+            }
+            else if let Some(error) = self.auth_error.as_ref() {
+                ui.text_colored([1.0, 0.0, 0.0, 1.0], format!("Authorization failed: {}", error));
+            }
+            else if !self.web_api_enabled {
+                ui.text_colored([1.0, 0.5, 0.0, 1.0], "No CLIENT_ID set, search and library features are disabled");
+            }
+            else if self.api_token.is_some() {
+                ui.text_colored([0.0, 1.0, 0.0, 1.0], "Web API authorized");
+            }
     
             ui.next_column();
     
@@ -89,17 +190,39 @@ impl LoginWindow {
             });
     
             if submitted {
-                let username = self.username.clone();
-                let password = self.password.clone();
-    
-                if username.is_empty() || password.is_empty() {
+                if self.username.is_empty() || self.password.is_empty() {
                     self.login_failed = true;
                     return;
                 }
+
+                self.pending_login = true;
+
+                if self.needs_authorization() && self.auth_flow.is_none() {
+                    self.start_auth_flow();
+                }
+            }
+
+            // The login goes through once both the credentials and the API token are there.
+            if self.pending_login && !self.needs_authorization() {
+                let (provider, web_api) = match self.build_providers() {
+                    Ok(providers) => providers,
+                    Err(error) => {
+                        println!("Error setting up metadata provider: {}", error.to_string());
+
+                        self.pending_login = false;
+                        self.login_failed = true;
+                        return;
+                    }
+                };
+
+                let username = self.username.clone();
+                let password = self.password.clone();
+
+                self.pending_login = false;
     
                 let (cmd_tx, cmd_rx) = std::sync::mpsc::channel();
     
-                if let Ok(handler) = SpotifyHandler::init(username.clone(), password.clone(), cmd_tx.clone(), cmd_rx) {
+                if let Ok(handler) = SpotifyHandler::init(username.clone(), password.clone(), provider, web_api, cmd_tx.clone(), cmd_rx) {
                     username_to_use = username.clone();
                     app_state.player_tx = Some(cmd_tx);
                     app_state.spotify_handler = Some(handler);
@@ -1,53 +1,240 @@
 use imgui::*;
 
+use librespot::core::spotify_id::SpotifyId;
+
 use crate::ui::AppState;
+use crate::ui::widgets;
+use crate::spotify::{PlaylistData, PlaylistKind};
 use crate::spotify::player::PlayerCommand;
+use crate::spotify::api::executor::RequestHandle;
+use crate::spotify::api::provider::PlaylistChanges;
 
 pub struct MainWindow {
     username: String,
-    playlists: Vec<String>
+    playlists: Vec<(PlaylistKind, String)>,
+    playlist_form: Option<PlaylistForm>,
+    // Liked Songs were asked to play, which happens once the whole list is loaded.
+    play_liked_songs: bool
+}
+
+/// The playlist being created or edited.
+struct PlaylistForm {
+    playlist_id: Option<SpotifyId>,
+    name: String,
+    description: String,
+    public: bool,
+    collaborative: bool,
+
+    original: PlaylistChanges,
+    description_request: Option<RequestHandle<String>>,
+    confirm_delete: bool
+}
+
+impl PlaylistForm {
+    fn new() -> PlaylistForm {
+        PlaylistForm {
+            playlist_id: None,
+            name: String::from("New Playlist"),
+            description: String::new(),
+            public: false,
+            collaborative: false,
+
+            original: PlaylistChanges::default(),
+            description_request: None,
+            confirm_delete: false
+        }
+    }
+
+    fn edit(playlist: &PlaylistData, description_request: Option<RequestHandle<String>>) -> PlaylistForm {
+        let details = playlist.details();
+
+        PlaylistForm {
+            playlist_id: playlist.id().cloned(),
+            name: playlist.title().clone(),
+            description: details.description.clone(),
+            public: details.public,
+            collaborative: details.collaborative,
+
+            original: PlaylistChanges {
+                name: Some(playlist.title().clone()),
+                description: Some(details.description.clone()),
+                public: Some(details.public),
+                collaborative: Some(details.collaborative)
+            },
+            description_request,
+            confirm_delete: false
+        }
+    }
+
+    /// Takes the description once it's loaded. If it can't be, the last known one stays.
+    fn poll_description(&mut self) {
+        if let Some(request) = self.description_request.as_ref() {
+            if let Some(description) = request.result() {
+                self.description = description.clone();
+                self.original.description = Some(description);
+                self.description_request = None;
+            }
+            else if let Some(error) = request.error() {
+                println!("Error loading playlist description: {}", error);
+                self.description_request = None;
+            }
+        }
+    }
+
+    /// Only what was actually changed gets sent, the rest might not be known.
+    fn changes(&self) -> PlaylistChanges {
+        PlaylistChanges {
+            name: changed(self.name.clone(), &self.original.name),
+            description: changed(self.description.clone(), &self.original.description),
+            public: changed(self.public, &self.original.public),
+            collaborative: changed(self.collaborative, &self.original.collaborative)
+        }
+    }
+}
+
+fn changed<T: PartialEq>(new: T, old: &Option<T>) -> Option<T> {
+    if old.as_ref() != Some(&new) {
+        Some(new)
+    }
+    else {
+        None
+    }
 }
 
 impl MainWindow {
-    pub fn init(username: String, playlists: Vec<String>) -> MainWindow {
+    pub fn init(username: String) -> MainWindow {
         MainWindow {
             username,
-            playlists
+            playlists: Vec::new(),
+            playlist_form: None,
+            play_liked_songs: false
         }
     }
 
     pub fn draw(&mut self, ui: &Ui, app_state: &mut AppState) {
         let mut show_separator = true;
+        let mut loading_playlists = false;
+        let mut playlists_error = None;
+        let mut throttled = false;
+        let mut liked_songs = None;
+        let mut loading_liked_songs = false;
+        let mut liked_songs_error = None;
+        let mut saved_albums = Vec::new();
+        let mut followed_artists = Vec::new();
+        let mut loading_library = false;
+        let mut library_error = None;
+        let mut request_error = None;
+        let mut open_form = None;
+        let mut play_liked_songs = self.play_liked_songs;
+
+        if let Some(handler) = app_state.spotify_handler.as_ref() {
+            request_error = handler.request_error();
+            liked_songs = handler.get_liked_songs();
+            loading_liked_songs = handler.is_loading_liked_songs();
+            liked_songs_error = handler.liked_songs_error();
+            saved_albums = handler.get_saved_albums();
+            followed_artists = handler.get_followed_artists();
+            loading_library = handler.is_loading_library();
+            library_error = handler.library_error();
+            self.playlists = handler.get_playlists_names();
+            loading_playlists = handler.is_loading_playlists();
+            playlists_error = handler.playlists_error();
+            throttled = handler.get_api_handler().is_throttled();
+        }
 
         Window::new("Main Window").size([800.0, 500.0], Condition::FirstUseEver).build(ui, || {
             ui.text_colored([0.0, 1.0, 0.0, 1.0], format!("Connected to Spotify as {}", self.username));
+
+            if throttled {
+                ui.same_line();
+                ui.text_colored([1.0, 0.5, 0.0, 1.0], "(rate limited, requests are slowed down)");
+            }
+
+            ui.separator();
+
+            if let Some(liked_songs) = liked_songs.as_ref() {
+                ui.text(format!("Liked Songs - {} tracks", liked_songs.entries().len()));
+                ui.same_line_with_pos(200.0);
+
+                if ui.button("Play##liked_songs") {
+                    play_liked_songs = true;
+                }
+
+                ui.same_line_with_pos(250.0);
+
+                if ui.button("View##liked_songs") {
+                    if let Some(handler) = app_state.spotify_handler.as_ref() {
+                        let playlist_fetch = liked_songs.clone();
+                        let api_handler = handler.get_api_handler();
+
+                        std::thread::spawn(move || {
+                            playlist_fetch.fetch_data(api_handler);
+                        });
+
+                        app_state.playlist_data = Some(liked_songs.clone());
+                        app_state.show_playlist_window = true;
+                    }
+                }
+            }
+            else if loading_liked_songs {
+                ui.text("Loading Liked Songs...");
+                ui.same_line_with_pos(200.0);
+
+                if play_liked_songs {
+                    ui.text_disabled("Plays once loaded");
+                }
+                else if ui.button("Play##liked_songs") {
+                    play_liked_songs = true;
+                }
+            }
+            else if let Some(error) = liked_songs_error.as_ref() {
+                ui.text_colored([1.0, 0.0, 0.0, 1.0], format!("Couldn't load Liked Songs: {}", error));
+            }
+
             ui.separator();
     
+            if let Some(error) = request_error.as_ref() {
+                ui.text_colored([1.0, 0.0, 0.0, 1.0], format!("Last change failed: {}", error));
+                ui.separator();
+            }
+
             TreeNode::new("User Playlists").build(ui, || {
                 if !self.playlists.is_empty() {
                     show_separator = false;
                 }
 
-                for (idx, plist) in self.playlists.iter().enumerate() {
+                if ui.button("New Playlist") {
+                    open_form = Some(PlaylistForm::new());
+                }
+
+                if loading_playlists {
+                    ui.text("Loading playlists...");
+                }
+                else if let Some(error) = playlists_error.as_ref() {
+                    ui.text_colored([1.0, 0.0, 0.0, 1.0], format!("Couldn't load playlists: {}", error));
+                }
+
+                for (kind, plist) in self.playlists.iter() {
                     ui.text(plist);
                     ui.same_line_with_pos(200.0);
     
                     if ui.button(format!("Play##{}", plist)) {
                         if let Some(tx) = app_state.player_tx.as_ref() {
                             if let Some(handler) = app_state.spotify_handler.as_mut() {
-                                // A bit of a gamble, but should be fine.
-                                let plist = handler.get_playlist(idx).unwrap();
-                                let api_handler = handler.get_api_handler();
-                            
-                                if let Err(error) = tx.send(PlayerCommand::StartPlaylist(plist.entries().clone())) {
-                                    println!("{}", error.to_string());
-                                }
-    
-                                std::thread::spawn(move || {
-                                    plist.fetch_data(api_handler);
-                                });
+                                // The playlist might have been deleted since the list was taken.
+                                if let Some(plist) = handler.find_playlist(kind) {
+                                    let api_handler = handler.get_api_handler();
+
+                                    if let Err(error) = tx.send(PlayerCommand::StartPlaylist(plist.entries().clone())) {
+                                        println!("{}", error.to_string());
+                                    }
+
+                                    std::thread::spawn(move || {
+                                        plist.fetch_data(api_handler);
+                                    });
 
-                                app_state.show_player_window = true;
+                                    app_state.show_player_window = true;
+                                }
                             }
                         }
                     }
@@ -56,7 +243,7 @@ impl MainWindow {
     
                     if ui.button(format!("View##{}", plist)) {
                         if let Some(handler) = app_state.spotify_handler.as_mut() {
-                            let playlist = handler.get_playlist(idx);
+                            let playlist = handler.find_playlist(kind);
                             let playlist_fetch = playlist.clone();
     
                             app_state.playlist_data = playlist;
@@ -72,11 +259,74 @@ impl MainWindow {
                             app_state.show_playlist_window = true;
                         }
                     }
+
+                    if let Some(playlist) = app_state.spotify_handler.as_mut().and_then(|h| h.find_playlist(kind)) {
+                        if playlist.is_editable() {
+                            ui.same_line();
+
+                            if ui.button(format!("Edit##{}", plist)) {
+                                let description_request = playlist.id().and_then(|id| app_state.spotify_handler.as_ref().map(|h| h.get_playlist_description(id)));
+                                open_form = Some(PlaylistForm::edit(&playlist, description_request));
+                            }
+                        }
+                    }
                     
                     ui.separator();
                 }
             });
     
+            TreeNode::new("Saved Albums").build(ui, || {
+                if loading_library && saved_albums.is_empty() {
+                    ui.text("Loading library...");
+                }
+                else if let Some(error) = library_error.as_ref() {
+                    ui.text_colored([1.0, 0.0, 0.0, 1.0], format!("Couldn't load library: {}", error));
+                }
+
+                for album in saved_albums.iter() {
+                    ui.text(format!("{} - {}", album.name(), album.artists().join(", ")));
+                    ui.same_line_with_pos(300.0);
+
+                    if ui.button(format!("Play##{}", album.id())) {
+                        if let Some(handler) = app_state.spotify_handler.as_mut() {
+                            handler.play_album(album, false, None);
+                            app_state.show_player_window = true;
+                        }
+                    }
+
+                    ui.same_line();
+
+                    if ui.button(format!("View##{}", album.id())) {
+                        app_state.open_album(album.id().clone());
+                    }
+
+                    ui.same_line();
+                    widgets::save_album_button(ui, &mut app_state.spotify_handler, album.id());
+
+                    ui.separator();
+                }
+            });
+
+            TreeNode::new("Followed Artists").build(ui, || {
+                if loading_library && followed_artists.is_empty() {
+                    ui.text("Loading library...");
+                }
+
+                for artist in followed_artists.iter() {
+                    ui.text(format!("{} - {} followers", artist.name(), artist.followers()));
+                    ui.same_line_with_pos(300.0);
+
+                    if ui.button(format!("View##{}", artist.id())) {
+                        app_state.open_artist(artist.id().clone(), artist.name().clone());
+                    }
+
+                    ui.same_line();
+                    widgets::follow_button(ui, &mut app_state.spotify_handler, artist.id());
+
+                    ui.separator();
+                }
+            });
+
             if show_separator {
                 ui.separator();
             }
@@ -84,6 +334,129 @@ impl MainWindow {
             if ui.button("Search in Spotify") {
                 app_state.show_search_window = true;
             }
+
+            ui.same_line();
+
+            if ui.button("Browse") {
+                app_state.show_browse_window = true;
+            }
+
+            ui.same_line();
+
+            if ui.button("Recommendations") {
+                app_state.show_recommendations_window = true;
+            }
+
+            ui.same_line();
+
+            if ui.button("Your Stats") {
+                app_state.show_stats_window = true;
+            }
+
+            ui.same_line();
+
+            if ui.button("Devices") {
+                app_state.show_devices_window = true;
+            }
         });
+
+        if open_form.is_some() {
+            self.playlist_form = open_form;
+        }
+
+        // Playing what's loaded so far would leave out the rest of the list, so it waits for the whole one.
+        if play_liked_songs && !loading_liked_songs {
+            play_liked_songs = false;
+
+            if let (Some(liked_songs), Some(tx), Some(handler)) = (liked_songs, app_state.player_tx.as_ref(), app_state.spotify_handler.as_ref()) {
+                let api_handler = handler.get_api_handler();
+
+                if let Err(error) = tx.send(PlayerCommand::StartPlaylist(liked_songs.entries())) {
+                    println!("{}", error.to_string());
+                }
+
+                std::thread::spawn(move || {
+                    liked_songs.fetch_data(api_handler);
+                });
+
+                app_state.show_player_window = true;
+            }
+        }
+
+        self.play_liked_songs = play_liked_songs;
+
+        self.draw_playlist_form(ui, app_state);
+    }
+
+    fn draw_playlist_form(&mut self, ui: &Ui, app_state: &mut AppState) {
+        let mut show_window = self.playlist_form.is_some();
+        let mut close_form = false;
+
+        if let Some(form) = self.playlist_form.as_mut() {
+            form.poll_description();
+
+            let title = if form.playlist_id.is_some() { "Edit Playlist" } else { "New Playlist" };
+
+            Window::new(format!("{}###Playlist Form", title)).size([400.0, 250.0], Condition::FirstUseEver).opened(&mut show_window).build(ui, || {
+                ui.input_text("Name", &mut form.name).build();
+                if form.description_request.is_some() {
+                    ui.text_disabled("Loading description...");
+                }
+                else {
+                    ui.input_text_multiline("Description", &mut form.description, [0.0, 60.0]).build();
+                }
+
+                ui.checkbox("Public", &mut form.public);
+
+                // Spotify only allows private playlists to be collaborative.
+                if ui.checkbox("Collaborative", &mut form.collaborative) && form.collaborative {
+                    form.public = false;
+                }
+
+                if form.public && form.collaborative {
+                    form.collaborative = false;
+                }
+
+                ui.separator();
+
+                if ui.button("Save") && !form.name.trim().is_empty() {
+                    if let Some(handler) = app_state.spotify_handler.as_mut() {
+                        match form.playlist_id.as_ref() {
+                            Some(playlist_id) => handler.change_playlist_details(playlist_id, form.changes()),
+                            None => handler.create_playlist(form.name.clone(), form.description.clone(), form.public, form.collaborative)
+                        }
+                    }
+
+                    close_form = true;
+                }
+
+                ui.same_line();
+
+                if ui.button("Cancel") {
+                    close_form = true;
+                }
+
+                if let Some(playlist_id) = form.playlist_id.as_ref() {
+                    ui.same_line();
+
+                    if !form.confirm_delete {
+                        if ui.button("Delete") {
+                            form.confirm_delete = true;
+                        }
+                    }
+                    else if ui.button("Really delete?") {
+                        if let Some(handler) = app_state.spotify_handler.as_mut() {
+                            handler.delete_playlist(playlist_id);
+                        }
+
+                        close_form = true;
+                    }
+                }
+            });
+        }
+
+        if !show_window || close_form {
+            self.playlist_form = None;
+        }
     }
 }
@@ -1,6 +1,11 @@
 pub mod main_window;
 pub mod login_window;
 pub mod artist_window;
+pub mod album_window;
+pub mod browse_window;
+pub mod devices_window;
 pub mod search_window;
+pub mod stats_window;
 pub mod player_window;
-pub mod playlist_window;
\ No newline at end of file
+pub mod playlist_window;
+pub mod recommendations_window;
\ No newline at end of file
@@ -1,54 +1,306 @@
+use std::time::{Duration, Instant};
+
 use imgui::*;
+use librespot::core::spotify_id::SpotifyId;
 
 use crate::ui::AppState;
-use crate::spotify::player::PlayerCommand;
+use crate::ui::widgets;
+use crate::spotify::SpotifyHandler;
+use crate::spotify::player::{PlayContext, PlayerCommand};
+use crate::spotify::api::cache::TrackInfo;
+use crate::spotify::api::executor::RequestHandle;
+use crate::spotify::api::provider::{RemoteCommand, RemotePlayback};
+
+const REMOTE_POLL_INTERVAL: Duration = Duration::from_secs(1);
 
 pub struct PlayerWindow {
+    current_id: Option<SpotifyId>,
     current_track: String,
     current_artist: String,
+    current_request: Option<RequestHandle<TrackInfo>>,
 
+    next_id: Option<SpotifyId>,
     next_track: String,
-    next_artist: String
+    next_artist: String,
+    next_request: Option<RequestHandle<TrackInfo>>,
+
+    context: PlayContext,
+    context_name: String,
+    context_request: Option<RequestHandle<String>>,
+
+    // Remote mode, while playback is on another Connect device.
+    remote_playback: Option<RemotePlayback>,
+    remote_request: Option<RequestHandle<Option<RemotePlayback>>>,
+    remote_polled_at: Option<Instant>,
+    // Positions of the sliders while they're being dragged, so polling doesn't yank them back.
+    seek_position: f32,
+    seeking: bool,
+    volume: f32,
+    changing_volume: bool
 }
 
 impl PlayerWindow {
     pub fn init() -> PlayerWindow {
         PlayerWindow {
+            current_id: None,
             current_track: String::from("No tracks loaded"),
             current_artist: String::from("No tracks loaded"),
+            current_request: None,
 
+            next_id: None,
             next_track: String::from("No tracks loaded"),
-            next_artist: String::from("No tracks loaded")
+            next_artist: String::from("No tracks loaded"),
+            next_request: None,
+
+            context: PlayContext::Tracks,
+            context_name: String::new(),
+            context_request: None,
+
+            remote_playback: None,
+            remote_request: None,
+            remote_polled_at: None,
+            seek_position: 0.0,
+            seeking: false,
+            volume: 0.0,
+            changing_volume: false
+        }
+    }
+
+    fn poll_remote(&mut self, handler: &SpotifyHandler) {
+        if let Some(playback) = self.remote_request.as_ref().and_then(|r| r.result()) {
+            self.remote_playback = playback;
+            self.remote_request = None;
+        }
+
+        let due = self.remote_polled_at.map_or(true, |polled_at| polled_at.elapsed() >= REMOTE_POLL_INTERVAL);
+
+        if due && self.remote_request.as_ref().map_or(true, |r| !r.is_loading()) {
+            self.remote_request = Some(handler.get_remote_playback());
+            self.remote_polled_at = Some(Instant::now());
+        }
+    }
+
+    fn draw_remote(&mut self, ui: &Ui, app_state: &mut AppState) {
+        let mut command = None;
+        let mut open_album = None;
+        let mut stop_remote = false;
+
+        match self.remote_playback.as_ref() {
+            Some(playback) => {
+                ui.text_colored([0.2, 1.0, 0.0, 1.0], format!("Playing on {}", playback.device.name));
+
+                if let Some(track) = playback.track.as_ref() {
+                    ui.text(track.name());
+                    ui.text(track.artists().join(", "));
+
+                    if widgets::album_link(ui, track.album_name(), track.album()) {
+                        open_album = Some(track.album().clone());
+                    }
+
+                    widgets::like_button(ui, &mut app_state.spotify_handler, track.id());
+
+                    // Interpolated between polls so the slider moves smoothly.
+                    let mut progress = playback.progress;
+
+                    if playback.is_playing {
+                        if let Some(polled_at) = self.remote_polled_at {
+                            progress += polled_at.elapsed().as_millis() as u32;
+                        }
+                    }
+
+                    let duration = *track.duration();
+
+                    if !self.seeking {
+                        self.seek_position = progress.min(duration) as f32;
+                    }
+
+                    Slider::new("##seek", 0.0, duration as f32).display_format("").build(ui, &mut self.seek_position);
+                    self.seeking = ui.is_item_active();
+
+                    if ui.is_item_deactivated_after_edit() {
+                        command = Some(RemoteCommand::Seek(self.seek_position as u32));
+                    }
+
+                    let seconds = self.seek_position as u32 / 1000;
+                    let total = duration / 1000;
+                    ui.same_line();
+                    ui.text(format!("{}:{:02} / {}:{:02}", seconds / 60, seconds % 60, total / 60, total % 60));
+                }
+                else {
+                    ui.text_disabled("Nothing playing");
+                }
+
+                ui.separator();
+
+                if ui.button("«") {
+                    command = Some(RemoteCommand::Previous);
+                }
+
+                ui.same_line();
+
+                if ui.button(if playback.is_playing { "Pause" } else { "Play" }) {
+                    command = Some(if playback.is_playing { RemoteCommand::Pause } else { RemoteCommand::Play });
+                }
+
+                ui.same_line();
+
+                if ui.button("»") {
+                    command = Some(RemoteCommand::Next);
+                }
+
+                ui.same_line();
+                ui.set_next_item_width(120.0);
+
+
+                if !self.changing_volume {
+                    self.volume = playback.device.volume as f32;
+                }
+
+                Slider::new("Volume", 0.0, 100.0).display_format("%.0f").build(ui, &mut self.volume);
+                self.changing_volume = ui.is_item_active();
+
+                if ui.is_item_deactivated_after_edit() {
+                    command = Some(RemoteCommand::Volume(self.volume as u8));
+                }
+            }
+            None => {
+                if let Some(error) = self.remote_request.as_ref().and_then(|r| r.error()) {
+                    ui.text_colored([1.0, 0.0, 0.0, 1.0], format!("Couldn't get playback state: {}", error));
+                }
+                else {
+                    ui.text("Waiting for the other device...");
+                }
+            }
+        }
+
+        ui.separator();
+
+        if ui.button("Back to this computer") {
+            stop_remote = true;
+        }
+
+        ui.same_line();
+
+        if ui.button("Devices") {
+            app_state.show_devices_window = true;
+        }
+
+        if let Some(error) = app_state.spotify_handler.as_ref().and_then(|h| h.request_error()) {
+            ui.text_colored([1.0, 0.0, 0.0, 1.0], error);
+        }
+
+        if let Some(handler) = app_state.spotify_handler.as_mut() {
+            if let Some(command) = command {
+                handler.remote_command(command);
+                // Poll right away so the change shows up without waiting for the next interval.
+                self.remote_polled_at = None;
+            }
+
+            if stop_remote {
+                handler.stop_remote();
+                self.remote_playback = None;
+                self.remote_request = None;
+            }
+        }
+
+        if let Some(album_id) = open_album {
+            app_state.open_album(album_id);
         }
     }
 
     pub fn draw(&mut self, ui: &Ui, app_state: &mut AppState) {
+        let remote = app_state.spotify_handler.as_ref().map_or(false, |h| h.remote_device().is_some());
+
+        if remote {
+            if let Some(handler) = app_state.spotify_handler.as_ref() {
+                self.poll_remote(handler);
+            }
+
+            Window::new("Player").size([420.0, 300.0], Condition::FirstUseEver).build(ui, || {
+                self.draw_remote(ui, app_state);
+            });
+
+            return;
+        }
+
         if let Some(handler) = app_state.spotify_handler.as_ref() {
             if handler.is_loaded() {
-                let api = handler.get_api_handler();
-
                 if let Some(track) = handler.get_current_song() {
-                    if let Ok(track) = api.get_track(track.to_base62()) {
-                        self.current_track = track.name().to_string();
-                        self.current_artist = track.artists()[0].to_string();
+                    if self.current_id != Some(track) {
+                        self.current_id = Some(track);
+                        self.current_track = String::from("Loading...");
+                        self.current_artist = String::new();
+                        self.current_request = Some(handler.get_track(track));
+                    }
+                }
+    
+                if let Some(context) = handler.get_play_context() {
+                    if self.context != context {
+                        self.context = context;
+                        self.context_name = String::new();
+                        self.context_request = Some(handler.get_context_name(context));
                     }
                 }
     
                 if let Some(track) = handler.get_next_song() {
-                    if let Ok(track) = api.get_track(track.to_base62()) {
-                        self.next_track = track.name().to_string();
-                        self.next_artist = track.artists()[0].to_string();
+                    if self.next_id != Some(track) {
+                        self.next_id = Some(track);
+                        self.next_track = String::from("Loading...");
+                        self.next_artist = String::new();
+                        self.next_request = Some(handler.get_track(track));
                     }
                 }
             }
         }
 
+        if let Some(track) = self.current_request.as_ref().and_then(|r| r.result()) {
+            self.current_track = track.name().to_string();
+            self.current_artist = track.artists().first().cloned().unwrap_or_default();
+            self.current_request = None;
+        }
+
+        if let Some(track) = self.next_request.as_ref().and_then(|r| r.result()) {
+            self.next_track = track.name().to_string();
+            self.next_artist = track.artists().first().cloned().unwrap_or_default();
+            self.next_request = None;
+        }
+
+        if let Some(name) = self.context_request.as_ref().and_then(|r| r.result()) {
+            self.context_name = name;
+            self.context_request = None;
+        }
+
         Window::new("Player").size([420.0, 300.0], Condition::FirstUseEver).build(ui, || {
             ui.text_colored([0.2, 1.0, 0.0, 1.0], "Currently Playing:");
     
             ui.text(&self.current_track);
             ui.text(&self.current_artist);
+
+            if let Some(id) = self.current_id {
+                widgets::like_button(ui, &mut app_state.spotify_handler, &id.to_base62());
+            }
     
+            match self.context {
+                PlayContext::Album(album) => {
+                    ui.text_disabled("Playing from album:");
+                    ui.same_line();
+
+                    if widgets::album_link(ui, &self.context_name, &album.to_base62()) {
+                        app_state.open_album(album.to_base62());
+                    }
+                }
+                PlayContext::Artist(artist) => {
+                    ui.text_disabled("Playing top tracks of:");
+                    ui.same_line();
+
+                    if Selectable::new(format!("{}##context", self.context_name)).build(ui) {
+                        app_state.open_artist(artist.to_base62(), self.context_name.clone());
+                    }
+                }
+                PlayContext::Recommendations => ui.text_disabled("Playing recommendations"),
+                PlayContext::Tracks => {}
+            }
+
             ui.separator();
     
             ui.text_colored([1.0, 0.5, 0.0, 1.0], "Next Track:");
@@ -99,6 +351,12 @@ impl PlayerWindow {
                     }
                 }
             }
+
+            ui.same_line();
+
+            if ui.button("Devices") {
+                app_state.show_devices_window = true;
+            }
         });
     }
 }
@@ -1,105 +1,601 @@
 use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
 
 use imgui::*;
 
 use crate::ui::AppState;
-use crate::spotify::PlaylistData;
+use crate::ui::widgets;
+use crate::spotify::{EntryState, PlaylistData, PlaylistEntry};
+use crate::spotify::api::cache::AudioFeatures;
+use crate::spotify::api::executor::RequestHandle;
+use crate::spotify::api::provider::RecommendationSeed;
+
+/// Index of the first audio feature column, they come right after the regular ones.
+const FEATURE_COLUMNS_START: usize = 6;
 
 pub struct PlaylistWindow {
-    playlist: Arc<PlaylistData>
+    playlist: Arc<PlaylistData>,
+
+    // Rows are shown in this order, as indices into the playlist's entries.
+    order: Vec<usize>,
+    sort: Option<(usize, TableSortDirection)>,
+    order_dirty: bool,
+    // How many entries the order was made for, filters can leave it shorter than that.
+    ordered_entries: usize,
+
+    selected: HashSet<usize>,
+    selection_anchor: Option<usize>,
+
+    show_features: bool,
+    features: HashMap<String, AudioFeatures>,
+    features_request: Option<RequestHandle<HashMap<String, AudioFeatures>>>,
+    features_loaded: bool,
+    // Only enabled filters hide tracks. Tracks without features are hidden by any of them.
+    filters: Vec<(FeatureFilter, bool)>
 }
 
 impl PlaylistWindow {
     pub fn init(playlist: Arc<PlaylistData>) -> PlaylistWindow {
         PlaylistWindow {
-            playlist
+            playlist,
+
+            order: Vec::new(),
+            sort: None,
+            order_dirty: true,
+            ordered_entries: 0,
+
+            selected: HashSet::new(),
+            selection_anchor: None,
+
+            show_features: false,
+            features: HashMap::new(),
+            features_request: None,
+            features_loaded: false,
+            filters: Feature::ALL.iter().map(|feature| {
+                let (min, max) = feature.bounds();
+                (FeatureFilter { feature: *feature, min, max }, false)
+            }).collect()
+        }
+    }
+
+    fn update_order(&mut self, entries: &[PlaylistEntry]) {
+        if !self.order_dirty && self.ordered_entries == entries.len() {
+            return;
+        }
+
+        let order = {
+            let filters = self.active_filters();
+
+            (0..entries.len()).filter(|idx| {
+                if filters.is_empty() {
+                    return true;
+                }
+
+                match entries[*idx].id().and_then(|id| self.features.get(id)) {
+                    Some(features) => filters.iter().all(|filter| filter.matches(features)),
+                    None => false
+                }
+            }).collect()
+        };
+
+        let show_features = self.show_features;
+        let features = &self.features;
+
+        self.order = order;
+        // The anchor is a row in the old order, which could be past the end of the new one.
+        self.selection_anchor = None;
+
+        self.order_dirty = false;
+        self.ordered_entries = entries.len();
+
+        if let Some((column, direction)) = self.sort {
+            self.order.sort_by(|a, b| {
+                let (a, b) = (&entries[*a], &entries[*b]);
+
+                let ordering = match column {
+                    0 => a.index().cmp(&b.index()),
+                    1 => a.title().cmp(b.title()),
+                    2 => a.artist().cmp(b.artist()),
+                    3 => a.album_name().cmp(b.album_name()),
+                    4 => a.added_at().cmp(&b.added_at()),
+                    5 => a.duration().cmp(&b.duration()),
+                    column if show_features && column >= FEATURE_COLUMNS_START => {
+                        let a = a.id().and_then(|id| features.get(id));
+                        let b = b.id().and_then(|id| features.get(id));
+
+                        match Feature::ALL.get(column - FEATURE_COLUMNS_START) {
+                            Some(feature) => feature.sort_key(a).partial_cmp(&feature.sort_key(b)).unwrap_or(std::cmp::Ordering::Equal),
+                            None => std::cmp::Ordering::Equal
+                        }
+                    }
+                    _ => std::cmp::Ordering::Equal
+                };
+
+                if direction == TableSortDirection::Descending {
+                    ordering.reverse()
+                }
+                else {
+                    ordering
+                }
+            });
+        }
+    }
+
+    fn active_filters(&self) -> Vec<&FeatureFilter> {
+        if !self.show_features {
+            return Vec::new();
+        }
+
+        self.filters.iter().filter(|(_, enabled)| *enabled).map(|(filter, _)| filter).collect()
+    }
+
+    fn draw_features(&mut self, ui: &Ui, app_state: &AppState) {
+        if ui.checkbox("Audio features", &mut self.show_features) {
+            self.order_dirty = true;
+        }
+
+        if !self.show_features {
+            return;
+        }
+
+        if !self.features_loaded {
+            if let Some(handler) = app_state.spotify_handler.as_ref() {
+                self.features_request = Some(handler.get_audio_features(&self.playlist));
+                self.features_loaded = true;
+            }
+        }
+
+        widgets::request_status(ui, &self.features_request, "audio features");
+
+        for (filter, enabled) in self.filters.iter_mut() {
+            let (lowest, highest) = filter.feature.bounds();
+            let id_token = ui.push_id(filter.feature.label());
+
+            if ui.checkbox(filter.feature.label(), enabled) {
+                self.order_dirty = true;
+            }
+
+            if *enabled {
+                ui.same_line_with_pos(100.0);
+                ui.set_next_item_width(150.0);
+                let min_changed = Slider::new("Min", lowest, highest).build(ui, &mut filter.min);
+
+                ui.same_line();
+                ui.set_next_item_width(150.0);
+                let max_changed = Slider::new("Max", lowest, highest).build(ui, &mut filter.max);
+
+                if min_changed || max_changed {
+                    // Dragging one end past the other takes the other one along.
+                    if filter.min > filter.max {
+                        if min_changed {
+                            filter.max = filter.min;
+                        }
+                        else {
+                            filter.min = filter.max;
+                        }
+                    }
+
+                    self.order_dirty = true;
+                }
+            }
+
+            id_token.pop();
         }
     }
 
+    /// Plain clicks select a row, ctrl toggles it and shift selects everything from the last clicked row.
+    fn select_row(&mut self, ui: &Ui, row: usize) {
+        let io = ui.io();
+
+        if io.key_shift {
+            if let Some(anchor) = self.selection_anchor {
+                let (first, last) = (anchor.min(row), anchor.max(row));
+                self.selected = self.order[first..=last].iter().cloned().collect();
+
+                return;
+            }
+        }
+
+        let idx = self.order[row];
+
+        if io.key_ctrl {
+            if !self.selected.remove(&idx) {
+                self.selected.insert(idx);
+            }
+        }
+        else {
+            self.selected.clear();
+            self.selected.insert(idx);
+        }
+
+        self.selection_anchor = Some(row);
+    }
+
+    /// The range of tracks to move when `dragged` is dropped.
+    /// That's the whole selection if it's contiguous and contains the row, otherwise just the row itself.
+    fn drag_range(&self, dragged: usize) -> (usize, usize) {
+        if self.selected.contains(&dragged) {
+            let first = *self.selected.iter().min().unwrap_or(&dragged);
+            let last = *self.selected.iter().max().unwrap_or(&dragged);
+
+            if last - first + 1 == self.selected.len() {
+                return (first, self.selected.len());
+            }
+        }
+
+        (dragged, 1)
+    }
+
     pub fn draw(&mut self, ui: &Ui, app_state: &mut AppState) {
         let mut show_window = app_state.show_playlist_window;
 
-        Window::new("Playlist").size([800.0, 500.0], Condition::FirstUseEver).opened(&mut show_window).build(ui, || {
+        // Playlists get replaced whenever they're edited, so keep up with the latest version.
+        if let Some(handler) = app_state.spotify_handler.as_ref() {
+            if let Some(playlist) = handler.find_playlist(self.playlist.kind()) {
+                if !Arc::ptr_eq(&playlist, &self.playlist) {
+                    // Edits keep the loaded rows, and a fetch that's still running fills in the new copy too.
+                    if !playlist.is_fully_loaded() && !playlist.is_fetching() {
+                        let api_handler = handler.get_api_handler();
+                        let playlist_fetch = playlist.clone();
+
+                        std::thread::spawn(move || {
+                            playlist_fetch.fetch_data(api_handler);
+                        });
+                    }
+
+                    // The selection is by position, which only still means the same thing if the playlist didn't change.
+                    // Another device might have reordered it without changing its length.
+                    let snapshot_changed = playlist.details().snapshot_id != self.playlist.details().snapshot_id;
+                    let items_changed = playlist.items().iter().map(|i| &i.uri).ne(self.playlist.items().iter().map(|i| &i.uri));
+
+                    if snapshot_changed || items_changed {
+                        self.selected.clear();
+                        self.selection_anchor = None;
+                    }
+
+                    self.playlist = playlist;
+                    self.order_dirty = true;
+                    self.features_loaded = false;
+                }
+            }
+        }
+
+        if let Some(features) = self.features_request.as_ref().and_then(|r| r.result()) {
+            self.features = features;
+            self.features_request = None;
+            self.order_dirty = true;
+        }
+
+        Window::new(format!("{}###Playlist", self.playlist.title())).size([800.0, 500.0], Condition::FirstUseEver).opened(&mut show_window).build(ui, || {
             let mut play_song = None;
-            let mut remove_song = None;
+            let mut open_album = None;
+            let mut remove_songs: Vec<usize> = Vec::new();
+
+            if self.playlist.is_fetching() {
+                let (loaded, total) = self.playlist.fetch_progress();
+                let fraction = if total > 0 { loaded as f32 / total as f32 } else { 0.0 };
+
+                ProgressBar::new(fraction)
+                    .overlay_text(format!("Loading tracks... {}/{}", loaded, total))
+                    .size([300.0, 0.0])
+                    .build(ui)
+                ;
+
+                if let Some(handler) = app_state.spotify_handler.as_ref() {
+                    if handler.get_api_handler().is_throttled() {
+                        ui.same_line();
+                        ui.text_colored([1.0, 0.5, 0.0, 1.0], "(rate limited, waiting before retrying)");
+                    }
+                }
+            }
     
-            if let Ok(mut entries) = self.playlist.entries_data().try_write() {
-                let token = ui.begin_table_header_with_flags(
-                    "Playlist Table",
-                    [
-                        TableColumnSetup::new("Title"),
-                        TableColumnSetup::new("Artist"),
-                        TableColumnSetup::new("Duration"),
-                        TableColumnSetup::new("Actions")
-                    ],
-                    TableFlags::BORDERS | TableFlags::RESIZABLE | TableFlags::SORTABLE
+            let editable = self.playlist.is_editable();
+            let mut move_tracks = None;
+
+            self.draw_features(ui, app_state);
+
+            // Every item has a row, so positions always line up, but dragging only makes sense in the playlist's own order.
+            let can_remove = editable;
+            // Sorting by # ascending is the playlist's own order too.
+            let in_playlist_order = self.sort.is_none() || self.sort == Some((0, TableSortDirection::Ascending));
+            let can_reorder = can_remove && in_playlist_order && self.active_filters().is_empty();
+
+            if editable && !can_reorder {
+                ui.text_disabled("Sort by # ascending, or not at all, and clear the filters to drag tracks around.");
+            }
+
+            if can_remove && !self.selected.is_empty() {
+                if ui.button(format!("Remove {} selected", self.selected.len())) {
+                    remove_songs = self.selected.iter().cloned().collect();
+                }
+
+                ui.same_line();
+
+                if ui.button("Clear selection") {
+                    self.selected.clear();
+                    self.selection_anchor = None;
+                }
+            }
+
+            let playlist = self.playlist.clone();
+
+            if let Ok(entries) = playlist.entries_data().try_read() {
+                let mut columns = vec!["#", "Title", "Artist", "Album", "Added", "Duration"];
+
+                if self.show_features {
+                    columns.extend(Feature::ALL.iter().map(|feature| feature.label()));
+                }
+
+                columns.push("Actions");
+
+                // A separate table for each set of columns, so sorting by a column that went away doesn't stick around.
+                let token = ui.begin_table_with_flags(
+                    if self.show_features { "Playlist Table Features" } else { "Playlist Table" },
+                    columns.len(),
+                    TableFlags::BORDERS | TableFlags::RESIZABLE | TableFlags::SORTABLE | TableFlags::SORT_TRISTATE
                 );
-                
+
                 if let Some(_t) = token {
+                    for column in columns.iter() {
+                        ui.table_setup_column(column);
+                    }
+
+                    ui.table_headers_row();
+
                     if let Some(data) = ui.table_sort_specs_mut() {
+                        let mut sort = self.sort;
+
                         data.conditional_sort(|specs| {
-                            if let Some(spec) = specs.iter().next() {
-                                if let Some(direction) = spec.sort_direction() {
-                                    match direction {
-                                        TableSortDirection::Ascending => match spec.column_idx() {
-                                            0 => entries.sort_by_key(|e| e.title().clone()),
-                                            1 => entries.sort_by_key(|e| e.artist().clone()),
-                                            2 => entries.sort_by_key(|e| *e.duration()),
-                                            _ => {}
-                                        }
-                                        TableSortDirection::Descending => match spec.column_idx() {
-                                            0 => entries.sort_by_key(|e| std::cmp::Reverse(e.title().clone())),
-                                            1 => entries.sort_by_key(|e| std::cmp::Reverse(e.artist().clone())),
-                                            2 => entries.sort_by_key(|e| std::cmp::Reverse(*e.duration())),
-                                            _ => {}
-                                        }
-                                    }
-                                }
-                            }
+                            sort = specs.iter().next().and_then(|spec| spec.sort_direction().map(|d| (spec.column_idx(), d)));
                         });
+
+                        if sort != self.sort {
+                            self.sort = sort;
+                            self.order_dirty = true;
+                        }
                     }
 
-                    for entry in entries.iter() {
+                    self.update_order(&entries);
+
+                    for row in 0..self.order.len() {
+                        let idx = self.order[row];
+                        let entry = &entries[idx];
+                        let id_token = ui.push_id(idx as i32);
+
                         ui.table_next_column();
-                        ui.text(entry.title());
+                        ui.text(format!("{}", entry.index() + 1));
+
+                        ui.table_next_column();
+
+                        if Selectable::new(entry.title()).selected(self.selected.contains(&idx)).build(ui) {
+                            self.select_row(ui, row);
+                        }
+
+                        if can_reorder {
+                            if let Some(tooltip) = DragDropSource::new("playlist_rows").begin_payload(ui, idx) {
+                                let (_, range_length) = self.drag_range(idx);
+
+                                if range_length > 1 {
+                                    ui.text(format!("{} tracks", range_length));
+                                }
+                                else {
+                                    ui.text(entry.title());
+                                }
+
+                                tooltip.end();
+                            }
+
+                            if let Some(target) = DragDropTarget::new(ui) {
+                                if let Some(Ok(payload)) = target.accept_payload::<usize, _>("playlist_rows", DragDropFlags::empty()) {
+                                    move_tracks = Some((payload.data, idx));
+                                }
+
+                                target.pop();
+                            }
+                        }
 
                         ui.table_next_column();
                         ui.text(entry.artist());
+
+                        ui.table_next_column();
+                        if let Some(album_id) = entry.album() {
+                            if widgets::album_link(ui, entry.album_name(), album_id) {
+                                open_album = Some(album_id.clone());
+                            }
+                        }
+
+                        ui.table_next_column();
+                        ui.text(entry.added_at().map(|a| a.as_str()).unwrap_or(""));
+
+                        if let Some(added_by) = entry.added_by() {
+                            if ui.is_item_hovered() {
+                                ui.tooltip_text(format!("Added by {}", added_by));
+                            }
+                        }
     
                         let seconds = entry.duration() / 1000;
                         let minutes = seconds / 60;
                         let seconds = seconds % 60;
     
                         ui.table_next_column();
-                        ui.text(format!("{}:{:02}", minutes, seconds));
+                        if entry.id().is_some() {
+                            ui.text(format!("{}:{:02}", minutes, seconds));
+                        }
+
+                        if self.show_features {
+                            let features = entry.id().and_then(|id| self.features.get(id));
+
+                            for feature in Feature::ALL.iter() {
+                                ui.table_next_column();
+
+                                if let Some(features) = features {
+                                    ui.text(feature.format(features));
+                                }
+                            }
+                        }
 
                         ui.table_next_column();
-                        if ui.button(format!("Play##{}", entry.id())) {
-                            play_song = Some(entry.id().clone());
+                        if let Some(track_id) = entry.id() {
+                            if ui.button("Play") {
+                                play_song = Some(track_id.clone());
+                            }
+        
+                            ui.same_line();
+                            widgets::like_button(ui, &mut app_state.spotify_handler, track_id);
+
+                            ui.same_line();
+                            widgets::add_to_playlist_button(ui, &mut app_state.spotify_handler, track_id);
+
+                            ui.same_line();
+                            widgets::seed_button(ui, &mut app_state.recommendation_seeds, RecommendationSeed::Track { id: track_id.clone(), name: entry.title().to_string() });
                         }
-    
-                        ui.same_line();
-                        if ui.button(format!("Remove##{}", entry.id())) {
-                            remove_song = Some(entry.id().clone());
+                        else {
+                            ui.text_disabled(state_label(entry.state()));
                         }
+
+                        if can_remove && entry.is_removable() {
+                            ui.same_line();
+                            if ui.button("Remove") {
+                                remove_songs = vec![idx];
+                            }
+                        }
+
+                        id_token.pop();
                     }
                 }
             }
 
+            if let (Some((dragged, target)), Some(playlist_id)) = (move_tracks, self.playlist.id()) {
+                let (range_start, range_length) = self.drag_range(dragged);
+
+                // Dropping on a row puts the tracks where that row was.
+                let insert_before = if target > range_start { target + 1 } else { target };
+
+                if insert_before < range_start || insert_before > range_start + range_length {
+                    if let Some(handler) = app_state.spotify_handler.as_mut() {
+                        handler.move_playlist_tracks(playlist_id, range_start, range_length, insert_before);
+                    }
+
+                    let insert_at = if insert_before > range_start { insert_before - range_length } else { insert_before };
+
+                    self.selected = (insert_at..insert_at + range_length).collect();
+                    self.selection_anchor = None;
+                }
+            }
+
+            if let Some(album_id) = open_album {
+                app_state.open_album(album_id);
+            }
+
             if let Some(track_to_play) = play_song {
                 if let Some(handler) = app_state.spotify_handler.as_mut() {
                     app_state.show_player_window = true;
-                    handler.play_song_on_playlist(self.playlist.id().to_base62(), &track_to_play);
+                    handler.play_song_on_playlist(&self.playlist, &track_to_play);
                 }
             }
 
-            if let Some(track_to_remove) = remove_song {
-                if let Some(handler) = app_state.spotify_handler.as_mut() {
-                    handler.remove_track_from_playlist(&self.playlist.id().to_base62(), &track_to_remove);
+            if !remove_songs.is_empty() {
+                if let (Some(handler), Some(playlist_id)) = (app_state.spotify_handler.as_mut(), self.playlist.id()) {
+                    handler.remove_playlist_tracks(playlist_id, &remove_songs);
                 }
+
+                self.selected.clear();
+                self.selection_anchor = None;
             }
         });
     
         app_state.show_playlist_window = show_window;
     }
 }
+
+fn state_label(state: &EntryState) -> &'static str {
+    match state {
+        EntryState::Loading => "Loading...",
+        EntryState::Loaded(_) => "",
+        EntryState::Unavailable => "Unavailable",
+        EntryState::LocalFile => "Local file",
+        EntryState::Episode => "Episode"
+    }
+}
+
+/// Audio feature columns, in the order they're shown.
+#[derive(Clone, Copy)]
+enum Feature {
+    Tempo,
+    Key,
+    Energy,
+    Danceability,
+    Valence,
+    Loudness
+}
+
+impl Feature {
+    const ALL: [Feature; 6] = [Feature::Tempo, Feature::Key, Feature::Energy, Feature::Danceability, Feature::Valence, Feature::Loudness];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Feature::Tempo => "BPM",
+            Feature::Key => "Key",
+            Feature::Energy => "Energy",
+            Feature::Danceability => "Dance",
+            Feature::Valence => "Valence",
+            Feature::Loudness => "Loudness"
+        }
+    }
+
+    /// The range filters can be set to. Keys are filtered by their Camelot number.
+    fn bounds(&self) -> (f32, f32) {
+        match self {
+            Feature::Tempo => (40.0, 220.0),
+            Feature::Key => (1.0, 12.0),
+            Feature::Loudness => (-60.0, 0.0),
+            _ => (0.0, 1.0)
+        }
+    }
+
+    fn value(&self, features: &AudioFeatures) -> Option<f32> {
+        match self {
+            Feature::Tempo => Some(*features.tempo()),
+            Feature::Key => features.camelot().map(|(number, _)| number as f32),
+            Feature::Energy => Some(*features.energy()),
+            Feature::Danceability => Some(*features.danceability()),
+            Feature::Valence => Some(*features.valence()),
+            Feature::Loudness => Some(*features.loudness())
+        }
+    }
+
+    /// Keys sort around the Camelot wheel, with the minor key before the major one at each number.
+    fn sort_key(&self, features: Option<&AudioFeatures>) -> Option<f32> {
+        match self {
+            Feature::Key => features.and_then(|f| f.camelot()).map(|(number, letter)| number as f32 * 2.0 + if letter == 'B' { 1.0 } else { 0.0 }),
+            _ => features.and_then(|f| self.value(f))
+        }
+    }
+
+    fn format(&self, features: &AudioFeatures) -> String {
+        match self {
+            Feature::Tempo => format!("{:.0}", features.tempo()),
+            Feature::Key => match features.camelot() {
+                Some((number, letter)) => format!("{}{} ({})", number, letter, features.key_name()),
+                None => String::new()
+            },
+            Feature::Loudness => format!("{:.1} dB", features.loudness()),
+            _ => format!("{:.2}", self.value(features).unwrap_or(0.0))
+        }
+    }
+}
+
+struct FeatureFilter {
+    feature: Feature,
+    min: f32,
+    max: f32
+}
+
+impl FeatureFilter {
+    fn matches(&self, features: &AudioFeatures) -> bool {
+        match self.feature.value(features) {
+            Some(value) => value >= self.min && value <= self.max,
+            None => false
+        }
+    }
+}
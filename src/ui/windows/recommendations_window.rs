@@ -0,0 +1,230 @@
+use imgui::*;
+
+use crate::ui::AppState;
+use crate::ui::widgets;
+use crate::spotify::player::PlayContext;
+use crate::spotify::api::cache::TrackInfo;
+use crate::spotify::api::executor::RequestHandle;
+use crate::spotify::api::provider::{AttributeRange, RecommendationSeed, TrackAttribute, MAX_SEEDS};
+
+pub struct RecommendationsWindow {
+    genre: String,
+    // Only enabled attributes are sent, the others are left up to Spotify.
+    attributes: Vec<(AttributeRange, bool)>,
+
+    tracks: Vec<TrackInfo>,
+    request: Option<RequestHandle<Vec<TrackInfo>>>,
+
+    playlist_name: String
+}
+
+impl RecommendationsWindow {
+    pub fn init() -> RecommendationsWindow {
+        let attributes = TrackAttribute::ALL.iter().map(|attribute| {
+            let (min, max) = attribute.bounds();
+            (AttributeRange { attribute: *attribute, min, max }, false)
+        }).collect();
+
+        RecommendationsWindow {
+            genre: String::new(),
+            attributes,
+
+            tracks: Vec::new(),
+            request: None,
+
+            playlist_name: String::from("Recommendations")
+        }
+    }
+
+    pub fn draw(&mut self, ui: &Ui, app_state: &mut AppState) {
+        let mut show_window = app_state.show_recommendations_window;
+
+        if let Some(tracks) = self.request.as_ref().and_then(|r| r.result()) {
+            self.tracks = tracks;
+            self.request = None;
+        }
+
+        Window::new("Recommendations").size([700.0, 600.0], Condition::FirstUseEver).opened(&mut show_window).build(ui, || {
+            self.draw_seeds(ui, app_state);
+
+            ui.separator();
+            self.draw_attributes(ui);
+
+            ui.separator();
+
+            if app_state.recommendation_seeds.is_empty() {
+                ui.text_disabled("Recommendations need at least one seed.");
+            }
+            else if ui.button("Get recommendations") {
+                if let Some(handler) = app_state.spotify_handler.as_ref() {
+                    let attributes = self.attributes.iter().filter(|(_, enabled)| *enabled).map(|(range, _)| *range).collect();
+                    self.request = Some(handler.get_recommendations(app_state.recommendation_seeds.clone(), attributes));
+                }
+            }
+
+            if let Some(request) = self.request.as_ref() {
+                if let Some(error) = request.error() {
+                    ui.text_colored([1.0, 0.0, 0.0, 1.0], format!("Couldn't get recommendations: {}", error));
+                }
+                else {
+                    ui.text("Loading...");
+                }
+            }
+
+            if self.tracks.is_empty() {
+                return;
+            }
+
+            self.draw_tracks(ui, app_state);
+        });
+
+        app_state.show_recommendations_window = show_window;
+    }
+
+    fn draw_seeds(&mut self, ui: &Ui, app_state: &mut AppState) {
+        let seeds = &mut app_state.recommendation_seeds;
+
+        ui.text(format!("Seeds ({}/{})", seeds.len(), MAX_SEEDS));
+
+        if seeds.is_empty() {
+            ui.text_disabled("Use the Seed buttons next to tracks and artists, or add a genre below.");
+        }
+
+        let mut remove = None;
+
+        for (idx, seed) in seeds.iter().enumerate() {
+            ui.text(seed.label());
+            ui.same_line();
+
+            if ui.small_button(format!("Remove##seed_{}", idx)) {
+                remove = Some(idx);
+            }
+        }
+
+        if let Some(idx) = remove {
+            seeds.remove(idx);
+        }
+
+        ui.input_text("Genre", &mut self.genre).hint("e.g. synth-pop").build();
+        ui.same_line();
+
+        let genre = self.genre.trim().to_lowercase();
+
+        if ui.button("Add genre") && !genre.is_empty() && seeds.len() < MAX_SEEDS {
+            let seed = RecommendationSeed::Genre(genre);
+
+            if !seeds.contains(&seed) {
+                seeds.push(seed);
+            }
+
+            self.genre.clear();
+        }
+    }
+
+    fn draw_attributes(&mut self, ui: &Ui) {
+        for (range, enabled) in self.attributes.iter_mut() {
+            let (lowest, highest) = range.attribute.bounds();
+            let id_token = ui.push_id(range.attribute.name());
+
+            ui.checkbox(range.attribute.label(), enabled);
+
+            if *enabled {
+                Slider::new("Min", lowest, highest).build(ui, &mut range.min);
+                Slider::new("Max", lowest, highest).build(ui, &mut range.max);
+
+                // Dragging one end past the other takes the other one along.
+                if range.min > range.max {
+                    range.max = range.min;
+                }
+            }
+
+            id_token.pop();
+        }
+    }
+
+    fn draw_tracks(&mut self, ui: &Ui, app_state: &mut AppState) {
+        let mut play = None;
+        let mut open_album = None;
+
+        if ui.button("Play all") {
+            play = Some(None);
+        }
+
+        ui.same_line();
+        ui.set_next_item_width(200.0);
+        ui.input_text("##playlist_name", &mut self.playlist_name).build();
+        ui.same_line();
+
+        if ui.button("Save as playlist") && !self.playlist_name.trim().is_empty() {
+            if let Some(handler) = app_state.spotify_handler.as_mut() {
+                let track_ids = self.tracks.iter().map(|t| t.id().clone()).collect();
+                handler.save_as_playlist(self.playlist_name.trim().to_string(), track_ids);
+            }
+        }
+
+        let token = ui.begin_table_header_with_flags(
+            "Recommended Tracks",
+            [
+                TableColumnSetup::new("Title"),
+                TableColumnSetup::new("Artist"),
+                TableColumnSetup::new("Album"),
+                TableColumnSetup::new("Duration"),
+                TableColumnSetup::new("Actions")
+            ],
+            TableFlags::BORDERS | TableFlags::RESIZABLE
+        );
+
+        if let Some(_t) = token {
+            for track in self.tracks.iter() {
+                let seconds = track.duration() / 1000;
+                let minutes = seconds / 60;
+                let seconds = seconds % 60;
+
+                let id_token = ui.push_id(track.id().as_str());
+
+                ui.table_next_column();
+                ui.text(track.name());
+
+                ui.table_next_column();
+                ui.text(track.artists().join(", "));
+
+                ui.table_next_column();
+
+                if widgets::album_link(ui, track.album_name(), track.album()) {
+                    open_album = Some(track.album().clone());
+                }
+
+                ui.table_next_column();
+                ui.text(format!("{}:{:02}", minutes, seconds));
+
+                ui.table_next_column();
+
+                if ui.button("Play") {
+                    play = Some(Some(track.id().clone()));
+                }
+
+                ui.same_line();
+                widgets::like_button(ui, &mut app_state.spotify_handler, track.id());
+
+                ui.same_line();
+                widgets::add_to_playlist_button(ui, &mut app_state.spotify_handler, track.id());
+
+                ui.same_line();
+                widgets::seed_button(ui, &mut app_state.recommendation_seeds, RecommendationSeed::Track { id: track.id().clone(), name: track.name().clone() });
+
+                id_token.pop();
+            }
+        }
+
+        if let Some(start) = play {
+            if let Some(handler) = app_state.spotify_handler.as_mut() {
+                handler.play_tracks(PlayContext::Recommendations, &self.tracks, start.as_deref());
+                app_state.show_player_window = true;
+            }
+        }
+
+        if let Some(album_id) = open_album {
+            app_state.open_album(album_id);
+        }
+    }
+}
@@ -1,75 +1,721 @@
+use std::cmp::Ordering;
+use std::time::{Duration, Instant};
+
 use imgui::*;
 use librespot::core::spotify_id::SpotifyId;
 
+use rspotify::model::page::Page;
+use rspotify::model::track::FullTrack;
+use rspotify::model::artist::{FullArtist, SimplifiedArtist};
+use rspotify::model::album::SimplifiedAlbum;
+use rspotify::model::search::SearchResult;
+use rspotify::model::playlist::SimplifiedPlaylist;
+use rspotify::model::show::{SimplifiedEpisode, SimplifiedShow};
+use rspotify::senum::SearchType;
+
 use crate::ui::AppState;
+use crate::ui::widgets;
+use crate::ui::search_history::SearchHistory;
+use crate::spotify::SpotifyHandler;
+use crate::spotify::search::{self, SearchQuery};
+use crate::spotify::api::executor::RequestHandle;
+use crate::spotify::api::provider::RecommendationSeed;
 
-pub fn build(ui: &Ui, app_state: &mut AppState) {
-    let mut show_window = app_state.show_search_window;
+// Spotify doesn't page search results past this.
+const MAX_SEARCH_OFFSET: u32 = 1000;
 
-    Window::new("Search").size([800.0, 500.0], Condition::FirstUseEver).opened(&mut show_window).build(ui, || {
-        if ui.input_text("Search Query", &mut app_state.search_query).enter_returns_true(true).build() {
-            if let Some(handler) = app_state.spotify_handler.as_ref() {
-                app_state.search_results_artists = handler.search_artists(app_state.search_query.to_string());
-                app_state.search_results_tracks = handler.search_tracks(app_state.search_query.to_string());
+// How long typing has to pause before the query is searched.
+const SEARCH_DEBOUNCE: Duration = Duration::from_millis(300);
+
+const MAX_HISTORY_ROWS: usize = 8;
+
+/// Result types that get their own search tab.
+trait Searchable: Clone {
+    const SEARCH_TYPE: SearchType;
+
+    fn from_search_result(result: SearchResult) -> Option<Page<Self>>;
+}
+
+impl Searchable for FullTrack {
+    const SEARCH_TYPE: SearchType = SearchType::Track;
+
+    fn from_search_result(result: SearchResult) -> Option<Page<FullTrack>> {
+        if let SearchResult::Tracks(page) = result { Some(page) } else { None }
+    }
+}
+
+impl Searchable for FullArtist {
+    const SEARCH_TYPE: SearchType = SearchType::Artist;
+
+    fn from_search_result(result: SearchResult) -> Option<Page<FullArtist>> {
+        if let SearchResult::Artists(page) = result { Some(page) } else { None }
+    }
+}
+
+impl Searchable for SimplifiedAlbum {
+    const SEARCH_TYPE: SearchType = SearchType::Album;
+
+    fn from_search_result(result: SearchResult) -> Option<Page<SimplifiedAlbum>> {
+        if let SearchResult::Albums(page) = result { Some(page) } else { None }
+    }
+}
+
+impl Searchable for SimplifiedPlaylist {
+    const SEARCH_TYPE: SearchType = SearchType::Playlist;
+
+    fn from_search_result(result: SearchResult) -> Option<Page<SimplifiedPlaylist>> {
+        if let SearchResult::Playlists(page) = result { Some(page) } else { None }
+    }
+}
+
+impl Searchable for SimplifiedShow {
+    const SEARCH_TYPE: SearchType = SearchType::Show;
+
+    fn from_search_result(result: SearchResult) -> Option<Page<SimplifiedShow>> {
+        if let SearchResult::Shows(page) = result { Some(page) } else { None }
+    }
+}
+
+impl Searchable for SimplifiedEpisode {
+    const SEARCH_TYPE: SearchType = SearchType::Episode;
+
+    fn from_search_result(result: SearchResult) -> Option<Page<SimplifiedEpisode>> {
+        if let SearchResult::Episodes(page) = result { Some(page) } else { None }
+    }
+}
+
+/// The results of one type for the current query, loaded a page at a time.
+struct SearchTab<T> {
+    query: String,
+    market: Option<String>,
+    results: Vec<T>,
+    total: u32,
+    has_more: bool,
+    request: Option<RequestHandle<SearchResult>>,
+
+    // Rows are shown in this order, as indices into the results.
+    order: Vec<usize>,
+    sort: Option<(usize, TableSortDirection)>,
+    order_dirty: bool
+}
+
+impl<T: Searchable> SearchTab<T> {
+    fn new() -> SearchTab<T> {
+        SearchTab {
+            query: String::new(),
+            market: None,
+            results: Vec::new(),
+            total: 0,
+            has_more: false,
+            request: None,
+
+            order: Vec::new(),
+            sort: None,
+            order_dirty: true
+        }
+    }
+
+    /// Replacing the request means a response for an older query is never applied.
+    fn search(&mut self, handler: &SpotifyHandler, query: &str, market: Option<&str>) {
+        self.query = query.to_string();
+        self.market = market.map(|m| m.to_string());
+        self.results.clear();
+        self.total = 0;
+        self.has_more = false;
+        self.order_dirty = true;
+
+        self.request = Some(handler.search(self.query.clone(), T::SEARCH_TYPE, self.market.clone(), 0));
+    }
+
+    fn load_more(&mut self, handler: &SpotifyHandler) {
+        self.request = Some(handler.search(self.query.clone(), T::SEARCH_TYPE, self.market.clone(), self.results.len() as u32));
+    }
+
+    fn update(&mut self) {
+        if let Some(result) = self.request.as_ref().and_then(|r| r.result()) {
+            if let Some(page) = T::from_search_result(result) {
+                self.has_more = page.next.is_some() && page.offset + page.limit < MAX_SEARCH_OFFSET;
+                self.total = page.total;
+                self.results.extend(page.items);
+                self.order_dirty = true;
             }
+
+            self.request = None;
         }
+    }
 
-        ui.separator();
+    fn update_order(&mut self, compare: impl Fn(&T, &T, usize) -> Ordering) {
+        if !self.order_dirty && self.order.len() == self.results.len() {
+            return;
+        }
 
-        ui.text_colored([0.0, 1.0, 0.0, 1.0], "Artists");
-        ui.separator();
+        self.order = (0..self.results.len()).collect();
+        self.order_dirty = false;
 
-        ui.columns(3, "results_columns_artists", true);
-        
-        for artist in app_state.search_results_artists.iter() {
-            ui.text(artist.name.to_string());
-            ui.next_column();
+        if let Some((column, direction)) = self.sort {
+            let results = &self.results;
 
-            ui.text(format!("{} followers", artist.followers.get("total").unwrap().as_ref().unwrap()));
-            ui.next_column();
+            self.order.sort_by(|a, b| {
+                let ordering = compare(&results[*a], &results[*b], column);
 
-            if ui.button(format!("View##{}", artist.id)) {
-                if let Some(handler) = app_state.spotify_handler.as_mut() {
-                    app_state.show_artist_window = true;
-                    app_state.search_artist_page_tracks = handler.get_artist_data(artist.id.clone());
+                if direction == TableSortDirection::Descending {
+                    ordering.reverse()
+                }
+                else {
+                    ordering
                 }
+            });
+        }
+    }
+}
+
+pub struct SearchWindow {
+    query: String,
+    submitted_query: String,
+    edited_at: Option<Instant>,
+
+    show_advanced: bool,
+    filters: SearchQuery,
+    market: String,
+    submitted_market: Option<String>,
+
+    history: SearchHistory,
+    history_hovered: bool,
+
+    tracks: SearchTab<FullTrack>,
+    artists: SearchTab<FullArtist>,
+    albums: SearchTab<SimplifiedAlbum>,
+    playlists: SearchTab<SimplifiedPlaylist>,
+    shows: SearchTab<SimplifiedShow>,
+    episodes: SearchTab<SimplifiedEpisode>
+}
+
+impl SearchWindow {
+    pub fn init() -> SearchWindow {
+        SearchWindow {
+            query: String::new(),
+            submitted_query: String::new(),
+            edited_at: None,
+
+            show_advanced: false,
+            filters: SearchQuery::default(),
+            market: String::new(),
+            submitted_market: None,
+
+            history: SearchHistory::load(),
+            history_hovered: false,
+
+            tracks: SearchTab::new(),
+            artists: SearchTab::new(),
+            albums: SearchTab::new(),
+            playlists: SearchTab::new(),
+            shows: SearchTab::new(),
+            episodes: SearchTab::new()
+        }
+    }
+
+    pub fn draw(&mut self, ui: &Ui, app_state: &mut AppState) {
+        let mut show_window = app_state.show_search_window;
+
+        Window::new("Search").size([800.0, 500.0], Condition::FirstUseEver).opened(&mut show_window).build(ui, || {
+            let previous_query = self.query.clone();
+
+            if ui.input_text("Search Query", &mut self.query).enter_returns_true(true).build() {
+                self.submit(true);
+            }
+            else if self.query != previous_query {
+                self.filters = SearchQuery::parse(&self.query);
+                self.edited_at = Some(Instant::now());
             }
 
-            ui.next_column();
+            let input_active = ui.is_item_active();
+
+            if self.edited_at.map_or(false, |edited_at| edited_at.elapsed() >= SEARCH_DEBOUNCE) {
+                self.submit(false);
+            }
+
+            // The dropdown stays open while it's hovered, so clicking it doesn't close it before the click lands.
+            if input_active || self.history_hovered {
+                self.draw_history(ui);
+            }
+            else {
+                self.history_hovered = false;
+            }
+
+            ui.checkbox("Advanced", &mut self.show_advanced);
+
+            if self.show_advanced {
+                self.draw_advanced(ui);
+            }
+
+            ui.separator();
+
+            if self.submitted_query.is_empty() {
+                return;
+            }
+
+            // Tabs only search once they're opened, and again when the query changed since.
+            let query = self.submitted_query.clone();
+            let market = self.submitted_market.clone();
+
+            TabBar::new("Search Tabs").build(ui, || {
+                TabItem::new("Tracks").build(ui, || {
+                    results_table(ui, &mut self.tracks, &query, market.as_deref(), app_state, &["Title", "Artist", "Album", "Duration"], compare_tracks, track_row);
+                });
+
+                TabItem::new("Artists").build(ui, || {
+                    results_table(ui, &mut self.artists, &query, market.as_deref(), app_state, &["Name", "Followers", "Genres"], compare_artists, artist_row);
+                });
+
+                TabItem::new("Albums").build(ui, || {
+                    results_table(ui, &mut self.albums, &query, market.as_deref(), app_state, &["Title", "Artist", "Released", "Type"], compare_albums, album_row);
+                });
+
+                TabItem::new("Playlists").build(ui, || {
+                    results_table(ui, &mut self.playlists, &query, market.as_deref(), app_state, &["Name", "Owner", "Tracks"], compare_playlists, playlist_row);
+                });
+
+                TabItem::new("Shows").build(ui, || {
+                    results_table(ui, &mut self.shows, &query, market.as_deref(), app_state, &["Name", "Publisher"], compare_shows, show_row);
+                });
+
+                TabItem::new("Episodes").build(ui, || {
+                    results_table(ui, &mut self.episodes, &query, market.as_deref(), app_state, &["Name", "Released", "Duration"], compare_episodes, episode_row);
+                });
+            });
+        });
+
+        app_state.show_search_window = show_window;
+    }
+
+    /// Searches the typed query. Only queries that were entered or picked end up in the history,
+    /// not everything searched while typing.
+    fn submit(&mut self, remember: bool) {
+        self.edited_at = None;
+        self.filters = SearchQuery::parse(&self.query);
+
+        // The advanced panel shows what's wrong, Spotify would only ignore it or fail.
+        if self.filters.year_error().is_some() || search::market_error(&self.market).is_some() {
+            return;
         }
 
-        ui.columns(1, "yeet", false);
+        self.submitted_query = self.query.trim().to_string();
+        self.submitted_market = if self.market.is_empty() { None } else { Some(self.market.to_uppercase()) };
+
+        if remember {
+            self.history.add(&self.submitted_query);
+        }
+    }
+
+    /// A field for each filter. Editing them rewrites the query, typing filters into the query fills them in.
+    fn draw_advanced(&mut self, ui: &Ui) {
+        let mut changed = false;
+
+        changed |= ui.input_text("Artist", &mut self.filters.artist).build();
+        changed |= ui.input_text("Album", &mut self.filters.album).build();
+        changed |= ui.input_text("Genre", &mut self.filters.genre).build();
+        changed |= ui.input_text("Year", &mut self.filters.year).hint("1995 or 1990-1999").build();
+
+        if let Some(error) = self.filters.year_error() {
+            ui.text_colored([1.0, 0.0, 0.0, 1.0], error);
+        }
+
+        changed |= ui.checkbox("New releases (tag:new)", &mut self.filters.tag_new);
+        ui.same_line();
+        changed |= ui.checkbox("Least popular (tag:hipster)", &mut self.filters.tag_hipster);
+
+        if ui.input_text("Market", &mut self.market).hint("Your own").build() {
+            self.edited_at = Some(Instant::now());
+        }
+
+        if let Some(error) = search::market_error(&self.market) {
+            ui.text_colored([1.0, 0.0, 0.0, 1.0], error);
+        }
+
+        if changed {
+            self.query = self.filters.to_query_string();
+            self.edited_at = Some(Instant::now());
+        }
 
         ui.separator();
-        ui.text_colored([0.0, 1.0, 0.0, 1.0], "Tracks");
-        ui.separator();
+    }
 
-        ui.columns(4, "results_columns_tracks", true);
+    fn draw_history(&mut self, ui: &Ui) {
+        let filter = self.query.trim().to_lowercase();
+        let matches = |query: &&String| query.to_lowercase().contains(&filter) && **query != self.query.trim();
 
-        for track in app_state.search_results_tracks.iter() {
-            ui.text(track.name.to_string());
-            ui.next_column();
+        let pinned: Vec<String> = self.history.pinned().iter().filter(matches).cloned().collect();
+        let recent: Vec<String> = self.history.recent().iter().filter(matches).take(MAX_HISTORY_ROWS).cloned().collect();
 
-            ui.text(track.artists[0].name.to_string());
-            ui.next_column();
+        if pinned.is_empty() && recent.is_empty() {
+            self.history_hovered = false;
+            return;
+        }
+
+        let rows = (pinned.len() + recent.len()).min(MAX_HISTORY_ROWS) + 1;
+        let height = rows as f32 * ui.text_line_height_with_spacing() + 8.0;
+
+        let mut picked = None;
+        let mut pin = None;
+        let mut unpin = None;
+        let mut remove = None;
+        let mut hovered = false;
 
-            ui.text(track.album.name.to_string());
-            ui.next_column();
+        ChildWindow::new("Search History").size([0.0, height]).border(true).build(ui, || {
+            hovered = ui.is_window_hovered();
 
-            if let Some(id) = track.id.as_ref() {
-                if ui.button(format!("Play##{}", id)) {
-                    if let Some(handler) = app_state.spotify_handler.as_mut() {
-                        let id = SpotifyId::from_base62(id).unwrap();
-                        
-                        handler.play_single_track(id);
-                        app_state.show_player_window = true;
-                    }
+            for query in pinned.iter() {
+                let id_token = ui.push_id(query.as_str());
+
+                if Selectable::new(format!("[pinned] {}", query)).build(ui) {
+                    picked = Some(query.clone());
                 }
+
+                ui.same_line_with_pos(ui.window_content_region_width() - 60.0);
+
+                if ui.small_button("Unpin") {
+                    unpin = Some(query.clone());
+                }
+
+                id_token.pop();
             }
 
-            ui.next_column();
+            for query in recent.iter() {
+                let id_token = ui.push_id(query.as_str());
+
+                if Selectable::new(query).build(ui) {
+                    picked = Some(query.clone());
+                }
+
+                ui.same_line_with_pos(ui.window_content_region_width() - 60.0);
+
+                if ui.small_button("Pin") {
+                    pin = Some(query.clone());
+                }
+
+                ui.same_line();
+
+                if ui.small_button("x") {
+                    remove = Some(query.clone());
+                }
+
+                id_token.pop();
+            }
+        });
+
+        self.history_hovered = hovered;
+
+        if let Some(query) = pin {
+            self.history.pin(&query);
+        }
+
+        if let Some(query) = unpin {
+            self.history.unpin(&query);
+        }
+
+        if let Some(query) = remove {
+            self.history.remove(&query);
+        }
+
+        if let Some(query) = picked {
+            self.query = query;
+            self.history_hovered = false;
+            self.submit(true);
+        }
+    }
+}
+
+/// A sortable table of a tab's results, with an actions column at the end and a button to load more.
+fn results_table<T: Searchable>(
+    ui: &Ui,
+    tab: &mut SearchTab<T>,
+    query: &str,
+    market: Option<&str>,
+    app_state: &mut AppState,
+    columns: &[&str],
+    compare: impl Fn(&T, &T, usize) -> Ordering,
+    draw_row: impl Fn(&Ui, &T, &mut AppState)
+) {
+    if tab.query != query || tab.market.as_deref() != market {
+        if let Some(handler) = app_state.spotify_handler.as_ref() {
+            tab.search(handler, query, market);
+        }
+    }
+
+    tab.update();
+
+    if let Some(request) = tab.request.as_ref() {
+        if let Some(error) = request.error() {
+            ui.text_colored([1.0, 0.0, 0.0, 1.0], error);
+        }
+        else {
+            ui.text("Searching...");
+        }
+    }
+    else if tab.results.is_empty() {
+        ui.text_disabled("No results.");
+        return;
+    }
+
+    let flags = TableFlags::BORDERS | TableFlags::RESIZABLE | TableFlags::SORTABLE | TableFlags::SORT_TRISTATE;
+
+    if let Some(_t) = ui.begin_table_with_flags("Search Results", columns.len() + 1, flags) {
+        for column in columns.iter() {
+            ui.table_setup_column(column);
+        }
+
+        ui.table_setup_column_with(TableColumnSetup {
+            flags: TableColumnFlags::NO_SORT,
+            ..TableColumnSetup::new("Actions")
+        });
+
+        ui.table_headers_row();
+
+        if let Some(data) = ui.table_sort_specs_mut() {
+            let mut sort = tab.sort;
+
+            data.conditional_sort(|specs| {
+                sort = specs.iter().next().and_then(|spec| spec.sort_direction().map(|d| (spec.column_idx(), d)));
+            });
+
+            if sort != tab.sort {
+                tab.sort = sort;
+                tab.order_dirty = true;
+            }
+        }
+
+        tab.update_order(compare);
+
+        for row in 0..tab.order.len() {
+            let idx = tab.order[row];
+            let id_token = ui.push_id(idx as i32);
+
+            draw_row(ui, &tab.results[idx], app_state);
+            id_token.pop();
         }
-    });
+    }
+
+    if tab.has_more && tab.request.is_none() {
+        if ui.button(format!("Load more ({} of {})", tab.results.len(), tab.total)) {
+            if let Some(handler) = app_state.spotify_handler.as_ref() {
+                tab.load_more(handler);
+            }
+        }
+    }
+}
+
+fn artist_names(artists: &[SimplifiedArtist]) -> String {
+    artists.iter().map(|a| a.name.as_str()).collect::<Vec<&str>>().join(", ")
+}
+
+fn followers(artist: &FullArtist) -> u64 {
+    artist.followers.get("total").and_then(|total| total.as_ref()).and_then(|total| total.as_u64()).unwrap_or(0)
+}
+
+fn playlist_length(playlist: &SimplifiedPlaylist) -> u64 {
+    playlist.tracks.get("total").and_then(|total| total.as_u64()).unwrap_or(0)
+}
+
+fn compare_tracks(a: &FullTrack, b: &FullTrack, column: usize) -> Ordering {
+    match column {
+        0 => a.name.cmp(&b.name),
+        1 => artist_names(&a.artists).cmp(&artist_names(&b.artists)),
+        2 => a.album.name.cmp(&b.album.name),
+        3 => a.duration_ms.cmp(&b.duration_ms),
+        _ => Ordering::Equal
+    }
+}
+
+fn track_row(ui: &Ui, track: &FullTrack, app_state: &mut AppState) {
+    let seconds = track.duration_ms / 1000;
+    let minutes = seconds / 60;
+    let seconds = seconds % 60;
+
+    ui.table_next_column();
+    ui.text(&track.name);
+
+    ui.table_next_column();
+    ui.text(artist_names(&track.artists));
 
-    app_state.show_search_window = show_window;
-}
\ No newline at end of file
+    ui.table_next_column();
+
+    if let Some(album_id) = track.album.id.as_ref() {
+        if widgets::album_link(ui, &track.album.name, album_id) {
+            app_state.open_album(album_id.clone());
+        }
+    }
+    else {
+        ui.text(&track.album.name);
+    }
+
+    ui.table_next_column();
+    ui.text(format!("{}:{:02}", minutes, seconds));
+
+    ui.table_next_column();
+
+    if let Some(id) = track.id.as_ref() {
+        if ui.button("Play") {
+            if let (Some(handler), Ok(id)) = (app_state.spotify_handler.as_mut(), SpotifyId::from_base62(id)) {
+                handler.play_single_track(id);
+                app_state.show_player_window = true;
+            }
+        }
+
+        ui.same_line();
+        widgets::like_button(ui, &mut app_state.spotify_handler, id);
+
+        ui.same_line();
+        widgets::add_to_playlist_button(ui, &mut app_state.spotify_handler, id);
+
+        ui.same_line();
+        widgets::seed_button(ui, &mut app_state.recommendation_seeds, RecommendationSeed::Track { id: id.clone(), name: track.name.clone() });
+    }
+}
+
+fn compare_artists(a: &FullArtist, b: &FullArtist, column: usize) -> Ordering {
+    match column {
+        0 => a.name.cmp(&b.name),
+        1 => followers(a).cmp(&followers(b)),
+        2 => a.genres.cmp(&b.genres),
+        _ => Ordering::Equal
+    }
+}
+
+fn artist_row(ui: &Ui, artist: &FullArtist, app_state: &mut AppState) {
+    ui.table_next_column();
+
+    if Selectable::new(&artist.name).build(ui) {
+        app_state.open_artist(artist.id.clone(), artist.name.clone());
+    }
+
+    ui.table_next_column();
+    ui.text(format!("{}", followers(artist)));
+
+    ui.table_next_column();
+    ui.text(artist.genres.join(", "));
+
+    ui.table_next_column();
+    widgets::follow_button(ui, &mut app_state.spotify_handler, &artist.id);
+
+    ui.same_line();
+    widgets::seed_button(ui, &mut app_state.recommendation_seeds, RecommendationSeed::Artist { id: artist.id.clone(), name: artist.name.clone() });
+}
+
+fn compare_albums(a: &SimplifiedAlbum, b: &SimplifiedAlbum, column: usize) -> Ordering {
+    match column {
+        0 => a.name.cmp(&b.name),
+        1 => artist_names(&a.artists).cmp(&artist_names(&b.artists)),
+        2 => a.release_date.cmp(&b.release_date),
+        3 => a.album_type.cmp(&b.album_type),
+        _ => Ordering::Equal
+    }
+}
+
+fn album_row(ui: &Ui, album: &SimplifiedAlbum, app_state: &mut AppState) {
+    ui.table_next_column();
+
+    if let Some(album_id) = album.id.as_ref() {
+        if widgets::album_link(ui, &album.name, album_id) {
+            app_state.open_album(album_id.clone());
+        }
+    }
+    else {
+        ui.text(&album.name);
+    }
+
+    ui.table_next_column();
+    ui.text(artist_names(&album.artists));
+
+    ui.table_next_column();
+    ui.text(album.release_date.as_deref().unwrap_or(""));
+
+    ui.table_next_column();
+    ui.text(album.album_type.as_deref().unwrap_or(""));
+
+    ui.table_next_column();
+
+    if let Some(album_id) = album.id.as_ref() {
+        widgets::save_album_button(ui, &mut app_state.spotify_handler, album_id);
+    }
+}
+
+fn compare_playlists(a: &SimplifiedPlaylist, b: &SimplifiedPlaylist, column: usize) -> Ordering {
+    match column {
+        0 => a.name.cmp(&b.name),
+        1 => a.owner.display_name.cmp(&b.owner.display_name),
+        2 => playlist_length(a).cmp(&playlist_length(b)),
+        _ => Ordering::Equal
+    }
+}
+
+fn playlist_row(ui: &Ui, playlist: &SimplifiedPlaylist, app_state: &mut AppState) {
+    ui.table_next_column();
+    ui.text(&playlist.name);
+
+    ui.table_next_column();
+    ui.text(playlist.owner.display_name.as_ref().unwrap_or(&playlist.owner.id));
+
+    ui.table_next_column();
+    ui.text(format!("{}", playlist_length(playlist)));
+
+    ui.table_next_column();
+
+    if ui.button("Play") {
+        if let Some(handler) = app_state.spotify_handler.as_mut() {
+            handler.play_playlist(playlist.id.clone());
+            app_state.show_player_window = true;
+        }
+    }
+}
+
+fn compare_shows(a: &SimplifiedShow, b: &SimplifiedShow, column: usize) -> Ordering {
+    match column {
+        0 => a.name.cmp(&b.name),
+        1 => a.publisher.cmp(&b.publisher),
+        _ => Ordering::Equal
+    }
+}
+
+// Shows are only listed, there's nothing to play until an episode is picked.
+fn show_row(ui: &Ui, show: &SimplifiedShow, _app_state: &mut AppState) {
+    ui.table_next_column();
+    ui.text(&show.name);
+
+    ui.table_next_column();
+    ui.text(&show.publisher);
+
+    ui.table_next_column();
+}
+
+fn compare_episodes(a: &SimplifiedEpisode, b: &SimplifiedEpisode, column: usize) -> Ordering {
+    match column {
+        0 => a.name.cmp(&b.name),
+        1 => a.release_date.cmp(&b.release_date),
+        2 => a.duration_ms.cmp(&b.duration_ms),
+        _ => Ordering::Equal
+    }
+}
+
+fn episode_row(ui: &Ui, episode: &SimplifiedEpisode, app_state: &mut AppState) {
+    let seconds = episode.duration_ms / 1000;
+    let minutes = seconds / 60;
+    let seconds = seconds % 60;
+
+    ui.table_next_column();
+    ui.text(&episode.name);
+
+    ui.table_next_column();
+    ui.text(&episode.release_date);
+
+    ui.table_next_column();
+    ui.text(format!("{}:{:02}", minutes, seconds));
+
+    ui.table_next_column();
+
+    if ui.button("Play") {
+        if let (Some(handler), Ok(id)) = (app_state.spotify_handler.as_mut(), SpotifyId::from_uri(&episode.uri)) {
+            handler.play_single_track(id);
+            app_state.show_player_window = true;
+        }
+    }
+}
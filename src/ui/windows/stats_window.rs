@@ -0,0 +1,268 @@
+use imgui::*;
+
+use crate::ui::AppState;
+use crate::ui::widgets;
+use crate::spotify::SpotifyHandler;
+use crate::spotify::player::PlayContext;
+use crate::spotify::api::cache::{ArtistInfo, TrackInfo};
+use crate::spotify::api::executor::RequestHandle;
+use crate::spotify::api::provider::{PlayedTrack, TopTerm};
+
+pub struct StatsWindow {
+    recently_played: Vec<PlayedTrack>,
+    recently_played_request: Option<RequestHandle<Vec<PlayedTrack>>>,
+
+    term: TopTerm,
+
+    top_tracks: Vec<TrackInfo>,
+    top_tracks_request: Option<RequestHandle<Vec<TrackInfo>>>,
+
+    top_artists: Vec<ArtistInfo>,
+    top_artists_request: Option<RequestHandle<Vec<ArtistInfo>>>,
+
+    loaded: bool
+}
+
+impl StatsWindow {
+    pub fn init() -> StatsWindow {
+        StatsWindow {
+            recently_played: Vec::new(),
+            recently_played_request: None,
+
+            term: TopTerm::Medium,
+
+            top_tracks: Vec::new(),
+            top_tracks_request: None,
+
+            top_artists: Vec::new(),
+            top_artists_request: None,
+
+            loaded: false
+        }
+    }
+
+    fn load(&mut self, handler: &SpotifyHandler) {
+        self.recently_played_request = Some(handler.get_recently_played());
+        self.load_top(handler);
+
+        self.loaded = true;
+    }
+
+    fn load_top(&mut self, handler: &SpotifyHandler) {
+        self.top_tracks_request = Some(handler.get_top_tracks(self.term));
+        self.top_artists_request = Some(handler.get_top_artists(self.term));
+    }
+
+    fn update_requests(&mut self) {
+        if let Some(played) = self.recently_played_request.as_ref().and_then(|r| r.result()) {
+            self.recently_played = played;
+            self.recently_played_request = None;
+        }
+
+        if let Some(tracks) = self.top_tracks_request.as_ref().and_then(|r| r.result()) {
+            self.top_tracks = tracks;
+            self.top_tracks_request = None;
+        }
+
+        if let Some(artists) = self.top_artists_request.as_ref().and_then(|r| r.result()) {
+            self.top_artists = artists;
+            self.top_artists_request = None;
+        }
+    }
+
+    pub fn draw(&mut self, ui: &Ui, app_state: &mut AppState) {
+        let mut show_window = app_state.show_stats_window;
+
+        if !self.loaded {
+            if let Some(handler) = app_state.spotify_handler.as_ref() {
+                self.load(handler);
+            }
+        }
+
+        self.update_requests();
+
+        Window::new("Your Stats").size([700.0, 500.0], Condition::FirstUseEver).opened(&mut show_window).build(ui, || {
+            if ui.button("Refresh") {
+                if let Some(handler) = app_state.spotify_handler.as_ref() {
+                    self.load(handler);
+                }
+            }
+
+            let mut term = self.term;
+
+            for option in TopTerm::ALL.iter() {
+                ui.same_line();
+                ui.radio_button(option.label(), &mut term, *option);
+            }
+
+            if term != self.term {
+                self.term = term;
+
+                if let Some(handler) = app_state.spotify_handler.as_ref() {
+                    self.load_top(handler);
+                }
+            }
+
+            TabBar::new("Stats Tabs").build(ui, || {
+                TabItem::new("Recently Played").build(ui, || {
+                    widgets::request_status(ui, &self.recently_played_request, "recently played tracks");
+
+                    let tracks: Vec<TrackInfo> = self.recently_played.iter().map(|p| p.track.clone()).collect();
+                    let played_at: Vec<&str> = self.recently_played.iter().map(|p| p.played_at.as_str()).collect();
+
+                    track_list(ui, "Recently Played", &tracks, Some(played_at.as_slice()), app_state);
+                });
+
+                TabItem::new("Top Tracks").build(ui, || {
+                    widgets::request_status(ui, &self.top_tracks_request, "top tracks");
+
+                    let name = format!("Top Tracks ({})", self.term.label());
+                    track_list(ui, &name, &self.top_tracks, None, app_state);
+                });
+
+                TabItem::new("Top Artists").build(ui, || {
+                    widgets::request_status(ui, &self.top_artists_request, "top artists");
+
+                    let name = format!("Top Artists ({})", self.term.label());
+                    artist_list(ui, &name, &self.top_artists, app_state);
+                });
+            });
+        });
+
+        app_state.show_stats_window = show_window;
+    }
+}
+
+/// A numbered table of tracks that can be played from any of them, or saved as a playlist called `name`.
+/// `played_at` replaces the duration column for recently played tracks.
+fn track_list(ui: &Ui, name: &str, tracks: &[TrackInfo], played_at: Option<&[&str]>, app_state: &mut AppState) {
+    if tracks.is_empty() {
+        return;
+    }
+
+    let mut play = None;
+    let mut open_album = None;
+
+    if ui.button("Play all") {
+        play = Some(None);
+    }
+
+    ui.same_line();
+
+    if ui.button("Save as playlist") {
+        if let Some(handler) = app_state.spotify_handler.as_mut() {
+            handler.save_as_playlist(name.to_string(), tracks.iter().map(|t| t.id().clone()).collect());
+        }
+    }
+
+    let token = ui.begin_table_header_with_flags(
+        name,
+        [
+            TableColumnSetup::new("#"),
+            TableColumnSetup::new("Title"),
+            TableColumnSetup::new("Artist"),
+            TableColumnSetup::new("Album"),
+            TableColumnSetup::new(if played_at.is_some() { "Played" } else { "Duration" }),
+            TableColumnSetup::new("Actions")
+        ],
+        TableFlags::BORDERS | TableFlags::RESIZABLE
+    );
+
+    if let Some(_t) = token {
+        for (idx, track) in tracks.iter().enumerate() {
+            // The same track can be in the recently played list more than once.
+            let id_token = ui.push_id(idx as i32);
+
+            ui.table_next_column();
+            ui.text(format!("{}", idx + 1));
+
+            ui.table_next_column();
+            ui.text(track.name());
+
+            ui.table_next_column();
+            ui.text(track.artists().join(", "));
+
+            ui.table_next_column();
+
+            if widgets::album_link(ui, track.album_name(), track.album()) {
+                open_album = Some(track.album().clone());
+            }
+
+            ui.table_next_column();
+
+            if let Some(played_at) = played_at {
+                ui.text(played_at[idx]);
+            }
+            else {
+                let seconds = track.duration() / 1000;
+                ui.text(format!("{}:{:02}", seconds / 60, seconds % 60));
+            }
+
+            ui.table_next_column();
+
+            if ui.button("Play") {
+                play = Some(Some(track.id().clone()));
+            }
+
+            ui.same_line();
+            widgets::like_button(ui, &mut app_state.spotify_handler, track.id());
+
+            ui.same_line();
+            widgets::add_to_playlist_button(ui, &mut app_state.spotify_handler, track.id());
+
+            id_token.pop();
+        }
+    }
+
+    if let Some(start) = play {
+        if let Some(handler) = app_state.spotify_handler.as_mut() {
+            handler.play_tracks(PlayContext::Tracks, tracks, start.as_deref());
+            app_state.show_player_window = true;
+        }
+    }
+
+    if let Some(album_id) = open_album {
+        app_state.open_album(album_id);
+    }
+}
+
+/// Artists are played and saved as a mix of their top tracks.
+fn artist_list(ui: &Ui, name: &str, artists: &[ArtistInfo], app_state: &mut AppState) {
+    if artists.is_empty() {
+        return;
+    }
+
+    let artist_ids: Vec<String> = artists.iter().map(|a| a.id().clone()).collect();
+    let mut open_artist = None;
+
+    if ui.button("Play all") {
+        if let Some(handler) = app_state.spotify_handler.as_mut() {
+            handler.play_artists(artist_ids.clone());
+            app_state.show_player_window = true;
+        }
+    }
+
+    ui.same_line();
+
+    if ui.button("Save as playlist") {
+        if let Some(handler) = app_state.spotify_handler.as_mut() {
+            handler.save_artists_as_playlist(name.to_string(), artist_ids);
+        }
+    }
+
+    for (idx, artist) in artists.iter().enumerate() {
+        ui.text(format!("{}.", idx + 1));
+        ui.same_line_with_pos(40.0);
+
+        if Selectable::new(format!("{}##{}", artist.name(), artist.id())).build(ui) {
+            open_artist = Some((artist.id().clone(), artist.name().clone()));
+        }
+
+        ui.same_line_with_pos(300.0);
+        ui.text_disabled(artist.genres().join(", "));
+    }
+
+    if let Some((artist_id, artist_name)) = open_artist {
+        app_state.open_artist(artist_id, artist_name);
+    }
+}